@@ -0,0 +1,530 @@
+use std::rc::Rc;
+use ttsmagic_types::{
+    frontend_to_server::FrontendToServerMessage as F2SMsg, server_to_frontend as s2f,
+    server_to_frontend::ServerToFrontendMessage as S2FMsg, Card, CardPrinting, DeckCard,
+    DeckDetail, DeckEdit, DeckId, Pile,
+};
+use uuid::Uuid;
+use yew::prelude::*;
+
+use crate::remote_resource::RemoteResource;
+
+/// Card types in the order decklists usually group them. A card goes in the
+/// first group its front face's type line matches.
+const TYPE_GROUPS: &[(&str, &str)] = &[
+    ("Creature", "Creatures"),
+    ("Planeswalker", "Planeswalkers"),
+    ("Battle", "Battles"),
+    ("Instant", "Instants"),
+    ("Sorcery", "Sorceries"),
+    ("Artifact", "Artifacts"),
+    ("Enchantment", "Enchantments"),
+    ("Land", "Lands"),
+];
+
+fn type_group(card: &DeckCard) -> &'static str {
+    let front = card.type_line.split(" // ").next().unwrap_or("");
+    let types = front.split(" — ").next().unwrap_or("");
+    for (card_type, label) in TYPE_GROUPS.iter() {
+        if types.split_whitespace().any(|t| t == *card_type) {
+            return label;
+        }
+    }
+    "Other"
+}
+
+fn pile_cards(detail: &DeckDetail, pile: Pile) -> &[DeckCard] {
+    match pile {
+        Pile::Commander => &detail.commanders,
+        Pile::Companion => &detail.companions,
+        Pile::MainDeck => &detail.main_deck,
+        Pile::Sideboard => &detail.sideboard,
+        Pile::Maybeboard => &detail.maybeboard,
+    }
+}
+
+/// The printings of one deck entry, shown while picking another one.
+struct PrintingPicker {
+    card: Uuid,
+    pile: Pile,
+    oracle_id: Uuid,
+    printings: RemoteResource<Vec<CardPrinting>>,
+}
+
+pub enum Msg {
+    AddCard,
+    AddNamedCard(String),
+    Edit(DeckEdit),
+    FromServer(Rc<S2FMsg>),
+    PickPrinting(Uuid),
+    Search,
+    SetNewCardCount(String),
+    SetNewCardName(String),
+    SetNewCardPile(Pile),
+    TogglePrintings {
+        card: Uuid,
+        pile: Pile,
+        oracle_id: Uuid,
+    },
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub socket: Rc<crate::ws::WebSocket>,
+    pub deck_id: DeckId,
+}
+
+/// The card list of one deck, loaded when the component is first shown and
+/// again whenever the deck is reloaded or edited.
+pub struct DeckDetailView {
+    link: ComponentLink<Self>,
+    socket: Rc<crate::ws::WebSocket>,
+    deck_id: DeckId,
+    detail: RemoteResource<DeckDetail>,
+    new_card_name: String,
+    new_card_count: u8,
+    new_card_pile: Pile,
+    /// The last search sent, so results for other views' searches are
+    /// ignored.
+    search_query: Option<String>,
+    search_results: Option<Result<Vec<Card>, String>>,
+    printing_picker: Option<PrintingPicker>,
+}
+
+impl Component for DeckDetailView {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        props
+            .socket
+            .register_message_callback(link.callback(Msg::FromServer));
+        props
+            .socket
+            .send(F2SMsg::GetDeck { id: props.deck_id })
+            .unwrap();
+        Self {
+            link,
+            socket: props.socket,
+            deck_id: props.deck_id,
+            detail: RemoteResource::Loading,
+            new_card_name: String::new(),
+            new_card_count: 1,
+            new_card_pile: Pile::MainDeck,
+            search_query: None,
+            search_results: None,
+            printing_picker: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if !Rc::ptr_eq(&self.socket, &props.socket) || self.deck_id != props.deck_id {
+            self.socket = props.socket;
+            self.deck_id = props.deck_id;
+            self.detail = RemoteResource::Loading;
+            self.socket
+                .send(F2SMsg::GetDeck { id: self.deck_id })
+                .unwrap();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::AddCard => {
+                let name = self.new_card_name.trim().to_string();
+                self.update(Msg::AddNamedCard(name))
+            }
+            Msg::AddNamedCard(name) => {
+                if name.is_empty() {
+                    return false;
+                }
+                let edit = DeckEdit::AddCard {
+                    name,
+                    pile: self.new_card_pile,
+                    count: self.new_card_count,
+                };
+                self.socket
+                    .send(F2SMsg::EditDeck {
+                        id: self.deck_id,
+                        edit,
+                    })
+                    .unwrap();
+                self.new_card_name = String::new();
+                self.new_card_count = 1;
+                self.search_query = None;
+                self.search_results = None;
+                true
+            }
+            Msg::Edit(edit) => {
+                self.socket
+                    .send(F2SMsg::EditDeck {
+                        id: self.deck_id,
+                        edit,
+                    })
+                    .unwrap();
+                false
+            }
+            Msg::FromServer(msg_rc) => match &*msg_rc {
+                S2FMsg::Printings {
+                    oracle_id,
+                    printings,
+                } => match self.printing_picker.as_mut() {
+                    Some(picker) if picker.oracle_id == *oracle_id => {
+                        picker.printings = RemoteResource::Loaded(printings.clone());
+                        true
+                    }
+                    _ => false,
+                },
+                S2FMsg::CardSearchResults { query, results }
+                    if self.search_query.as_ref() == Some(query) =>
+                {
+                    self.search_results = Some(results.clone());
+                    true
+                }
+                S2FMsg::DeckDetail(detail) if detail.deck.id == self.deck_id => {
+                    self.detail = RemoteResource::Loaded(detail.clone());
+                    true
+                }
+                S2FMsg::Notification(s2f::Notification::DeckParsed { deck_id, .. })
+                | S2FMsg::Notification(s2f::Notification::DeckEdited { deck_id, .. })
+                    if *deck_id == self.deck_id =>
+                {
+                    self.socket
+                        .send(F2SMsg::GetDeck { id: self.deck_id })
+                        .unwrap();
+                    false
+                }
+                _ => false,
+            },
+            Msg::PickPrinting(printing) => {
+                if let Some(picker) = self.printing_picker.take() {
+                    let edit = DeckEdit::SetPrinting {
+                        card: picker.card,
+                        pile: picker.pile,
+                        printing,
+                    };
+                    self.update(Msg::Edit(edit));
+                }
+                true
+            }
+            Msg::Search => {
+                let query = self.new_card_name.trim().to_string();
+                if query.is_empty() {
+                    return false;
+                }
+                self.search_query = Some(query.clone());
+                self.socket.send(F2SMsg::SearchCards { query }).unwrap();
+                false
+            }
+            Msg::SetNewCardCount(count) => {
+                if let Ok(count) = count.parse() {
+                    self.new_card_count = count;
+                }
+                true
+            }
+            Msg::SetNewCardName(name) => {
+                self.new_card_name = name;
+                true
+            }
+            Msg::SetNewCardPile(pile) => {
+                self.new_card_pile = pile;
+                false
+            }
+            Msg::TogglePrintings {
+                card,
+                pile,
+                oracle_id,
+            } => {
+                let already_open = self
+                    .printing_picker
+                    .as_ref()
+                    .map_or(false, |p| p.card == card && p.pile == pile);
+                if already_open {
+                    self.printing_picker = None;
+                } else {
+                    self.printing_picker = Some(PrintingPicker {
+                        card,
+                        pile,
+                        oracle_id,
+                        printings: RemoteResource::Loading,
+                    });
+                    self.socket
+                        .send(F2SMsg::GetPrintings { oracle_id })
+                        .unwrap();
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        match self.detail.as_ref() {
+            RemoteResource::Loading => html! { <p> { "Loading…" } </p> },
+            RemoteResource::Error(e) => html! { <p> { format!("Error: {}", e) } </p> },
+            RemoteResource::Loaded(detail) => html! {
+                <div class="deck-detail">
+                    { for Pile::ALL.iter().map(|pile| self.view_pile(*pile, pile_cards(detail, *pile))) }
+                    { self.view_add_card() }
+                    { self.view_search_results() }
+                </div>
+            },
+        }
+    }
+}
+
+impl DeckDetailView {
+    fn view_pile_option(pile: Pile, current: Pile) -> Html {
+        html! {
+            <option value=pile.slug() selected=(pile == current)> { pile.label() } </option>
+        }
+    }
+
+    /// Buttons to change the count of a card, move it or take it out.
+    fn view_card_edit(&self, card: &DeckCard, pile: Pile) -> Html {
+        let (id, count) = (card.scryfall_id, card.count);
+        let set_count = move |count: u8| {
+            Msg::Edit(DeckEdit::SetCount {
+                card: id,
+                pile,
+                count,
+            })
+        };
+        let move_to = self.link.batch_callback(move |e: ChangeData| match e {
+            ChangeData::Select(select) => Pile::from_slug(&select.value())
+                .filter(|to| *to != pile)
+                .map(|to| {
+                    Msg::Edit(DeckEdit::MoveCard {
+                        card: id,
+                        from: pile,
+                        to,
+                    })
+                })
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        });
+        let count_buttons = html! {
+            <>
+                <button title="One fewer" onclick=self.link.callback(move |_| set_count(count - 1))>
+                    { "\u{2212}" }
+                </button>
+                <button title="One more" disabled=pile.is_single() onclick=self.link.callback(move |_| set_count(count.saturating_add(1)))>
+                    { "+" }
+                </button>
+            </>
+        };
+        let printings_button = match card.oracle_id {
+            Some(oracle_id) => html! {
+                <button title="Choose another printing" onclick=self.link.callback(move |_| Msg::TogglePrintings { card: id, pile, oracle_id })>
+                    { "Printing" }
+                </button>
+            },
+            None => html! { <> </> },
+        };
+        html! {
+            <span class="deck-card-edit">
+                { count_buttons }
+                { printings_button }
+                <select title="Move to another pile" onchange=move_to>
+                    { for Pile::ALL.iter().map(|p| Self::view_pile_option(*p, pile)) }
+                </select>
+                <button title="Remove" onclick=self.link.callback(move |_| set_count(0))>
+                    { "\u{2715}" }
+                </button>
+            </span>
+        }
+    }
+
+    fn view_card(&self, card: &DeckCard, pile: Pile) -> Html {
+        let image = match &card.image_url {
+            Some(url) => html! { <img src=url.to_string() alt="" loading="lazy" /> },
+            None => html! { <> </> },
+        };
+        let edit = self.view_card_edit(card, pile);
+        let printings = match &self.printing_picker {
+            Some(picker) if picker.card == card.scryfall_id && picker.pile == pile => {
+                self.view_printings(picker)
+            }
+            _ => html! { <> </> },
+        };
+        html! {
+            <li title=card.type_line.clone()>
+                { image }
+                { format!("{} {} ", card.count, card.name) }
+                <span class="deck-card-printing">
+                    { format!("({}) {}", card.set_code.to_uppercase(), card.collector_number) }
+                </span>
+                { edit }
+                { printings }
+            </li>
+        }
+    }
+
+    fn view_printing(&self, printing: &CardPrinting, current: Uuid) -> Html {
+        let id = printing.scryfall_id;
+        let image = match &printing.image_url {
+            Some(url) => html! { <img src=url.to_string() alt="" loading="lazy" /> },
+            None => html! { <> </> },
+        };
+        let mut details = vec![printing.set_name.clone()];
+        if let Some(released_at) = printing.released_at {
+            details.push(released_at.format("%Y").to_string());
+        }
+        if !printing.frame.is_empty() {
+            details.push(format!("{} frame", printing.frame));
+        }
+        if printing.full_art {
+            details.push("full art".to_string());
+        }
+        if printing.borderless {
+            details.push("borderless".to_string());
+        }
+        html! {
+            <li class=if id == current { "current" } else { "" } title=details.join(", ") onclick=self.link.callback(move |_| Msg::PickPrinting(id))>
+                { image }
+                { format!("({}) {}", printing.set_code.to_uppercase(), printing.collector_number) }
+            </li>
+        }
+    }
+
+    fn view_printings(&self, picker: &PrintingPicker) -> Html {
+        match picker.printings.as_ref() {
+            RemoteResource::Loading => html! { <p> { "Loading…" } </p> },
+            RemoteResource::Error(e) => html! { <p> { format!("Error: {}", e) } </p> },
+            RemoteResource::Loaded(printings) => html! {
+                <ul class="deck-printings">
+                    { for printings.iter().map(|p| self.view_printing(p, picker.card)) }
+                </ul>
+            },
+        }
+    }
+
+    fn view_add_card(&self) -> Html {
+        let current = self.new_card_pile;
+        let onchange = self.link.batch_callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => Pile::from_slug(&select.value())
+                .map(Msg::SetNewCardPile)
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        });
+        let count_input = html! {
+            <input
+                type="number"
+                min="1"
+                max="99"
+                value=self.new_card_count.to_string()
+                oninput=self.link.callback(|e: InputData| Msg::SetNewCardCount(e.value))
+            />
+        };
+        let name_input = html! {
+            <input
+                type="text"
+                placeholder="Card name or search"
+                value=&self.new_card_name
+                oninput=self.link.callback(|e: InputData| Msg::SetNewCardName(e.value))
+            />
+        };
+        html! {
+            <div class="deck-add-card">
+                { count_input }
+                { name_input }
+                <select onchange=onchange>
+                    { for Pile::ALL.iter().map(|p| Self::view_pile_option(*p, current)) }
+                </select>
+                <button
+                    disabled=self.new_card_name.trim().is_empty()
+                    onclick=self.link.callback(|_| Msg::AddCard)
+                >
+                    { "Add" }
+                </button>
+                <button
+                    title="Search with Scryfall's syntax, like \"t:goblin c:r cmc<=2\""
+                    disabled=self.new_card_name.trim().is_empty()
+                    onclick=self.link.callback(|_| Msg::Search)
+                >
+                    { "Search" }
+                </button>
+            </div>
+        }
+    }
+
+    fn view_search_result(&self, card: &Card) -> Html {
+        let name = card.name.clone();
+        let image = match &card.image_urls {
+            Some(urls) => html! { <img src=urls.small.to_string() alt="" loading="lazy" /> },
+            None => html! { <> </> },
+        };
+        let type_line = card
+            .faces
+            .iter()
+            .map(|face| face.type_line.as_str())
+            .collect::<Vec<_>>()
+            .join(" // ");
+        html! {
+            <li title=type_line>
+                { image }
+                { format!("{} ", card.name) }
+                <button onclick=self.link.callback(move |_| Msg::AddNamedCard(name.clone()))>
+                    { "Add" }
+                </button>
+            </li>
+        }
+    }
+
+    fn view_search_results(&self) -> Html {
+        match &self.search_results {
+            None => html! { <> </> },
+            Some(Err(e)) => html! { <p class="deck-search-error"> { e } </p> },
+            Some(Ok(cards)) if cards.is_empty() => html! { <p> { "No cards found." } </p> },
+            Some(Ok(cards)) => html! {
+                <ul class="deck-cards deck-search-results">
+                    { for cards.iter().map(|card| self.view_search_result(card)) }
+                </ul>
+            },
+        }
+    }
+
+    fn view_type_group(&self, label: &str, pile: Pile, cards: &[&DeckCard]) -> Html {
+        let count: u32 = cards.iter().map(|c| u32::from(c.count)).sum();
+        html! {
+            <>
+                <h5> { format!("{} ({})", label, count) } </h5>
+                <ul class="deck-cards">
+                    { for cards.iter().map(|card| self.view_card(card, pile)) }
+                </ul>
+            </>
+        }
+    }
+
+    fn view_pile(&self, pile: Pile, cards: &[DeckCard]) -> Html {
+        if cards.is_empty() {
+            return html! { <> </> };
+        }
+        let count: u32 = cards.iter().map(|c| u32::from(c.count)).sum();
+        let mut groups: Vec<(&str, Vec<&DeckCard>)> = vec![];
+        for card in cards {
+            let group = type_group(card);
+            match groups.iter_mut().find(|(label, _)| *label == group) {
+                Some((_, group_cards)) => group_cards.push(card),
+                None => groups.push((group, vec![card])),
+            }
+        }
+        let group_order = |label: &str| {
+            TYPE_GROUPS
+                .iter()
+                .position(|(_, l)| *l == label)
+                .unwrap_or(TYPE_GROUPS.len())
+        };
+        groups.sort_by_key(|(label, _)| group_order(label));
+        html! {
+            <section class="deck-detail-pile">
+                <h4> { format!("{} ({})", pile.label(), count) } </h4>
+                { for groups.iter().map(|(label, cards)| self.view_type_group(label, pile, cards)) }
+            </section>
+        }
+    }
+}
@@ -1,11 +1,18 @@
-use std::{num::NonZeroU16, rc::Rc};
+use std::{
+    num::{NonZeroU16, NonZeroU8},
+    rc::Rc,
+    str::FromStr,
+};
 use ttsmagic_types::{
     frontend_to_server::FrontendToServerMessage as F2SMsg, server_to_frontend as s2f,
-    server_to_frontend::ServerToFrontendMessage as S2FMsg, Deck, DeckId,
+    server_to_frontend::ServerToFrontendMessage as S2FMsg, Deck, DeckDiff, DeckId, DeckRevision,
+    DeckVersion, ExportMode, Format, LayoutPreset, LegalityReport, PileDiff, RenderOptions,
+    TokenCopies,
 };
+use wasm_bindgen::{JsCast, JsValue};
 use yew::prelude::*;
 
-use crate::remote_resource::RemoteResource;
+use crate::{deck_detail::DeckDetailView, remote_resource::RemoteResource};
 
 pub enum DeckStatus {
     Loading,
@@ -19,6 +26,13 @@ pub enum DeckStatus {
 struct DeckInfo {
     deck: Deck,
     status: DeckStatus,
+    /// Only loaded once the deck's history is opened.
+    revisions: Option<Vec<DeckRevision>>,
+    /// The last comparison of a past revision against the current list.
+    diff: Option<DeckDiff>,
+    legality: Option<LegalityReport>,
+    /// The card list is only loaded once it's opened.
+    show_cards: bool,
 }
 
 impl DeckInfo {
@@ -113,8 +127,83 @@ impl DeckInfo {
 
 pub enum Msg {
     DeleteDeck(DeckId),
+    DiffRevision(DeckId, u32),
     FromServer(Rc<S2FMsg>),
+    GetRevisions(DeckId),
     RebuildDeck(DeckId),
+    RenderSavedDeck(DeckId),
+    CopyShareLink(String),
+    RestoreRevision(DeckId, u32),
+    SetRenderOptions(DeckId, RenderOptions),
+    SetShared(DeckId, bool),
+    ShowCards(DeckId),
+    ValidateDeck(DeckId, Format),
+}
+
+/// Calls `navigator.clipboard.writeText` by name, since web-sys only has the
+/// Clipboard API behind an unstable flag.
+pub(crate) fn copy_to_clipboard(text: &str) -> Result<(), JsValue> {
+    let window =
+        web_sys::window().ok_or_else(|| JsValue::from_str("No window property available"))?;
+    let navigator = js_sys::Reflect::get(&window, &JsValue::from_str("navigator"))?;
+    let clipboard = js_sys::Reflect::get(&navigator, &JsValue::from_str("clipboard"))?;
+    let write_text: js_sys::Function =
+        js_sys::Reflect::get(&clipboard, &JsValue::from_str("writeText"))?.dyn_into()?;
+    write_text.call1(&clipboard, &JsValue::from_str(text))?;
+    Ok(())
+}
+
+fn share_link(token: &str) -> Result<String, JsValue> {
+    let window =
+        web_sys::window().ok_or_else(|| JsValue::from_str("No window property available"))?;
+    let origin = window.location().origin()?;
+    Ok(format!("{}/shared/{}.json", origin, token))
+}
+
+fn token_copies_value(copies: TokenCopies) -> String {
+    match copies {
+        TokenCopies::Single => "1".to_string(),
+        TokenCopies::Fixed(n) => n.to_string(),
+        TokenCopies::Estimated => "estimate".to_string(),
+    }
+}
+
+fn parse_token_copies(value: &str) -> Option<TokenCopies> {
+    match value {
+        "estimate" => Some(TokenCopies::Estimated),
+        "1" => Some(TokenCopies::Single),
+        other => NonZeroU8::from_str(other).ok().map(TokenCopies::Fixed),
+    }
+}
+
+fn set_token_copies(options: &mut RenderOptions, value: &str) -> bool {
+    match parse_token_copies(value) {
+        Some(token_copies) => {
+            options.token_copies = token_copies;
+            true
+        }
+        None => false,
+    }
+}
+
+fn set_export_mode(options: &mut RenderOptions, value: &str) -> bool {
+    match ExportMode::from_slug(value) {
+        Some(export_mode) => {
+            options.export_mode = export_mode;
+            true
+        }
+        None => false,
+    }
+}
+
+fn set_layout(options: &mut RenderOptions, value: &str) -> bool {
+    match LayoutPreset::from_slug(value) {
+        Some(layout) => {
+            options.layout = layout;
+            true
+        }
+        None => false,
+    }
 }
 
 #[derive(Clone, Properties)]
@@ -158,6 +247,12 @@ impl Component for DeckList {
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         let should_render = match msg {
+            Msg::CopyShareLink(token) => {
+                if let Err(e) = share_link(&token).and_then(|link| copy_to_clipboard(&link)) {
+                    error!("Failed to copy share link: {:?}", e);
+                }
+                false
+            }
             Msg::DeleteDeck(deck_id) => {
                 self.socket
                     .send(F2SMsg::DeleteDeck { id: deck_id })
@@ -165,16 +260,64 @@ impl Component for DeckList {
                 true
             }
             Msg::FromServer(msg_rc) => match &*msg_rc {
+                S2FMsg::ApiTokenCreated { .. }
+                | S2FMsg::ApiTokens { .. }
+                | S2FMsg::CardSearchResults { .. }
+                | S2FMsg::Printings { .. } => false,
+                S2FMsg::DeckDetail(_) => false,
                 S2FMsg::DeckList { decks } => {
                     let mut deck_infos = Vec::with_capacity(decks.len());
                     for deck in decks.into_iter().cloned() {
                         let status = DeckStatus::Complete;
-                        deck_infos.push(DeckInfo { deck, status });
+                        deck_infos.push(DeckInfo {
+                            deck,
+                            status,
+                            revisions: None,
+                            diff: None,
+                            legality: None,
+                            show_cards: false,
+                        });
                     }
                     self.decks = RemoteResource::Loaded(deck_infos);
                     true
                 }
+                S2FMsg::DeckRevisions { deck_id, revisions } => match &mut self.decks {
+                    RemoteResource::Loaded(ref mut decks) => {
+                        for di in decks.iter_mut() {
+                            if &di.deck.id == deck_id {
+                                di.revisions = Some(revisions.clone());
+                                break;
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                },
+                S2FMsg::DeckDiff(diff) => match &mut self.decks {
+                    RemoteResource::Loaded(ref mut decks) => {
+                        for di in decks.iter_mut() {
+                            if di.deck.id == diff.new.deck_id {
+                                di.diff = Some(diff.clone());
+                                break;
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                },
                 S2FMsg::FatalError(s2f::Error { .. }) => false,
+                S2FMsg::LegalityReport { deck_id, report } => match &mut self.decks {
+                    RemoteResource::Loaded(ref mut decks) => {
+                        for di in decks.iter_mut() {
+                            if &di.deck.id == deck_id {
+                                di.legality = Some(report.clone());
+                                break;
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                },
                 S2FMsg::Notification(s2f::Notification::DeckDeleted { deck_id }) => {
                     match &mut self.decks {
                         RemoteResource::Loaded(ref mut decks) => {
@@ -197,17 +340,27 @@ impl Component for DeckList {
                     RemoteResource::Loaded(ref mut decks) => {
                         let mut new_deck_info = Some(DeckInfo {
                             status: DeckStatus::Loading,
+                            revisions: None,
+                            diff: None,
+                            legality: None,
+                            show_cards: false,
                             deck: Deck {
                                 id: *deck_id,
                                 title: title.clone(),
                                 url: url.clone(),
                                 rendered: false,
                                 color_identity: Default::default(),
+                                render_options: Default::default(),
+                                thumbnail_url: None,
+                                share_token: None,
+                                modified: false,
                             },
                         });
                         for di in decks.iter_mut() {
                             if &di.deck.id == deck_id {
-                                if let Some(new_di) = new_deck_info.take() {
+                                if let Some(mut new_di) = new_deck_info.take() {
+                                    new_di.deck.share_token = di.deck.share_token.take();
+                                    new_di.show_cards = di.show_cards;
                                     *di = new_di;
                                 }
                             }
@@ -232,6 +385,80 @@ impl Component for DeckList {
                                 di.deck.title = title.clone();
                                 di.deck.url = url.clone();
                                 di.deck.color_identity = color_identity.clone();
+                                di.deck.modified = false;
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                },
+                S2FMsg::Notification(s2f::Notification::DeckEdited {
+                    deck_id,
+                    color_identity,
+                }) => match &mut self.decks {
+                    RemoteResource::Loaded(ref mut decks) => {
+                        for di in decks.iter_mut() {
+                            if &di.deck.id == deck_id {
+                                di.deck.color_identity = color_identity.clone();
+                                di.deck.modified = true;
+                                di.deck.rendered = false;
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                },
+                S2FMsg::Notification(s2f::Notification::DeckThumbnail {
+                    deck_id,
+                    thumbnail_url,
+                }) => match &mut self.decks {
+                    RemoteResource::Loaded(ref mut decks) => {
+                        for di in decks.iter_mut() {
+                            if &di.deck.id == deck_id {
+                                di.deck.thumbnail_url = Some(thumbnail_url.clone());
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                },
+                S2FMsg::Notification(s2f::Notification::DeckShareChanged {
+                    deck_id,
+                    share_token,
+                }) => match &mut self.decks {
+                    RemoteResource::Loaded(ref mut decks) => {
+                        for di in decks.iter_mut() {
+                            if &di.deck.id == deck_id {
+                                di.deck.share_token = share_token.clone();
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                },
+                S2FMsg::Notification(s2f::Notification::DeckRenderOptionsChanged {
+                    deck_id,
+                    options,
+                }) => match &mut self.decks {
+                    RemoteResource::Loaded(ref mut decks) => {
+                        for di in decks.iter_mut() {
+                            if &di.deck.id == deck_id {
+                                di.deck.render_options = options.clone();
+                                di.deck.rendered = false;
+                            }
+                        }
+                        true
+                    }
+                    _ => false,
+                },
+                S2FMsg::Notification(s2f::Notification::DeckCommandersChanged {
+                    deck_id,
+                    commanders: _,
+                }) => match &mut self.decks {
+                    RemoteResource::Loaded(ref mut decks) => {
+                        for di in decks.iter_mut() {
+                            if &di.deck.id == deck_id {
+                                di.deck.rendered = false;
                             }
                         }
                         true
@@ -281,6 +508,34 @@ impl Component for DeckList {
                     }
                 }
             },
+            Msg::DiffRevision(deck_id, revision) => {
+                let msg = F2SMsg::DiffDecks {
+                    old: DeckVersion {
+                        deck_id,
+                        revision: Some(revision),
+                    },
+                    new: DeckVersion {
+                        deck_id,
+                        revision: None,
+                    },
+                };
+                self.socket.send(msg).unwrap();
+                false
+            }
+            Msg::GetRevisions(deck_id) => {
+                self.socket
+                    .send(F2SMsg::GetDeckRevisions { id: deck_id })
+                    .unwrap();
+                false
+            }
+            Msg::RestoreRevision(deck_id, revision) => {
+                let msg = F2SMsg::RestoreDeckRevision {
+                    id: deck_id,
+                    revision,
+                };
+                self.socket.send(msg).unwrap();
+                false
+            }
             Msg::RebuildDeck(deck_id) => {
                 let mut url = None;
                 self.decks.as_ref().map(|dis| {
@@ -294,6 +549,47 @@ impl Component for DeckList {
                 }
                 false
             }
+            Msg::RenderSavedDeck(deck_id) => {
+                self.socket
+                    .send(F2SMsg::RenderSavedDeck { id: deck_id })
+                    .unwrap();
+                false
+            }
+            Msg::SetRenderOptions(deck_id, options) => {
+                let msg = F2SMsg::SetRenderOptions {
+                    id: deck_id,
+                    options,
+                };
+                self.socket.send(msg).unwrap();
+                false
+            }
+            Msg::SetShared(deck_id, shared) => {
+                let msg = F2SMsg::SetDeckShared {
+                    id: deck_id,
+                    shared,
+                };
+                self.socket.send(msg).unwrap();
+                false
+            }
+            Msg::ShowCards(deck_id) => match &mut self.decks {
+                RemoteResource::Loaded(ref mut decks) => {
+                    for di in decks.iter_mut() {
+                        if di.deck.id == deck_id {
+                            di.show_cards = true;
+                        }
+                    }
+                    true
+                }
+                _ => false,
+            },
+            Msg::ValidateDeck(deck_id, format) => {
+                let msg = F2SMsg::ValidateDeck {
+                    id: deck_id,
+                    format,
+                };
+                self.socket.send(msg).unwrap();
+                false
+            }
         };
         self.decks
             .as_mut()
@@ -321,6 +617,244 @@ impl Component for DeckList {
 }
 
 impl DeckList {
+    /// A drop-down for one of the deck's render options. `apply` updates the
+    /// options with the selected value, returning false if it isn't valid.
+    fn view_option_select(
+        &self,
+        di: &DeckInfo,
+        label: &str,
+        title: &str,
+        choices: Vec<(String, String)>,
+        current: String,
+        apply: fn(&mut RenderOptions, &str) -> bool,
+    ) -> Html {
+        let deck_id = di.deck.id;
+        let options = di.deck.render_options.clone();
+        let onchange = self.link.batch_callback(move |e: ChangeData| {
+            let mut options = options.clone();
+            match e {
+                ChangeData::Select(select) if apply(&mut options, &select.value()) => {
+                    vec![Msg::SetRenderOptions(deck_id, options)]
+                }
+                _ => vec![],
+            }
+        });
+        html! {
+            <label class="deck-option" title=title>
+                { label }
+                <select onchange=onchange>
+                    { for choices.into_iter().map(|(value, label)| html! {
+                        <option value=value.clone() selected=(value == current)> { label } </option>
+                    }) }
+                </select>
+            </label>
+        }
+    }
+
+    fn view_option_checkbox(
+        &self,
+        di: &DeckInfo,
+        label: &str,
+        title: &str,
+        checked: bool,
+        toggle: fn(&mut RenderOptions),
+    ) -> Html {
+        let deck_id = di.deck.id;
+        let options = di.deck.render_options.clone();
+        let onclick = self.link.callback(move |_| {
+            let mut options = options.clone();
+            toggle(&mut options);
+            Msg::SetRenderOptions(deck_id, options)
+        });
+        html! {
+            <label title=title>
+                <input type="checkbox" checked=checked onclick=onclick />
+                { label }
+            </label>
+        }
+    }
+
+    fn view_sharing(&self, di: &DeckInfo) -> Html {
+        let deck_id = di.deck.id;
+        match di.deck.share_token.clone() {
+            Some(token) => html! {
+                <>
+                    <button style="flex: 0 0 auto" title="Anyone with the link can download the rendered deck" onclick=self.link.callback(move |_| Msg::CopyShareLink(token.clone()))>
+                        { "Copy share link" }
+                    </button>
+                    <button style="flex: 0 0 auto" title="Make the share link stop working" onclick=self.link.callback(move |_| Msg::SetShared(deck_id, false))>
+                        { "Unshare" }
+                    </button>
+                </>
+            },
+            None => html! {
+                <button style="flex: 0 0 auto" title="Make a link anyone can download the rendered deck from" onclick=self.link.callback(move |_| Msg::SetShared(deck_id, true))>
+                    { "Share" }
+                </button>
+            },
+        }
+    }
+
+    fn view_revision(&self, r: &DeckRevision) -> Html {
+        let (deck_id, revision) = (r.deck_id, r.revision);
+        html! {
+            <li>
+                { format!(
+                    "#{} {} {} ({} cards) ",
+                    revision,
+                    r.loaded_at.format("%Y-%m-%d %H:%M"),
+                    r.title,
+                    r.card_count,
+                ) }
+                <a href={ format!("/decks/{}/revisions/{}.txt", deck_id, revision) } target="_blank" title="Decklist">
+                    { "txt" }
+                </a>
+                { " " }
+                <button onclick=self.link.callback(move |_| Msg::DiffRevision(deck_id, revision)) title="Compare with the current list">
+                    { "Diff" }
+                </button>
+                <button onclick=self.link.callback(move |_| Msg::RestoreRevision(deck_id, revision))>
+                    { "Restore" }
+                </button>
+            </li>
+        }
+    }
+
+    fn view_format_option(format: Format, current: Option<Format>) -> Html {
+        html! {
+            <option value=format.slug() selected=(current == Some(format))>
+                { format.label() }
+            </option>
+        }
+    }
+
+    fn view_legality(&self, di: &DeckInfo) -> Html {
+        let deck_id = di.deck.id;
+        let current = di.legality.as_ref().map(|report| report.format);
+        let onchange = self.link.batch_callback(move |e: ChangeData| match e {
+            ChangeData::Select(select) => Format::from_slug(&select.value())
+                .map(|format| Msg::ValidateDeck(deck_id, format))
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        });
+        let (summary, problems) = match &di.legality {
+            None => ("Legality".to_string(), html! { <> </> }),
+            Some(report) if report.is_legal() => (
+                format!("\u{2714} Legal in {}", report.format.label()),
+                html! { <> </> },
+            ),
+            Some(report) => (
+                format!(
+                    "\u{2718} Not legal in {} ({})",
+                    report.format.label(),
+                    report.problems.len()
+                ),
+                html! {
+                    <ul class="deck-legality">
+                        { for report.problems.iter().map(|p| html! { <li> { p.to_string() } </li> }) }
+                    </ul>
+                },
+            ),
+        };
+        html! {
+            <details class="deck-option">
+                <summary> { summary } </summary>
+                <label title="Check the deck against a format's deck building rules">
+                    { "Format: " }
+                    <select onchange=onchange>
+                        <option value="" selected=current.is_none() disabled=true> { "Choose…" } </option>
+                        { for Format::ALL.iter().map(|format| Self::view_format_option(*format, current)) }
+                    </select>
+                </label>
+                { problems }
+            </details>
+        }
+    }
+
+    fn view_pile_diff(label: &str, pile: &PileDiff) -> Html {
+        if pile.is_empty() {
+            return html! { <> </> };
+        }
+        let lines = pile
+            .added
+            .iter()
+            .map(|c| format!("+ {} {}", c.new_count, c.name))
+            .chain(
+                pile.removed
+                    .iter()
+                    .map(|c| format!("- {} {}", c.old_count, c.name)),
+            )
+            .chain(
+                pile.changed
+                    .iter()
+                    .map(|c| format!("~ {} {} (was {})", c.new_count, c.name, c.old_count)),
+            );
+        html! {
+            <>
+                <p> { label } </p>
+                <ul class="deck-diff">
+                    { for lines.map(|line| html! { <li> { line } </li> }) }
+                </ul>
+            </>
+        }
+    }
+
+    fn view_diff(diff: &DeckDiff) -> Html {
+        if diff.is_empty() {
+            return html! { <p> { "No differences" } </p> };
+        }
+        let revision = diff.old.revision.unwrap_or_default();
+        html! {
+            <div>
+                <p> { format!("Changes since #{}:", revision) } </p>
+                { Self::view_pile_diff("Commanders", &diff.commanders) }
+                { Self::view_pile_diff("Companion", &diff.companions) }
+                { Self::view_pile_diff("Main deck", &diff.main_deck) }
+                { Self::view_pile_diff("Sideboard", &diff.sideboard) }
+            </div>
+        }
+    }
+
+    fn view_revisions(&self, di: &DeckInfo) -> Html {
+        let deck_id = di.deck.id;
+        let revisions = match &di.revisions {
+            None => html! { <p> { "Loading…" } </p> },
+            Some(revisions) if revisions.is_empty() => html! { <p> { "No history yet" } </p> },
+            Some(revisions) => html! {
+                <ol class="deck-revisions">
+                    { for revisions.iter().map(|r| self.view_revision(r)) }
+                </ol>
+            },
+        };
+        html! {
+            <details class="deck-option">
+                <summary onclick=self.link.callback(move |_| Msg::GetRevisions(deck_id))>
+                    { "History" }
+                </summary>
+                { revisions }
+                { di.diff.as_ref().map(Self::view_diff).unwrap_or_default() }
+            </details>
+        }
+    }
+
+    fn view_cards(&self, di: &DeckInfo) -> Html {
+        let deck_id = di.deck.id;
+        let cards = if di.show_cards {
+            html! { <DeckDetailView socket=self.socket.clone() deck_id=deck_id /> }
+        } else {
+            html! { <> </> }
+        };
+        html! {
+            <details class="deck-option deck-cards">
+                <summary onclick=self.link.callback(move |_| Msg::ShowCards(deck_id))>
+                    { "Cards" }
+                </summary>
+                { cards }
+            </details>
+        }
+    }
+
     fn view_deck(&self, di: &DeckInfo) -> Html {
         let deck_id = di.deck.id;
         let download_link = if di.deck.rendered {
@@ -332,11 +866,66 @@ impl DeckList {
         } else {
             html! { { di.deck.title.clone() } }
         };
+        // TTS only shows a saved object's thumbnail if it's saved next to the
+        // JSON file with the same name.
+        let thumbnail_link = match di.deck.render_options.export_mode {
+            ExportMode::SavedObject if di.deck.rendered => html! {
+                <>
+                    { " " }
+                    <a href={ format!("/decks/{}.png", deck_id) } target="_blank" title="Thumbnail">
+                        { "\u{1F5BC}" }
+                    </a>
+                </>
+            },
+            _ => html! { <> </> },
+        };
+        let proxies_link = html! {
+            <>
+                { " " }
+                <a href={ format!("/decks/{}.pdf?cut_marks=true", deck_id) } target="_blank" title="Printable proxies">
+                    { "\u{1F5A8}" }
+                </a>
+            </>
+        };
+        let decklist_links = html! {
+            <>
+                { " " }
+                <a href={ format!("/decks/{}.txt", deck_id) } target="_blank" title="Decklist">
+                    { "txt" }
+                </a>
+                { " " }
+                <a href={ format!("/decks/{}.csv", deck_id) } target="_blank" title="Decklist spreadsheet">
+                    { "csv" }
+                </a>
+                { " " }
+                <a href={ format!("/decks/{}.arena.txt", deck_id) } target="_blank" title="Decklist for MTG Arena">
+                    { "arena" }
+                </a>
+            </>
+        };
+        let thumbnail = match &di.deck.thumbnail_url {
+            Some(url) => html! { <img class="deck-thumbnail" src=url.to_string() alt="" /> },
+            None => html! { <> </> },
+        };
+        let modified = if di.deck.modified {
+            html! {
+                <span class="deck-modified" title="Edited here since it was loaded from the deck site">
+                    { " (edited)" }
+                </span>
+            }
+        } else {
+            html! { <> </> }
+        };
         let deck_name = html! {
             <>
+                { thumbnail }
                 <a href=di.deck.url.to_string() target="_blank"> { "\u{1F5C3}" } </a>
                 { " " }
                 { download_link }
+                { modified }
+                { thumbnail_link }
+                { proxies_link }
+                { decklist_links }
             </>
         };
         let (status_msg, progress_bar) = match &di.status {
@@ -364,11 +953,130 @@ impl DeckList {
             // DeckStatus::Error(Some(e)) => format!("Error rendering deck: {}", e),
             // DeckStatus::Error(None) => "Unknown error rendering deck".to_string(),
         };
+        let token_copies = {
+            let current = token_copies_value(di.deck.render_options.token_copies);
+            let mut choices = vec![
+                ("1".to_string(), "1 of each".to_string()),
+                ("2".to_string(), "2 of each".to_string()),
+                ("3".to_string(), "3 of each".to_string()),
+                ("4".to_string(), "4 of each".to_string()),
+                ("5".to_string(), "5 of each".to_string()),
+                (
+                    "estimate".to_string(),
+                    "1 per card that makes it".to_string(),
+                ),
+            ];
+            if !choices.iter().any(|(value, _)| value == &current) {
+                choices.push((current.clone(), format!("{} of each", current)));
+            }
+            self.view_option_select(
+                di,
+                "Tokens: ",
+                "Copies of each token to put in the token pile",
+                choices,
+                current,
+                set_token_copies,
+            )
+        };
+        let layout = self.view_option_select(
+            di,
+            "Layout: ",
+            "Where the piles go on the table",
+            LayoutPreset::ALL
+                .iter()
+                .map(|preset| (preset.slug().to_string(), preset.label().to_string()))
+                .collect(),
+            di.deck.render_options.layout.slug().to_string(),
+            set_layout,
+        );
+        let export_mode = self.view_option_select(
+            di,
+            "Export: ",
+            "Loose piles, or a saved object with every pile in one bag",
+            ExportMode::ALL
+                .iter()
+                .map(|mode| (mode.slug().to_string(), mode.label().to_string()))
+                .collect(),
+            di.deck.render_options.export_mode.slug().to_string(),
+            set_export_mode,
+        );
+        let scripts = {
+            let scripts = di.deck.render_options.scripts;
+            html! {
+                <details class="deck-option">
+                    <summary> { "Scripts" } </summary>
+                    { self.view_option_checkbox(
+                        di,
+                        "Mulligan buttons",
+                        "Buttons on the library to draw an opening hand or mulligan",
+                        scripts.mulligan_buttons,
+                        |options| options.scripts.mulligan_buttons ^= true,
+                    ) }
+                    { self.view_option_checkbox(
+                        di,
+                        "Life counter",
+                        "A life counter on the commander",
+                        scripts.life_counter,
+                        |options| options.scripts.life_counter ^= true,
+                    ) }
+                    { self.view_option_checkbox(
+                        di,
+                        "Token spawner",
+                        "Right-click the token pile to spawn a token by name",
+                        scripts.token_spawner,
+                        |options| options.scripts.token_spawner ^= true,
+                    ) }
+                </details>
+            }
+        };
+        let maybeboard = self.view_option_checkbox(
+            di,
+            "Maybeboard",
+            "Add the maybeboard as a face-up pile",
+            di.deck.render_options.include_maybeboard,
+            |options| options.include_maybeboard ^= true,
+        );
+        let split_categories = self.view_option_checkbox(
+            di,
+            "Split categories",
+            "A separate pile for each of the deck site's categories, like Ramp or Removal",
+            di.deck.render_options.split_categories,
+            |options| options.split_categories ^= true,
+        );
+        let cards = self.view_cards(di);
+        let legality = self.view_legality(di);
+        let revisions = self.view_revisions(di);
+        let sharing = self.view_sharing(di);
+        let render_edits = if di.deck.modified {
+            html! {
+                <button style="flex: 0 0 auto" title="Render the edited card list" onclick=self.link.callback(move |_| Msg::RenderSavedDeck(deck_id))>
+                    { "Render" }
+                </button>
+            }
+        } else {
+            html! { <> </> }
+        };
+        let rebuild_title = if di.deck.modified {
+            "Load the deck from the deck site again, throwing away your edits"
+        } else {
+            "Load the deck from the deck site again"
+        };
         html! {
             <li style={ di.bg_gradient_css() }>
                 <span class="deck-name"> { deck_name } </span>
                 <span class="deck-status"> { status_msg } { progress_bar } </span>
-                <button style="flex: 0 0 auto" onclick=self.link.callback(move |_| Msg::RebuildDeck(deck_id))>
+                { token_copies }
+                { layout }
+                { export_mode }
+                { scripts }
+                { maybeboard }
+                { split_categories }
+                { cards }
+                { legality }
+                { revisions }
+                { sharing }
+                { render_edits }
+                <button style="flex: 0 0 auto" title=rebuild_title onclick=self.link.callback(move |_| Msg::RebuildDeck(deck_id))>
                   { "Rebuild" }
                 </button>
                 <button style="color: red; flex: 0 0 auto" onclick=self.link.callback(move |_| Msg::DeleteDeck(deck_id))>
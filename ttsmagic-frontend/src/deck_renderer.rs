@@ -47,7 +47,10 @@ impl Component for DeckRenderer {
         match msg {
             Msg::RenderDeck => match self.parsed_url.clone() {
                 Ok(url) => {
-                    let msg = F2SMsg::RenderDeck { url: url.clone() };
+                    let msg = match shared_deck_token(&url) {
+                        Some(share_token) => F2SMsg::CopySharedDeck { share_token },
+                        None => F2SMsg::RenderDeck { url: url.clone() },
+                    };
                     self.socket.send(msg).unwrap();
                     self.parsed_url = Err(format!("Please enter a URL"));
                     true
@@ -63,6 +66,10 @@ impl Component for DeckRenderer {
     }
 
     fn view(&self) -> Html {
+        let is_share_link = match &self.parsed_url {
+            Ok(url) => shared_deck_token(url).is_some(),
+            Err(_) => false,
+        };
         html! {
             <div id="create-deck-form">
                 <label for="create-url"> { "URL:" } </label>
@@ -77,9 +84,25 @@ impl Component for DeckRenderer {
                     disabled=self.parsed_url.is_err()
                     onclick=self.link.callback(|_| Msg::RenderDeck)
                 >
-                    { "Convert!" }
+                    { if is_share_link { "Copy to my decks" } else { "Convert!" } }
                 </button>
             </div>
         }
     }
 }
+
+/// Share links point back at this site, so pasting one copies the deck instead
+/// of trying to load it from a deck site.
+fn shared_deck_token(url: &Url) -> Option<String> {
+    let origin = web_sys::window()?.location().origin().ok()?;
+    if url.origin().ascii_serialization() != origin {
+        return None;
+    }
+    let token = url.path().strip_prefix("/shared/")?;
+    let token = token.strip_suffix(".json").unwrap_or(token);
+    if token.is_empty() || token.contains('/') {
+        None
+    } else {
+        Some(token.to_string())
+    }
+}
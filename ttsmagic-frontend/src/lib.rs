@@ -9,9 +9,11 @@ use ttsmagic_types::server_to_frontend as s2f;
 use wasm_bindgen::prelude::*;
 use yew::prelude::*;
 
+mod deck_detail;
 mod deck_list;
 mod deck_renderer;
 mod remote_resource;
+mod settings;
 mod ws;
 
 pub struct Model {
@@ -19,10 +21,12 @@ pub struct Model {
     link: ComponentLink<Self>,
     socket: Rc<ws::WebSocket>,
     fatal_errors: Vec<s2f::Error>,
+    show_settings: bool,
 }
 
 pub enum Msg {
     IgnoreError(usize),
+    ToggleSettings,
     WS(ws::Message),
 }
 
@@ -75,6 +79,7 @@ impl Component for Model {
             link,
             socket: Rc::new(socket),
             fatal_errors,
+            show_settings: false,
         }
     }
 
@@ -92,6 +97,10 @@ impl Component for Model {
                     false
                 }
             }
+            Msg::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+                true
+            }
             Msg::WS(ws_msg) => match &*ws_msg {
                 s2f::ServerToFrontendMessage::FatalError(e) => {
                     let details = match &e.details {
@@ -120,13 +129,31 @@ impl Component for Model {
                 </div>
             }
         };
+        let page = if self.show_settings {
+            html! { <settings::Settings socket=self.socket.clone() /> }
+        } else {
+            html! {
+                <>
+                    <deck_renderer::DeckRenderer socket=self.socket.clone() />
+                    <deck_list::DeckList socket=self.socket.clone() />
+                </>
+            }
+        };
+        let settings_label = if self.show_settings {
+            "Back to decks"
+        } else {
+            "Settings"
+        };
         html! {
             <div id="content">
                 <h1> {"MtG → Tabletop Simulator Deck Builder"} </h1>
                 { fatal_errors }
-                <deck_renderer::DeckRenderer socket=self.socket.clone() />
-                <deck_list::DeckList socket=self.socket.clone() />
+                { page }
                 <footer>
+                  <button onclick=self.link.callback(|_| Msg::ToggleSettings)>
+                      { settings_label }
+                  </button>
+                  { " " }
                   <a href="/logout/"> { "Sign out" } </a>
                 </footer>
             </div>
@@ -0,0 +1,180 @@
+use std::rc::Rc;
+use ttsmagic_types::{
+    frontend_to_server::FrontendToServerMessage as F2SMsg,
+    server_to_frontend::ServerToFrontendMessage as S2FMsg, ApiToken,
+};
+use uuid::Uuid;
+use yew::prelude::*;
+
+use crate::{deck_list::copy_to_clipboard, remote_resource::RemoteResource};
+
+pub enum Msg {
+    CopySecret,
+    CreateToken,
+    FromServer(Rc<S2FMsg>),
+    RevokeToken(Uuid),
+    SetName(String),
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    pub socket: Rc<crate::ws::WebSocket>,
+}
+
+pub struct Settings {
+    link: ComponentLink<Self>,
+    socket: Rc<crate::ws::WebSocket>,
+    tokens: RemoteResource<Vec<ApiToken>>,
+    new_token_name: String,
+    /// Only known right after creating a token, and lost on reload.
+    new_secret: Option<String>,
+}
+
+impl Component for Settings {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        props
+            .socket
+            .register_message_callback(link.callback(Msg::FromServer));
+        props.socket.send(F2SMsg::GetApiTokens).unwrap();
+        Self {
+            link,
+            socket: props.socket,
+            tokens: RemoteResource::Loading,
+            new_token_name: String::new(),
+            new_secret: None,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if !Rc::ptr_eq(&self.socket, &props.socket) {
+            self.socket = props.socket;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::CopySecret => {
+                if let Some(secret) = self.new_secret.as_ref() {
+                    if let Err(e) = copy_to_clipboard(secret) {
+                        error!("Failed to copy API token: {:?}", e);
+                    }
+                }
+                false
+            }
+            Msg::CreateToken => {
+                let name = self.new_token_name.trim().to_string();
+                if name.is_empty() {
+                    return false;
+                }
+                self.socket.send(F2SMsg::CreateApiToken { name }).unwrap();
+                self.new_token_name = String::new();
+                true
+            }
+            Msg::FromServer(msg_rc) => match &*msg_rc {
+                S2FMsg::ApiTokenCreated { token, secret } => {
+                    if let RemoteResource::Loaded(ref mut tokens) = self.tokens {
+                        tokens.push(token.clone());
+                    }
+                    self.new_secret = Some(secret.clone());
+                    true
+                }
+                S2FMsg::ApiTokens { tokens } => {
+                    self.tokens = RemoteResource::Loaded(tokens.clone());
+                    true
+                }
+                _ => false,
+            },
+            Msg::RevokeToken(id) => {
+                self.socket.send(F2SMsg::RevokeApiToken { id }).unwrap();
+                false
+            }
+            Msg::SetName(name) => {
+                self.new_token_name = name;
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        let tokens = match self.tokens.as_ref() {
+            RemoteResource::Loading => html! { <p> { "Loading…" } </p> },
+            RemoteResource::Error(e) => html! { <p> { format!("Error: {}", e) } </p> },
+            RemoteResource::Loaded(tokens) if tokens.is_empty() => {
+                html! { <p> { "You don't have any API tokens." } </p> }
+            }
+            RemoteResource::Loaded(tokens) => html! {
+                <ul class="api-tokens">
+                    { for tokens.iter().map(|token| self.view_token(token)) }
+                </ul>
+            },
+        };
+        let new_secret = match self.new_secret.as_ref() {
+            Some(secret) => html! {
+                <p class="api-token-secret">
+                    { "Your new token is " }
+                    <code> { secret.as_str() } </code>
+                    <button onclick=self.link.callback(|_| Msg::CopySecret)> { "Copy" } </button>
+                    <br />
+                    { "Save it somewhere safe now. It won't be shown again." }
+                </p>
+            },
+            None => html! { <></> },
+        };
+        let create_form = html! {
+            <div id="create-api-token-form">
+                <input
+                    type="text"
+                    placeholder="Token name, like \"Discord bot\""
+                    value=&self.new_token_name
+                    oninput=self.link.callback(|e: InputData| Msg::SetName(e.value))
+                />
+                <button
+                    disabled=self.new_token_name.trim().is_empty()
+                    onclick=self.link.callback(|_| Msg::CreateToken)
+                >
+                    { "Create token" }
+                </button>
+            </div>
+        };
+        html! {
+            <div id="settings">
+                <h2> { "API tokens" } </h2>
+                <p>
+                    { "Scripts can use these to act as you, by sending " }
+                    <code> { "Authorization: Bearer $TOKEN" } </code>
+                    { " to the API or the websocket." }
+                </p>
+                { tokens }
+                { new_secret }
+                { create_form }
+            </div>
+        }
+    }
+}
+
+impl Settings {
+    fn view_token(&self, token: &ApiToken) -> Html {
+        let id = token.id;
+        let last_used = match token.last_used_at {
+            Some(at) => format!("last used {}", at.format("%Y-%m-%d %H:%M")),
+            None => "never used".to_string(),
+        };
+        html! {
+            <li>
+                <span class="api-token-name"> { &token.name } </span>
+                <span class="api-token-dates">
+                    { format!("created {}, {}", token.created_at.format("%Y-%m-%d"), last_used) }
+                </span>
+                <button onclick=self.link.callback(move |_| Msg::RevokeToken(id))>
+                    { "Revoke" }
+                </button>
+            </li>
+        }
+    }
+}
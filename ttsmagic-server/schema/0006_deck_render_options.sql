@@ -0,0 +1 @@
+ALTER TABLE deck ADD COLUMN render_options JSONB NOT NULL DEFAULT '{}'::jsonb;
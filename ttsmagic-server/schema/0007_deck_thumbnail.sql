@@ -0,0 +1 @@
+ALTER TABLE deck ADD COLUMN thumbnail TEXT;
@@ -0,0 +1,24 @@
+CREATE TABLE deck_revision
+( deck_id UUID NOT NULL
+  REFERENCES deck (id)
+  ON DELETE CASCADE
+  ON UPDATE CASCADE
+, revision INTEGER NOT NULL CHECK (revision >= 1)
+, loaded_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+, title TEXT NOT NULL
+, url TEXT NOT NULL
+, PRIMARY KEY ( deck_id, revision )
+);
+
+CREATE TABLE deck_revision_entry
+( deck_id UUID NOT NULL
+, revision INTEGER NOT NULL
+, card UUID NOT NULL
+, copies SMALLINT NOT NULL CHECK (copies >= 1)
+, pile deck_pile NOT NULL
+, PRIMARY KEY ( deck_id, revision, card, pile )
+, FOREIGN KEY ( deck_id, revision )
+  REFERENCES deck_revision (deck_id, revision)
+  ON DELETE CASCADE
+  ON UPDATE CASCADE
+);
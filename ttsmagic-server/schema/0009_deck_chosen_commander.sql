@@ -0,0 +1,10 @@
+-- Commanders picked by the user, by oracle ID. They replace whatever the deck
+-- site or the heuristic picked whenever the deck is loaded again.
+CREATE TABLE deck_chosen_commander
+( deck_id UUID NOT NULL
+  REFERENCES deck (id)
+  ON DELETE CASCADE
+  ON UPDATE CASCADE
+, oracle_id UUID NOT NULL
+, PRIMARY KEY ( deck_id, oracle_id )
+);
@@ -0,0 +1,19 @@
+-- Postgres 11 can't add an enum value inside a transaction, so the type is
+-- swapped out instead.
+ALTER TYPE deck_pile RENAME TO deck_pile_old;
+
+CREATE TYPE deck_pile AS ENUM ('main_deck', 'sideboard', 'commander', 'companion');
+
+ALTER TABLE deck_entry DROP CONSTRAINT deck_entry_commander_single;
+
+ALTER TABLE deck_entry
+    ALTER COLUMN pile TYPE deck_pile USING pile::text::deck_pile;
+
+ALTER TABLE deck_revision_entry
+    ALTER COLUMN pile TYPE deck_pile USING pile::text::deck_pile;
+
+ALTER TABLE deck_entry
+    ADD CONSTRAINT deck_entry_commander_single
+        CHECK ((pile IN ('commander', 'companion') AND copies = 1) OR (pile NOT IN ('commander', 'companion')));
+
+DROP TYPE deck_pile_old;
@@ -0,0 +1,5 @@
+-- The deck site's own grouping of the card, like Archidekt's "Ramp" or
+-- "Removal" categories.
+ALTER TABLE deck_entry ADD COLUMN category TEXT NULL;
+
+ALTER TABLE deck_revision_entry ADD COLUMN category TEXT NULL;
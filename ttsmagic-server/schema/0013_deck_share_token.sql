@@ -0,0 +1,3 @@
+-- Anyone with the token can download the rendered deck. NULL when the deck
+-- isn't shared.
+ALTER TABLE deck ADD COLUMN share_token TEXT NULL UNIQUE;
@@ -0,0 +1,13 @@
+CREATE TABLE api_token
+( id UUID NOT NULL PRIMARY KEY
+, user_id BIGINT NOT NULL
+  REFERENCES ttsmagic_user(steam_id)
+  ON DELETE CASCADE
+  ON UPDATE CASCADE
+, name TEXT NOT NULL
+, token_hash TEXT NOT NULL UNIQUE
+, created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+, last_used_at TIMESTAMPTZ NULL DEFAULT NULL
+);
+
+CREATE INDEX api_token_user_id ON api_token (user_id);
@@ -0,0 +1,3 @@
+-- Set when the card list is edited by hand, and cleared when the deck is
+-- loaded from the deck site again.
+ALTER TABLE deck ADD COLUMN modified BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,117 @@
+//! Long-lived personal access tokens, so scripts and bots can act for a user
+//! without a session cookie. Only a hash of each token is stored.
+
+use anyhow::{ensure, Result};
+use chrono::prelude::*;
+use ring::digest;
+use sqlx::{PgConnection, Row};
+use ttsmagic_types::{ApiToken, UserId};
+use uuid::Uuid;
+
+use crate::user::User;
+
+/// Makes tokens easy to recognize if they end up somewhere they shouldn't.
+const TOKEN_PREFIX: &str = "ttsm_";
+
+fn hash_token(secret: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, secret.as_bytes()))
+}
+
+fn token_from_row(row: &sqlx::postgres::PgRow) -> ApiToken {
+    ApiToken {
+        id: row.get("id"),
+        name: row.get("name"),
+        created_at: row.get("created_at"),
+        last_used_at: row.get("last_used_at"),
+    }
+}
+
+/// Returns the new token along with its secret, which can't be recovered
+/// later.
+pub async fn create(
+    db: &mut PgConnection,
+    user_id: UserId,
+    name: &str,
+) -> Result<(ApiToken, String)> {
+    let name = name.trim();
+    ensure!(!name.is_empty(), "API tokens need a name");
+    let secret = format!(
+        "{}{}",
+        TOKEN_PREFIX,
+        hex::encode(rand::random::<[u8; 32]>())
+    );
+    let row = sqlx::query(
+        "\
+INSERT INTO api_token ( id, user_id, name, token_hash )
+VALUES ( $1, $2, $3, $4 )
+RETURNING id, name, created_at, last_used_at;",
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_id.as_queryable())
+    .bind(name)
+    .bind(hash_token(&secret))
+    .fetch_one(&mut *db)
+    .await?;
+    Ok((token_from_row(&row), secret))
+}
+
+pub async fn list_for_user(db: &mut PgConnection, user_id: UserId) -> Result<Vec<ApiToken>> {
+    let rows = sqlx::query(
+        "\
+SELECT id, name, created_at, last_used_at
+FROM api_token
+WHERE user_id = $1
+ORDER BY created_at;",
+    )
+    .bind(user_id.as_queryable())
+    .fetch_all(&mut *db)
+    .await?;
+    Ok(rows.iter().map(token_from_row).collect())
+}
+
+pub async fn revoke(db: &mut PgConnection, user_id: UserId, id: Uuid) -> Result<()> {
+    let deleted = sqlx::query("DELETE FROM api_token WHERE id = $1 AND user_id = $2;")
+        .bind(id)
+        .bind(user_id.as_queryable())
+        .execute(&mut *db)
+        .await?;
+    ensure!(
+        deleted.rows_affected() == 1,
+        "Invalid API token ID (that doesn't belong to you)"
+    );
+    Ok(())
+}
+
+/// Looks up the user a token belongs to, and records that it was used.
+pub async fn user_for_token(db: &mut PgConnection, secret: &str) -> Result<Option<User>> {
+    if !secret.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let row_opt = sqlx::query(
+        "\
+UPDATE api_token SET last_used_at = $2
+WHERE token_hash = $1
+RETURNING user_id;",
+    )
+    .bind(hash_token(secret))
+    .bind(Utc::now())
+    .fetch_optional(&mut *db)
+    .await?;
+    match row_opt {
+        Some(row) => {
+            let user_id = UserId::from(row.get::<i64, _>("user_id"));
+            User::get_by_id(db, user_id).await
+        }
+        None => Ok(None),
+    }
+}
+
+/// The token from an `Authorization: Bearer $TOKEN` header value.
+pub fn parse_authorization(header_value: &str) -> Option<&str> {
+    let token = header_value.strip_prefix("Bearer ")?.trim();
+    if token.is_empty() {
+        None
+    } else {
+        Some(token)
+    }
+}
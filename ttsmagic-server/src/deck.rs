@@ -3,13 +3,18 @@ use async_std::{prelude::*, sync::Arc};
 use futures::future::LocalBoxFuture;
 use redis::AsyncCommands;
 use serde_json::Value;
-use sqlx::{Executor, PgConnection, Postgres, Row};
+use sqlx::{Connection, Executor, PgConnection, Postgres, Row};
 use std::{collections::HashMap, convert::TryInto, fmt};
-use ttsmagic_types::{server_to_frontend as s2f, DeckColorIdentity, DeckId, UserId};
+use thiserror::Error as ErrorDerive;
+use ttsmagic_types::{
+    server_to_frontend as s2f, DeckColorIdentity, DeckEdit, DeckId, DeckRevision, DeckVersion,
+    Pile, RenderOptions, UserId,
+};
 use url::Url;
 use uuid::Uuid;
 
 use crate::{
+    files::MediaFile,
     notify::notify_user,
     scryfall::{
         self, api::ScryfallApi, ScryfallCard, ScryfallCardRow, ScryfallId, ScryfallOracleId,
@@ -19,7 +24,26 @@ use crate::{
     // utils::sqlx::PgArray1D,
 };
 
+mod commanders;
+pub mod diff;
+pub mod export;
+pub mod legality;
 mod loaders;
+mod text;
+
+/// Something wrong with the deck we were asked to load rather than with the
+/// server: an unknown deck site, a decklist that doesn't parse or a card that
+/// doesn't exist. Find it with `downcast_ref`, which looks past any context
+/// added on top.
+#[derive(ErrorDerive, Debug)]
+#[error("{0}")]
+pub struct DeckInputError(String);
+
+impl DeckInputError {
+    fn wrap(e: anyhow::Error) -> anyhow::Error {
+        DeckInputError(format!("{:#}", e)).into()
+    }
+}
 
 async fn expand_cards<I>(
     db: &mut PgConnection,
@@ -52,16 +76,34 @@ where
     Ok(output)
 }
 
+/// Expands a pile that only ever has one copy of each card, like the
+/// commanders.
+async fn expand_single_cards(
+    db: &mut PgConnection,
+    label: &'static str,
+    card_list: HashMap<ScryfallOracleId, String>,
+) -> Result<HashMap<ScryfallId, ScryfallCard>> {
+    let card_list = card_list.into_iter().map(|(k, name)| (k, (name, 1)));
+    let mut output = HashMap::with_capacity(card_list.len());
+    for (card_id, (card, count)) in expand_cards(db, label, card_list).await? {
+        assert_eq!(count, 1);
+        let prev = output.insert(card_id, card);
+        assert!(prev.is_none());
+    }
+    Ok(output)
+}
+
 async fn insert_deck_entry(
     db: impl sqlx::Executor<'_, Database = Postgres>,
     deck_id: DeckId,
     card_id: ScryfallId,
     card_count: u8,
     pile: &'static str,
+    category: Option<&str>,
 ) -> Result<()> {
     const INSERT_ENTRY_SQL: &'static str = "\
-INSERT INTO deck_entry ( deck_id, card, copies, pile )
-VALUES ( $1::uuid, $2::uuid, $3, $4::deck_pile );";
+INSERT INTO deck_entry ( deck_id, card, copies, pile, category )
+VALUES ( $1::uuid, $2::uuid, $3, $4::deck_pile, $5 );";
     debug!(
         "Creating row in deck_entry table for deck {} and card {} in pile {}",
         deck_id, card_id, pile,
@@ -71,17 +113,111 @@ VALUES ( $1::uuid, $2::uuid, $3, $4::deck_pile );";
         .bind(card_id.as_uuid())
         .bind(card_count as i16)
         .bind(pile)
+        .bind(category)
         .execute(db)
         .await?;
     Ok(())
 }
 
+/// Makes sure a pile can hold that many copies of one card.
+fn check_copies(pile: Pile, copies: u16) -> Result<()> {
+    ensure!(
+        !pile.is_single() || copies <= 1,
+        "The {} can only have one copy of each card",
+        pile.label().to_lowercase()
+    );
+    ensure!(
+        copies <= u16::from(u8::MAX),
+        "Can't have more than {} copies of a card",
+        u8::MAX
+    );
+    Ok(())
+}
+
+/// Copies the deck's current entries into a new revision, returning its number.
+async fn save_revision(
+    db: &mut PgConnection,
+    deck_id: DeckId,
+    title: &str,
+    url: &Url,
+) -> Result<u32> {
+    let mut tx = db.begin().await?;
+    // Locking the deck row makes concurrent saves take turns picking the next
+    // revision number, instead of both picking the same one.
+    sqlx::query("SELECT id FROM deck WHERE id = $1 FOR UPDATE;")
+        .bind(deck_id.as_uuid())
+        .execute(&mut tx)
+        .await?;
+    let revision: i32 = sqlx::query(
+        "\
+INSERT INTO deck_revision ( deck_id, revision, title, url )
+SELECT $1, COALESCE(MAX(revision), 0) + 1, $2, $3
+FROM deck_revision
+WHERE deck_id = $1
+RETURNING revision;",
+    )
+    .bind(deck_id.as_uuid())
+    .bind(title)
+    .bind(url.as_str())
+    .fetch_one(&mut tx)
+    .await?
+    .get("revision");
+    debug!("Saving revision {} of deck {}", revision, deck_id);
+    sqlx::query(
+        "\
+INSERT INTO deck_revision_entry ( deck_id, revision, card, copies, pile, category )
+SELECT deck_id, $2, card, copies, pile, category
+FROM deck_entry
+WHERE deck_id = $1;",
+    )
+    .bind(deck_id.as_uuid())
+    .bind(revision)
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+    Ok(revision.try_into()?)
+}
+
+async fn deck_color_identity(db: &mut PgConnection, deck_id: DeckId) -> Result<DeckColorIdentity> {
+    let mut rows = sqlx::query(
+        "\
+SELECT DISTINCT jsonb_array_elements_text(sc.json -> 'color_identity') AS color_identity
+FROM deck_entry
+INNER JOIN scryfall_card sc
+  ON ((sc.json ->> 'id')::uuid = deck_entry.card)
+WHERE deck_id = $1;",
+    )
+    .bind(deck_id.as_uuid())
+    .fetch(&mut *db);
+    let mut ci = DeckColorIdentity::default();
+    while let Some(row) = rows.next().await {
+        let row = row?;
+        let color = row.get::<String, _>("color_identity");
+        match color.as_str() {
+            "B" => ci.black = true,
+            "U" => ci.blue = true,
+            "G" => ci.green = true,
+            "R" => ci.red = true,
+            "W" => ci.white = true,
+            other => warn!(
+                "Got an unexpected color when parsing deck {:?}'s color identity: {:?}",
+                deck_id, other
+            ),
+        }
+    }
+    Ok(ci)
+}
+
 #[derive(Clone, Debug)]
 pub struct UnparsedDeck {
     pub id: DeckId,
     pub user_id: UserId,
     pub url: Url,
     pub title: String,
+    pub render_options: RenderOptions,
+    /// Oracle IDs of the commanders the user picked, if any.
+    pub chosen_commanders: Vec<ScryfallOracleId>,
+    pub share_token: Option<String>,
 }
 
 impl fmt::Display for UnparsedDeck {
@@ -98,6 +234,19 @@ impl fmt::Display for UnparsedDeck {
     }
 }
 
+/// The cards a loader found in each pile, before they're expanded into
+/// specific printings.
+#[derive(Clone, Debug, Default)]
+pub struct CardPiles {
+    pub commanders: HashMap<ScryfallOracleId, String>,
+    pub companions: HashMap<ScryfallOracleId, String>,
+    pub main_deck: HashMap<ScryfallOracleId, (String, u8)>,
+    pub sideboard: HashMap<ScryfallOracleId, (String, u8)>,
+    pub maybeboard: HashMap<ScryfallOracleId, (String, u8)>,
+    /// The deck site's category for main deck cards, for sites that have them.
+    pub categories: HashMap<ScryfallOracleId, String>,
+}
+
 impl UnparsedDeck {
     async fn save(
         db: &mut PgConnection,
@@ -107,24 +256,33 @@ impl UnparsedDeck {
     ) -> Result<Self> {
         debug!("Checking for an existing deck for this user and URL");
         let url_str = format!("{}", url);
-        let existing_deck_opt =
-            sqlx::query("SELECT id, title FROM deck WHERE user_id = $1 AND url = $2;")
-                .bind(user.id.as_queryable())
-                .bind(&url_str)
-                .fetch_optional(&mut *db)
-                .await?;
-        let (deck_id, title) = match existing_deck_opt {
+        let existing_deck_opt = sqlx::query(
+            "\
+SELECT id, title, render_options::text AS render_options, share_token
+FROM deck
+WHERE user_id = $1 AND url = $2;",
+        )
+        .bind(user.id.as_queryable())
+        .bind(&url_str)
+        .fetch_optional(&mut *db)
+        .await?;
+        let (deck_id, title, render_options, share_token) = match existing_deck_opt {
             Some(row) => {
                 debug!("Got row with {} values", row.len());
                 let deck_id: Uuid = row.get("id");
                 let title = row.get("title");
+                let render_options: String = row.get("render_options");
+                let render_options = serde_json::from_str(&render_options)?;
+                let share_token = row.get("share_token");
                 debug!("Updating deck {}", deck_id);
-                sqlx::query("UPDATE deck SET json = $1::jsonb WHERE id = $2;")
+                sqlx::query(
+                    "UPDATE deck SET json = $1::jsonb, thumbnail = NULL, modified = FALSE WHERE id = $2;",
+                )
                     .bind(None::<&str>)
                     .bind(deck_id)
                     .execute(&mut *db)
                     .await?;
-                (DeckId(deck_id), title)
+                (DeckId(deck_id), title, render_options, share_token)
             }
             None => {
                 let deck_id = DeckId(Uuid::new_v4());
@@ -146,13 +304,22 @@ ON CONFLICT (id) DO UPDATE SET user_id = $2, title = $3, url = $4;",
                     "Problem inserting deck row. Expected 1 row modified, saw {} instead",
                     inserted.rows_affected()
                 );
-                (deck_id, url_str)
+                (deck_id, url_str, RenderOptions::default(), None)
             }
         };
         sqlx::query("DELETE FROM deck_entry WHERE deck_id = $1")
             .bind(deck_id.as_uuid())
             .execute(&mut *db)
             .await?;
+        let chosen_commanders = sqlx::query(
+            "SELECT oracle_id FROM deck_chosen_commander WHERE deck_id = $1 ORDER BY oracle_id;",
+        )
+        .bind(deck_id.as_uuid())
+        .fetch_all(&mut *db)
+        .await?
+        .into_iter()
+        .map(|row| ScryfallOracleId::from(row.get::<Uuid, _>("oracle_id")))
+        .collect();
 
         notify_user(
             redis,
@@ -170,6 +337,9 @@ ON CONFLICT (id) DO UPDATE SET user_id = $2, title = $3, url = $4;",
             user_id: user.id,
             url,
             title,
+            render_options,
+            chosen_commanders,
+            share_token,
         })
     }
 
@@ -178,97 +348,85 @@ ON CONFLICT (id) DO UPDATE SET user_id = $2, title = $3, url = $4;",
         db: &mut PgConnection,
         redis: &mut R,
         title: String,
-        commanders: HashMap<ScryfallOracleId, String>,
-        main_deck: HashMap<ScryfallOracleId, (String, u8)>,
-        sideboard: HashMap<ScryfallOracleId, (String, u8)>,
+        mut piles: CardPiles,
     ) -> Result<Deck>
     where
         R: AsyncCommands,
     {
         debug!("Saving cards for deck {:?}", title);
+        if !self.chosen_commanders.is_empty() {
+            let missing = commanders::assign_commanders(
+                &mut piles.commanders,
+                &mut piles.main_deck,
+                &self.chosen_commanders,
+            );
+            if !missing.is_empty() {
+                warn!(
+                    "Chosen commanders {:?} are no longer in deck {}, forgetting the choice",
+                    missing, self.id
+                );
+                sqlx::query("DELETE FROM deck_chosen_commander WHERE deck_id = $1;")
+                    .bind(self.id.as_uuid())
+                    .execute(&mut *db)
+                    .await?;
+            }
+        }
+        if piles.commanders.is_empty() {
+            piles.commanders = commanders::guess_commanders(&mut *db, &mut piles.main_deck)
+                .await
+                .context("Failed to guess commanders from main deck list")?;
+        }
         sqlx::query("UPDATE deck SET title = $1 WHERE id = $2;")
             .bind(&title)
             .bind(self.id.as_uuid())
             .execute(&mut *db)
             .await?;
-        sqlx::query("DELETE FROM deck_entry WHERE deck_id = $1;")
-            .bind(self.id.as_uuid())
-            .execute(&mut *db)
-            .await?;
-        let commanders_len = commanders.len();
-        let commanders_iter = commanders.into_iter().map(|(k, name)| (k, (name, 1)));
-        let mut commanders = HashMap::with_capacity(commanders_len);
-        for (card_id, (name, count)) in
-            expand_cards(&mut *db, "commanders", commanders_iter).await?
-        {
-            assert_eq!(count, 1);
-            let prev = commanders.insert(card_id, name);
-            assert!(prev.is_none());
+        let commanders = expand_single_cards(&mut *db, "commanders", piles.commanders).await?;
+        let companions = expand_single_cards(&mut *db, "companions", piles.companions).await?;
+        let main_deck = expand_cards(&mut *db, "main deck", piles.main_deck.into_iter()).await?;
+        let sideboard = expand_cards(&mut *db, "sideboard", piles.sideboard.into_iter()).await?;
+        let maybeboard = expand_cards(&mut *db, "maybeboard", piles.maybeboard.into_iter()).await?;
+        let mut categories = HashMap::new();
+        for (card_id, (card, _)) in main_deck.iter() {
+            if let Some(category) = piles.categories.get(&card.oracle_id()?) {
+                categories.insert(*card_id, category.clone());
+            }
         }
-        let main_deck = expand_cards(&mut *db, "main deck", main_deck.into_iter()).await?;
-        let sideboard = expand_cards(&mut *db, "sideboard", sideboard.into_iter()).await?;
+        let deck = Deck {
+            id: self.id,
+            user_id: self.user_id,
+            title,
+            url: self.url,
+            commanders,
+            companions,
+            main_deck,
+            sideboard,
+            maybeboard,
+            categories,
+            render_options: self.render_options,
+            rendered_json: None,
+            thumbnail: None,
+            share_token: self.share_token,
+            modified: false,
+        };
+        deck.insert_entries(&mut *db).await?;
 
-        for (card_id, _) in commanders.iter() {
-            insert_deck_entry(&mut *db, self.id, *card_id, 1, "commander").await?;
-        }
-        for (card_id, (_, card_count)) in main_deck.iter() {
-            insert_deck_entry(&mut *db, self.id, *card_id, *card_count, "main_deck").await?;
-        }
-        for (card_id, (_, card_count)) in sideboard.iter() {
-            insert_deck_entry(&mut *db, self.id, *card_id, *card_count, "sideboard").await?;
-        }
+        save_revision(&mut *db, deck.id, &deck.title, &deck.url).await?;
 
-        let color_identity = {
-            let mut rows = sqlx::query(
-                "\
-SELECT DISTINCT jsonb_array_elements_text(sc.json -> 'color_identity') AS color_identity
-FROM deck_entry
-INNER JOIN scryfall_card sc
-  ON ((sc.json ->> 'id')::uuid = deck_entry.card)
-WHERE deck_id = $1;",
-            )
-            .bind(self.id.as_uuid())
-            .fetch(&mut *db);
-            let mut ci = DeckColorIdentity::default();
-            while let Some(row) = rows.next().await {
-                let row = row?;
-                let color = row.get::<String, _>("color_identity");
-                match color.as_str() {
-                    "B" => ci.black = true,
-                    "U" => ci.blue = true,
-                    "G" => ci.green = true,
-                    "R" => ci.red = true,
-                    "W" => ci.white = true,
-                    other => warn!(
-                        "Got an unexpected color when parsing deck {:?}'s color identity: {:?}",
-                        self.id, other
-                    ),
-                }
-            }
-            ci
-        };
+        let color_identity = deck_color_identity(&mut *db, deck.id).await?;
         notify_user(
             redis,
-            self.user_id,
+            deck.user_id,
             s2f::Notification::DeckParsed {
-                deck_id: self.id,
-                title: title.clone(),
-                url: self.url.clone(),
+                deck_id: deck.id,
+                title: deck.title.clone(),
+                url: deck.url.clone(),
                 color_identity,
             },
         )
         .await?;
 
-        Ok(Deck {
-            id: self.id,
-            user_id: self.user_id,
-            title: title,
-            url: self.url,
-            commanders,
-            main_deck,
-            sideboard,
-            rendered_json: None,
-        })
+        Ok(deck)
     }
 }
 
@@ -279,9 +437,23 @@ pub struct Deck {
     pub title: String,
     pub url: Url,
     pub commanders: HashMap<ScryfallId, ScryfallCard>,
+    /// Usually empty, and never more than one card in a legal deck.
+    pub companions: HashMap<ScryfallId, ScryfallCard>,
     pub main_deck: HashMap<ScryfallId, (ScryfallCard, u8)>,
     pub sideboard: HashMap<ScryfallId, (ScryfallCard, u8)>,
+    /// Cards being considered for the deck. They're only rendered when the
+    /// render options ask for them.
+    pub maybeboard: HashMap<ScryfallId, (ScryfallCard, u8)>,
+    /// The deck site's category for each main deck card that has one.
+    pub categories: HashMap<ScryfallId, String>,
+    pub render_options: RenderOptions,
     pub rendered_json: Option<Value>,
+    /// Media file key of the deck's thumbnail image, once it's been rendered.
+    pub thumbnail: Option<String>,
+    /// Token for the public `/shared/` link, while the deck is shared.
+    pub share_token: Option<String>,
+    /// Set by [`Deck::edit`] until the deck is loaded from its URL again.
+    pub modified: bool,
 }
 
 struct DeckEntryRow {
@@ -290,15 +462,21 @@ struct DeckEntryRow {
     deck_title: String,
     deck_url: String,
     deck_json: Option<Value>,
+    deck_render_options: RenderOptions,
+    deck_thumbnail: Option<String>,
+    deck_share_token: Option<String>,
+    deck_modified: bool,
     card_id: ScryfallId,
     card_row: ScryfallCardRow,
     copies: u8,
     pile: String,
+    category: Option<String>,
 }
 
 impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for DeckEntryRow {
     fn from_row(row: &sqlx::postgres::PgRow) -> Result<Self, sqlx::Error> {
         let deck_json: Option<String> = row.try_get("deck_json")?;
+        let deck_render_options: String = row.try_get("deck_render_options")?;
         let card_json: String = row.try_get("card_json")?;
         let row = DeckEntryRow {
             deck_id: Uuid::into(row.try_get("deck_id")?),
@@ -306,6 +484,11 @@ impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for DeckEntryRow {
             deck_title: row.try_get("deck_title")?,
             deck_url: row.try_get("deck_url")?,
             deck_json: deck_json.map(|s| serde_json::from_str(&s).unwrap()),
+            deck_render_options: serde_json::from_str(&deck_render_options)
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            deck_thumbnail: row.try_get("deck_thumbnail")?,
+            deck_share_token: row.try_get("deck_share_token")?,
+            deck_modified: row.try_get("deck_modified")?,
             card_id: Uuid::into(row.try_get("card_id")?),
             card_row: ScryfallCardRow {
                 json: card_json,
@@ -313,12 +496,46 @@ impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for DeckEntryRow {
             },
             copies: <i16 as TryInto<u8>>::try_into(row.try_get("copies")?).unwrap(),
             pile: row.try_get("pile")?,
+            category: row.try_get("category")?,
         };
         Ok(row)
     }
 }
 
 impl Deck {
+    /// Replaces the deck's rows in `deck_entry` with its current piles.
+    async fn insert_entries(&self, db: &mut PgConnection) -> Result<()> {
+        sqlx::query("DELETE FROM deck_entry WHERE deck_id = $1;")
+            .bind(self.id.as_uuid())
+            .execute(&mut *db)
+            .await?;
+        for (card_id, _) in self.commanders.iter() {
+            insert_deck_entry(&mut *db, self.id, *card_id, 1, "commander", None).await?;
+        }
+        for (card_id, _) in self.companions.iter() {
+            insert_deck_entry(&mut *db, self.id, *card_id, 1, "companion", None).await?;
+        }
+        for (card_id, (_, card_count)) in self.main_deck.iter() {
+            let category = self.categories.get(card_id).map(String::as_str);
+            insert_deck_entry(
+                &mut *db,
+                self.id,
+                *card_id,
+                *card_count,
+                "main_deck",
+                category,
+            )
+            .await?;
+        }
+        for (card_id, (_, card_count)) in self.sideboard.iter() {
+            insert_deck_entry(&mut *db, self.id, *card_id, *card_count, "sideboard", None).await?;
+        }
+        for (card_id, (_, card_count)) in self.maybeboard.iter() {
+            insert_deck_entry(&mut *db, self.id, *card_id, *card_count, "maybeboard", None).await?;
+        }
+        Ok(())
+    }
+
     pub async fn render<R>(
         &mut self,
         api: Arc<ScryfallApi>,
@@ -329,12 +546,23 @@ impl Deck {
         R: AsyncCommands,
     {
         let rendered = crate::tts::render_deck(api, &mut *db, redis, self).await?;
-        sqlx::query("UPDATE deck SET json = $1::jsonb WHERE id = $2;")
+        sqlx::query("UPDATE deck SET json = $1::jsonb, thumbnail = $2 WHERE id = $3;")
             .bind(serde_json::to_string(&rendered.json_description)?)
+            .bind(rendered.thumbnail.key())
             .bind(self.id.as_uuid())
             .execute(&mut *db)
             .await?;
         self.rendered_json = Some(rendered.json_description.clone());
+        self.thumbnail = Some(rendered.thumbnail.key().to_string());
+        notify_user(
+            redis,
+            self.user_id,
+            s2f::Notification::DeckThumbnail {
+                deck_id: self.id,
+                thumbnail_url: rendered.thumbnail.url()?,
+            },
+        )
+        .await?;
         Ok(rendered)
     }
 
@@ -349,11 +577,16 @@ SELECT deck.id as deck_id
      , deck.title as deck_title
      , deck.url as deck_url
      , deck.json::text as deck_json
+     , deck.render_options::text as deck_render_options
+     , deck.thumbnail as deck_thumbnail
+     , deck.share_token as deck_share_token
+     , deck.modified as deck_modified
      , deck_entry.card as card_id
      , scryfall_card.json::text as card_json
      , scryfall_card.updated_at as card_updated_at
      , deck_entry.copies as copies
      , deck_entry.pile::text as pile
+     , deck_entry.category as category
 FROM deck_entry
 INNER JOIN deck
   ON (deck.id = deck_entry.deck_id)
@@ -368,41 +601,720 @@ WHERE
 
         let mut deck = None;
         while let Some(row) = rows.next().await {
-            let row: DeckEntryRow = row?;
-            let card = row.card_row.try_into()?;
-            let deck = match deck.as_mut() {
-                None => {
-                    deck = Some(Deck {
-                        id: row.deck_id,
-                        user_id: row.user_id,
-                        title: row.deck_title,
-                        url: Url::parse(&row.deck_url)?,
-                        commanders: HashMap::new(),
-                        main_deck: HashMap::new(),
-                        sideboard: HashMap::new(),
-                        rendered_json: row.deck_json,
-                    });
-                    deck.as_mut().unwrap()
-                }
-                Some(deck_ref) => deck_ref,
-            };
-            match row.pile.as_str() {
-                "commander" => deck.commanders.insert(row.card_id, card).map(|_| ()),
-                "main_deck" => deck
-                    .main_deck
-                    .insert(row.card_id, (card, row.copies))
-                    .map(|_| ()),
-                "sideboard" => deck
-                    .sideboard
-                    .insert(row.card_id, (card, row.copies))
-                    .map(|_| ()),
-                other => Err(anyhow!("Got unexpected pile value from DB: {:?}", other))?,
-            };
+            Self::add_entry_row(&mut deck, row?)?;
+        }
+
+        Ok(deck)
+    }
+
+    pub async fn get_by_share_token(db: &mut PgConnection, token: &str) -> Result<Option<Self>> {
+        let row_opt = sqlx::query("SELECT id FROM deck WHERE share_token = $1;")
+            .bind(token)
+            .fetch_optional(&mut *db)
+            .await?;
+        match row_opt {
+            Some(row) => Self::get_by_id(&mut *db, DeckId(row.get("id"))).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Loads a past revision of the deck. The render options are the deck's
+    /// current ones, and the revision is never marked as rendered.
+    pub async fn get_revision(
+        db: impl sqlx::Executor<'_, Database = Postgres>,
+        id: DeckId,
+        revision: u32,
+    ) -> Result<Option<Self>> {
+        let mut rows = sqlx::query_as(
+            "\
+SELECT deck.id as deck_id
+     , deck.user_id as user_id
+     , deck_revision.title as deck_title
+     , deck_revision.url as deck_url
+     , NULL::text as deck_json
+     , deck.render_options::text as deck_render_options
+     , NULL::text as deck_thumbnail
+     , NULL::text as deck_share_token
+     , FALSE as deck_modified
+     , deck_revision_entry.card as card_id
+     , scryfall_card.json::text as card_json
+     , scryfall_card.updated_at as card_updated_at
+     , deck_revision_entry.copies as copies
+     , deck_revision_entry.pile::text as pile
+     , deck_revision_entry.category as category
+FROM deck_revision_entry
+INNER JOIN deck_revision
+  USING (deck_id, revision)
+INNER JOIN deck
+  ON (deck.id = deck_revision_entry.deck_id)
+INNER JOIN scryfall_card
+  ON (((scryfall_card.json ->> 'id')::uuid) = deck_revision_entry.card)
+WHERE
+  deck.id = $1 AND deck_revision.revision = $2
+;",
+        )
+        .bind(id.as_uuid())
+        .bind(revision as i32)
+        .fetch(db);
+
+        let mut deck = None;
+        while let Some(row) = rows.next().await {
+            Self::add_entry_row(&mut deck, row?)?;
         }
 
         Ok(deck)
     }
 
+    pub async fn get_version(
+        db: impl sqlx::Executor<'_, Database = Postgres>,
+        version: DeckVersion,
+    ) -> Result<Option<Self>> {
+        match version.revision {
+            Some(revision) => Self::get_revision(db, version.deck_id, revision).await,
+            None => Self::get_by_id(db, version.deck_id).await,
+        }
+    }
+
+    fn add_entry_row(deck: &mut Option<Self>, row: DeckEntryRow) -> Result<()> {
+        let card = row.card_row.try_into()?;
+        let deck = match deck.as_mut() {
+            None => {
+                *deck = Some(Deck {
+                    id: row.deck_id,
+                    user_id: row.user_id,
+                    title: row.deck_title,
+                    url: Url::parse(&row.deck_url)?,
+                    commanders: HashMap::new(),
+                    companions: HashMap::new(),
+                    main_deck: HashMap::new(),
+                    sideboard: HashMap::new(),
+                    maybeboard: HashMap::new(),
+                    categories: HashMap::new(),
+                    render_options: row.deck_render_options,
+                    rendered_json: row.deck_json,
+                    thumbnail: row.deck_thumbnail,
+                    share_token: row.deck_share_token,
+                    modified: row.deck_modified,
+                });
+                deck.as_mut().unwrap()
+            }
+            Some(deck_ref) => deck_ref,
+        };
+        if let (Some(category), "main_deck") = (row.category, row.pile.as_str()) {
+            deck.categories.insert(row.card_id, category);
+        }
+        match row.pile.as_str() {
+            "commander" => deck.commanders.insert(row.card_id, card).map(|_| ()),
+            "companion" => deck.companions.insert(row.card_id, card).map(|_| ()),
+            "main_deck" => deck
+                .main_deck
+                .insert(row.card_id, (card, row.copies))
+                .map(|_| ()),
+            "sideboard" => deck
+                .sideboard
+                .insert(row.card_id, (card, row.copies))
+                .map(|_| ()),
+            "maybeboard" => deck
+                .maybeboard
+                .insert(row.card_id, (card, row.copies))
+                .map(|_| ()),
+            other => Err(anyhow!("Got unexpected pile value from DB: {:?}", other))?,
+        };
+        Ok(())
+    }
+
+    /// Every saved revision of the deck, newest first.
+    pub async fn revisions(
+        db: impl sqlx::Executor<'_, Database = Postgres>,
+        id: DeckId,
+    ) -> Result<Vec<DeckRevision>> {
+        let mut rows = sqlx::query(
+            "\
+SELECT deck_revision.revision
+     , deck_revision.loaded_at
+     , deck_revision.title
+     , deck_revision.url
+     , COALESCE(SUM(deck_revision_entry.copies), 0)::bigint AS card_count
+FROM deck_revision
+LEFT JOIN deck_revision_entry
+  USING (deck_id, revision)
+WHERE deck_revision.deck_id = $1
+GROUP BY deck_revision.deck_id, deck_revision.revision
+ORDER BY deck_revision.revision DESC;",
+        )
+        .bind(id.as_uuid())
+        .fetch(db);
+        let mut revisions = vec![];
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            let url: String = row.get("url");
+            revisions.push(DeckRevision {
+                deck_id: id,
+                revision: row.get::<i32, _>("revision").try_into()?,
+                loaded_at: row.get("loaded_at"),
+                title: row.get("title"),
+                url: Url::parse(&url)?,
+                card_count: row.get::<i64, _>("card_count").try_into()?,
+            });
+        }
+        Ok(revisions)
+    }
+
+    /// Makes a past revision the deck's current card list, saved as a new
+    /// revision on top. The deck has to be rendered again afterwards.
+    pub async fn restore_revision(
+        &mut self,
+        db: &mut PgConnection,
+        redis: &mut impl AsyncCommands,
+        revision: u32,
+    ) -> Result<()> {
+        let restored = Self::get_revision(&mut *db, self.id, revision)
+            .await?
+            .ok_or_else(|| anyhow!("Deck {} has no revision {}", self.id, revision))?;
+        debug!("Restoring revision {} of deck {}", revision, self.id);
+        let mut tx = db.begin().await?;
+        sqlx::query("DELETE FROM deck_entry WHERE deck_id = $1;")
+            .bind(self.id.as_uuid())
+            .execute(&mut tx)
+            .await?;
+        sqlx::query(
+            "\
+INSERT INTO deck_entry ( deck_id, card, copies, pile, category )
+SELECT deck_id, card, copies, pile, category
+FROM deck_revision_entry
+WHERE deck_id = $1 AND revision = $2;",
+        )
+        .bind(self.id.as_uuid())
+        .bind(revision as i32)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(
+            "UPDATE deck SET title = $1, json = NULL, thumbnail = NULL, modified = FALSE WHERE id = $2;",
+        )
+        .bind(&restored.title)
+        .bind(self.id.as_uuid())
+        .execute(&mut tx)
+        .await?;
+        save_revision(&mut *tx, self.id, &restored.title, &restored.url).await?;
+        tx.commit().await?;
+        *self = restored;
+
+        let color_identity = deck_color_identity(&mut *db, self.id).await?;
+        notify_user(
+            redis,
+            self.user_id,
+            s2f::Notification::DeckParsed {
+                deck_id: self.id,
+                title: self.title.clone(),
+                url: self.url.clone(),
+                color_identity,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Makes the given cards the deck's commanders, and remembers the choice
+    /// for when the deck is loaded again. An empty list moves the commanders
+    /// back into the main deck and guesses again, and the next load goes back
+    /// to whatever the deck site says.
+    pub async fn set_commanders(
+        &mut self,
+        db: &mut PgConnection,
+        redis: &mut impl AsyncCommands,
+        chosen: Vec<ScryfallOracleId>,
+    ) -> Result<()> {
+        ensure!(
+            chosen.len() <= 2,
+            "A deck can have at most 2 commanders, not {}",
+            chosen.len()
+        );
+        let mut chosen_ids = Vec::with_capacity(chosen.len());
+        for oracle_id in chosen.iter() {
+            let card_id = self
+                .commanders
+                .iter()
+                .chain(self.main_deck.iter().map(|(id, (card, _))| (id, card)))
+                .find(|(_, card)| card.oracle_id().ok() == Some(*oracle_id))
+                .map(|(id, _)| *id)
+                .ok_or_else(|| anyhow!("Card {} isn't in deck {}", oracle_id, self.id))?;
+            chosen_ids.push(card_id);
+        }
+        if let [only] = chosen_ids.as_slice() {
+            let card = self
+                .commanders
+                .get(only)
+                .or_else(|| self.main_deck.get(only).map(|(card, _)| card))
+                .unwrap();
+            ensure!(
+                commanders::can_be_commander(card, true, false),
+                "{} can't be a commander",
+                card.combined_name()
+            );
+        }
+        if let [first, second] = chosen_ids.as_slice() {
+            let first = self
+                .commanders
+                .get(first)
+                .or_else(|| self.main_deck.get(first).map(|(card, _)| card))
+                .unwrap();
+            let second = self
+                .commanders
+                .get(second)
+                .or_else(|| self.main_deck.get(second).map(|(card, _)| card))
+                .unwrap();
+            ensure!(
+                commanders::can_pair(first, second)
+                    || commanders::is_oathbreaker_pair(first, second),
+                "{} and {} can't be commanders together",
+                first.combined_name(),
+                second.combined_name()
+            );
+        }
+
+        debug!("Setting commanders of deck {} to {:?}", self.id, chosen);
+        let mut tx = db.begin().await?;
+        sqlx::query("DELETE FROM deck_chosen_commander WHERE deck_id = $1;")
+            .bind(self.id.as_uuid())
+            .execute(&mut tx)
+            .await?;
+        for oracle_id in chosen.iter() {
+            sqlx::query(
+                "INSERT INTO deck_chosen_commander ( deck_id, oracle_id ) VALUES ( $1, $2 );",
+            )
+            .bind(self.id.as_uuid())
+            .bind(oracle_id.as_uuid())
+            .execute(&mut tx)
+            .await?;
+        }
+        if chosen.is_empty() {
+            commanders::assign_commanders(&mut self.commanders, &mut self.main_deck, &[]);
+            let mut by_oracle_id: HashMap<ScryfallOracleId, (String, u8)> = HashMap::new();
+            for (card, count) in self.main_deck.values() {
+                let entry = by_oracle_id
+                    .entry(card.oracle_id()?)
+                    .or_insert_with(|| (card.combined_name(), 0));
+                entry.1 = entry.1.saturating_add(*count);
+            }
+            let guessed = commanders::guess_commanders(&mut *tx, &mut by_oracle_id).await?;
+            chosen_ids = self
+                .main_deck
+                .iter()
+                .filter(|(_, (card, _))| {
+                    card.oracle_id()
+                        .map_or(false, |oracle_id| guessed.contains_key(&oracle_id))
+                })
+                .map(|(id, _)| *id)
+                .collect();
+        }
+
+        commanders::assign_commanders(&mut self.commanders, &mut self.main_deck, &chosen_ids);
+        self.insert_entries(&mut *tx).await?;
+        sqlx::query("UPDATE deck SET json = NULL, thumbnail = NULL WHERE id = $1;")
+            .bind(self.id.as_uuid())
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        self.rendered_json = None;
+        self.thumbnail = None;
+
+        let mut names: Vec<String> = self
+            .commanders
+            .values()
+            .map(|c| c.combined_name())
+            .collect();
+        names.sort();
+        notify_user(
+            redis,
+            self.user_id,
+            s2f::Notification::DeckCommandersChanged {
+                deck_id: self.id,
+                commanders: names,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// How many copies of the card are in the pile.
+    fn copies(&self, card_id: ScryfallId, pile: Pile) -> u8 {
+        let single = |cards: &HashMap<ScryfallId, ScryfallCard>| cards.contains_key(&card_id) as u8;
+        let counted = |cards: &HashMap<ScryfallId, (ScryfallCard, u8)>| {
+            cards.get(&card_id).map_or(0, |(_, count)| *count)
+        };
+        match pile {
+            Pile::Commander => single(&self.commanders),
+            Pile::Companion => single(&self.companions),
+            Pile::MainDeck => counted(&self.main_deck),
+            Pile::Sideboard => counted(&self.sideboard),
+            Pile::Maybeboard => counted(&self.maybeboard),
+        }
+    }
+
+    /// Moves a card's entry to another pile, or replaces it with another
+    /// printing, adding its copies to any that are already there.
+    async fn move_entry(
+        &self,
+        db: &mut PgConnection,
+        (from_card, from_pile): (ScryfallId, Pile),
+        (to_card, to_pile): (ScryfallId, Pile),
+    ) -> Result<()> {
+        let copies = self.copies(from_card, from_pile);
+        ensure!(
+            copies > 0,
+            "Card {} isn't in the {} of deck {}",
+            from_card,
+            from_pile.label().to_lowercase(),
+            self.id
+        );
+        check_copies(
+            to_pile,
+            u16::from(copies) + u16::from(self.copies(to_card, to_pile)),
+        )?;
+        sqlx::query(
+            "WITH moved AS (
+  DELETE FROM deck_entry
+  WHERE deck_id = $1 AND card = $2 AND pile = $3::deck_pile
+  RETURNING copies, category
+)
+INSERT INTO deck_entry ( deck_id, card, copies, pile, category )
+SELECT $1, $4, copies, $5::deck_pile, category
+FROM moved
+ON CONFLICT (deck_id, card, pile)
+  DO UPDATE SET copies = deck_entry.copies + EXCLUDED.copies;",
+        )
+        .bind(self.id.as_uuid())
+        .bind(from_card.as_uuid())
+        .bind(from_pile.slug())
+        .bind(to_card.as_uuid())
+        .bind(to_pile.slug())
+        .execute(&mut *db)
+        .await?;
+        Ok(())
+    }
+
+    /// Changes the card list by hand. The deck is marked as modified until
+    /// it's loaded from its URL again, and has to be rendered again.
+    pub async fn edit(
+        &mut self,
+        db: &mut PgConnection,
+        redis: &mut impl AsyncCommands,
+        edit: DeckEdit,
+    ) -> Result<()> {
+        debug!("Editing deck {}: {:?}", self.id, edit);
+        match edit {
+            DeckEdit::AddCard { name, pile, count } => {
+                let card = scryfall::card_by_name(&mut *db, name.trim(), false)
+                    .await?
+                    .ok_or_else(|| anyhow!("Couldn't find a card named {:?}", name))?;
+                let card_id = card.id()?;
+                ensure!(count > 0, "Can't add 0 copies of {}", card.combined_name());
+                check_copies(
+                    pile,
+                    u16::from(count) + u16::from(self.copies(card_id, pile)),
+                )?;
+                sqlx::query(
+                    "INSERT INTO deck_entry ( deck_id, card, copies, pile )
+VALUES ( $1, $2, $3, $4::deck_pile )
+ON CONFLICT (deck_id, card, pile)
+  DO UPDATE SET copies = deck_entry.copies + EXCLUDED.copies;",
+                )
+                .bind(self.id.as_uuid())
+                .bind(card_id.as_uuid())
+                .bind(count as i16)
+                .bind(pile.slug())
+                .execute(&mut *db)
+                .await?;
+            }
+            DeckEdit::SetCount { card, pile, count } => {
+                let card_id = ScryfallId::from(card);
+                ensure!(
+                    self.copies(card_id, pile) > 0,
+                    "Card {} isn't in the {} of deck {}",
+                    card_id,
+                    pile.label().to_lowercase(),
+                    self.id
+                );
+                check_copies(pile, u16::from(count))?;
+                if count == 0 {
+                    let entry_count = self.commanders.len()
+                        + self.companions.len()
+                        + self.main_deck.len()
+                        + self.sideboard.len()
+                        + self.maybeboard.len();
+                    ensure!(entry_count > 1, "A deck needs at least one card");
+                    sqlx::query(
+                        "DELETE FROM deck_entry WHERE deck_id = $1 AND card = $2 AND pile = $3::deck_pile;",
+                    )
+                    .bind(self.id.as_uuid())
+                    .bind(card_id.as_uuid())
+                    .bind(pile.slug())
+                    .execute(&mut *db)
+                    .await?;
+                } else {
+                    sqlx::query(
+                        "UPDATE deck_entry SET copies = $4
+WHERE deck_id = $1 AND card = $2 AND pile = $3::deck_pile;",
+                    )
+                    .bind(self.id.as_uuid())
+                    .bind(card_id.as_uuid())
+                    .bind(pile.slug())
+                    .bind(count as i16)
+                    .execute(&mut *db)
+                    .await?;
+                }
+            }
+            DeckEdit::MoveCard { card, from, to } => {
+                if from == to {
+                    return Ok(());
+                }
+                let card_id = ScryfallId::from(card);
+                self.move_entry(&mut *db, (card_id, from), (card_id, to))
+                    .await?;
+            }
+            DeckEdit::SetPrinting {
+                card,
+                pile,
+                printing,
+            } => {
+                if card == printing {
+                    return Ok(());
+                }
+                let (card_id, printing_id) = (ScryfallId::from(card), ScryfallId::from(printing));
+                let old = scryfall::card_by_id(&mut *db, card_id).await?;
+                let new = scryfall::card_by_id(&mut *db, printing_id).await?;
+                ensure!(
+                    old.oracle_id()? == new.oracle_id()?,
+                    "Card {} isn't a printing of {}",
+                    printing_id,
+                    old.combined_name()
+                );
+                self.move_entry(&mut *db, (card_id, pile), (printing_id, pile))
+                    .await?;
+            }
+        }
+
+        sqlx::query(
+            "UPDATE deck SET json = NULL, thumbnail = NULL, modified = TRUE WHERE id = $1;",
+        )
+        .bind(self.id.as_uuid())
+        .execute(&mut *db)
+        .await?;
+        *self = Self::get_by_id(&mut *db, self.id)
+            .await?
+            .ok_or_else(|| anyhow!("Deck {} disappeared while it was edited", self.id))?;
+
+        let color_identity = deck_color_identity(&mut *db, self.id).await?;
+        notify_user(
+            redis,
+            self.user_id,
+            s2f::Notification::DeckEdited {
+                deck_id: self.id,
+                color_identity,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Copies the deck, its card list and its commander choice into another
+    /// user's account, keeping the source URL. The copy isn't shared and has no
+    /// history before this point.
+    pub async fn copy_to_user(
+        &self,
+        db: &mut PgConnection,
+        redis: &mut impl AsyncCommands,
+        user_id: UserId,
+    ) -> Result<Deck> {
+        let mut tx = db.begin().await?;
+        let existing = sqlx::query("SELECT 1 FROM deck WHERE user_id = $1 AND url = $2;")
+            .bind(user_id.as_queryable())
+            .bind(self.url.as_str())
+            .fetch_optional(&mut tx)
+            .await?;
+        ensure!(
+            existing.is_none(),
+            "You already have a deck from {}",
+            self.url
+        );
+
+        let copy_id = DeckId(Uuid::new_v4());
+        debug!(
+            "Copying deck {} to {} for user {}",
+            self.id, copy_id, user_id
+        );
+        sqlx::query(
+            "\
+INSERT INTO deck ( id, user_id, title, url, render_options, modified )
+VALUES ( $1, $2, $3, $4, $5::jsonb, $6 );",
+        )
+        .bind(copy_id.as_uuid())
+        .bind(user_id.as_queryable())
+        .bind(&self.title)
+        .bind(self.url.as_str())
+        .bind(serde_json::to_string(&self.render_options)?)
+        .bind(self.modified)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(
+            "\
+INSERT INTO deck_entry ( deck_id, card, copies, pile, category )
+SELECT $2, card, copies, pile, category
+FROM deck_entry
+WHERE deck_id = $1;",
+        )
+        .bind(self.id.as_uuid())
+        .bind(copy_id.as_uuid())
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(
+            "\
+INSERT INTO deck_chosen_commander ( deck_id, oracle_id )
+SELECT $2, oracle_id
+FROM deck_chosen_commander
+WHERE deck_id = $1;",
+        )
+        .bind(self.id.as_uuid())
+        .bind(copy_id.as_uuid())
+        .execute(&mut tx)
+        .await?;
+        save_revision(&mut *tx, copy_id, &self.title, &self.url).await?;
+        tx.commit().await?;
+
+        notify_user(
+            redis,
+            user_id,
+            s2f::Notification::DeckParseStarted {
+                deck_id: copy_id,
+                title: self.title.clone(),
+                url: self.url.clone(),
+            },
+        )
+        .await?;
+        let color_identity = deck_color_identity(&mut *db, copy_id).await?;
+        notify_user(
+            redis,
+            user_id,
+            s2f::Notification::DeckParsed {
+                deck_id: copy_id,
+                title: self.title.clone(),
+                url: self.url.clone(),
+                color_identity,
+            },
+        )
+        .await?;
+
+        Ok(Deck {
+            id: copy_id,
+            user_id,
+            rendered_json: None,
+            thumbnail: None,
+            share_token: None,
+            ..self.clone()
+        })
+    }
+
+    /// Sharing a deck that's already shared keeps its token, so links that
+    /// were handed out keep working. Unsharing throws the token away.
+    pub async fn set_shared(
+        &mut self,
+        db: impl Executor<'_, Database = Postgres>,
+        redis: &mut impl AsyncCommands,
+        shared: bool,
+    ) -> Result<()> {
+        let share_token = match (shared, self.share_token.take()) {
+            (true, Some(token)) => Some(token),
+            (true, None) => Some(hex::encode(rand::random::<[u8; 24]>())),
+            (false, _) => None,
+        };
+        sqlx::query("UPDATE deck SET share_token = $1 WHERE id = $2;")
+            .bind(share_token.as_deref())
+            .bind(self.id.as_uuid())
+            .execute(db)
+            .await?;
+        self.share_token = share_token.clone();
+
+        notify_user(
+            redis,
+            self.user_id,
+            s2f::Notification::DeckShareChanged {
+                deck_id: self.id,
+                share_token,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// The deck as it's shown in deck lists.
+    pub async fn summary(&self, db: &mut PgConnection) -> Result<ttsmagic_types::Deck> {
+        let thumbnail_url = match self.thumbnail.as_deref().and_then(MediaFile::from_key) {
+            Some(file) => Some(file.url()?),
+            None => None,
+        };
+        Ok(ttsmagic_types::Deck {
+            id: self.id,
+            title: self.title.clone(),
+            url: self.url.clone(),
+            rendered: self.rendered_json.is_some(),
+            color_identity: deck_color_identity(&mut *db, self.id).await?,
+            render_options: self.render_options.clone(),
+            thumbnail_url,
+            share_token: self.share_token.clone(),
+            modified: self.modified,
+        })
+    }
+
+    pub async fn detail(&self, db: &mut PgConnection) -> Result<ttsmagic_types::DeckDetail> {
+        fn deck_card(
+            card: &ScryfallCard,
+            count: u8,
+            category: Option<&String>,
+        ) -> Result<ttsmagic_types::DeckCard> {
+            Ok(ttsmagic_types::DeckCard {
+                scryfall_id: card.id()?.as_uuid(),
+                oracle_id: card.oracle_id().ok().map(ScryfallOracleId::as_uuid),
+                name: card.combined_name(),
+                count,
+                type_line: card.type_line().unwrap_or("").to_string(),
+                set_code: card.set_code()?.to_string(),
+                collector_number: card.collector_number()?.to_string(),
+                image_url: card.image_url("small").ok(),
+                category: category.cloned(),
+            })
+        }
+        fn sorted(mut cards: Vec<ttsmagic_types::DeckCard>) -> Vec<ttsmagic_types::DeckCard> {
+            cards.sort_by(|a, b| a.name.cmp(&b.name));
+            cards
+        }
+        let single = |pile: &HashMap<ScryfallId, ScryfallCard>| -> Result<_> {
+            let cards = pile
+                .values()
+                .map(|card| deck_card(card, 1, None))
+                .collect::<Result<_>>()?;
+            Ok(sorted(cards))
+        };
+        let counted = |pile: &HashMap<ScryfallId, (ScryfallCard, u8)>| -> Result<_> {
+            let cards = pile
+                .iter()
+                .map(|(id, (card, count))| deck_card(card, *count, self.categories.get(id)))
+                .collect::<Result<_>>()?;
+            Ok(sorted(cards))
+        };
+        Ok(ttsmagic_types::DeckDetail {
+            deck: self.summary(&mut *db).await?,
+            commanders: single(&self.commanders)?,
+            companions: single(&self.companions)?,
+            main_deck: counted(&self.main_deck)?,
+            sideboard: counted(&self.sideboard)?,
+            maybeboard: counted(&self.maybeboard)?,
+        })
+    }
+
     pub async fn delete(
         self,
         db: impl Executor<'_, Database = Postgres>,
@@ -422,6 +1334,34 @@ WHERE
 
         Ok(())
     }
+
+    /// Changing the render options invalidates any previously rendered JSON.
+    pub async fn set_render_options(
+        &mut self,
+        db: impl Executor<'_, Database = Postgres>,
+        redis: &mut impl AsyncCommands,
+        options: RenderOptions,
+    ) -> Result<()> {
+        sqlx::query("UPDATE deck SET render_options = $1::jsonb, json = NULL WHERE id = $2;")
+            .bind(serde_json::to_string(&options)?)
+            .bind(self.id.as_uuid())
+            .execute(db)
+            .await?;
+        self.render_options = options.clone();
+        self.rendered_json = None;
+
+        notify_user(
+            redis,
+            self.user_id,
+            s2f::Notification::DeckRenderOptionsChanged {
+                deck_id: self.id,
+                options,
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
 }
 
 pub async fn get_decks_for_user(
@@ -431,6 +1371,10 @@ pub async fn get_decks_for_user(
     let mut rows = sqlx::query(
         "\
 SELECT id, user_id, title, url, (json IS NOT NULL) as rendered
+  , render_options::text AS render_options
+  , thumbnail
+  , share_token
+  , modified
   , array(
       SELECT DISTINCT jsonb_array_elements_text(sc.json -> 'color_identity') AS color_identity
       FROM deck_entry
@@ -447,6 +1391,12 @@ WHERE user_id = $1;",
         let row = row?;
         let deck_id = row.get::<Uuid, _>("id");
         let url: String = row.get("url");
+        let render_options: String = row.get("render_options");
+        let thumbnail: Option<String> = row.get("thumbnail");
+        let thumbnail_url = match thumbnail.as_deref().and_then(MediaFile::from_key) {
+            Some(file) => Some(file.url()?),
+            None => None,
+        };
         let color_identity = {
             let raw_identity = row.get::<Vec<String>, _>("color_identity");
             let mut ci = DeckColorIdentity::default();
@@ -472,6 +1422,10 @@ WHERE user_id = $1;",
             url: Url::parse(&url)?,
             rendered: row.get("rendered"),
             color_identity,
+            render_options: serde_json::from_str(&render_options)?,
+            thumbnail_url,
+            share_token: row.get("share_token"),
+            modified: row.get("modified"),
         });
     }
     decks.sort_by_key(|d| (d.title.clone(), d.url.clone()));
@@ -539,13 +1493,19 @@ pub async fn load_deck<R>(
 where
     R: AsyncCommands,
 {
+    if is_text_deck_url(&url) {
+        return Err(DeckInputError(
+            "Decks pasted in as text can't be loaded again from their URL".to_string(),
+        )
+        .into());
+    }
     let loader = match find_loader(url.clone()) {
         Ok(l) => l,
         Err(tried) => {
-            return Err(anyhow!(
+            return Err(anyhow::Error::new(DeckInputError(format!(
                 "Unknown deck site. We tried to load from the following deck sites: {}",
                 tried.join(", "),
-            ))
+            ))))
             .with_context(|| format!("Failed to load deck from URL {}", url));
         }
     };
@@ -562,3 +1522,76 @@ where
     drop(loader);
     Ok(deck)
 }
+
+/// Decks pasted in as text don't come from anywhere, but every deck needs a
+/// URL that's unique for its user.
+const TEXT_DECK_URL_PREFIX: &str = "urn:ttsmagic:text:";
+
+fn is_text_deck_url(url: &Url) -> bool {
+    url.as_str().starts_with(TEXT_DECK_URL_PREFIX)
+}
+
+/// Looks up a card named in a decklist. There being no such card is the
+/// decklist's fault, so that's a [`DeckInputError`].
+async fn oracle_id_for_entry(db: &mut PgConnection, name: &str) -> Result<ScryfallOracleId> {
+    match scryfall::oracle_id_by_name(&mut *db, name).await {
+        Err(e) if matches!(e.downcast_ref(), Some(sqlx::Error::RowNotFound)) => {
+            Err(DeckInputError(format!("Couldn't find a card named {:?}", name)).into())
+        }
+        result => result,
+    }
+}
+
+/// Looks up the cards of a decklist by name, adding up the copies of cards
+/// that are listed more than once in the same pile.
+async fn piles_from_entries(
+    db: &mut PgConnection,
+    entries: Vec<text::TextEntry>,
+) -> Result<CardPiles> {
+    let mut piles = CardPiles::default();
+    for text::TextEntry { pile, count, name } in entries {
+        debug!("Looking up oracle ID for {:?}", name);
+        let oracle_id = oracle_id_for_entry(&mut *db, &name).await?;
+        let pile = match pile {
+            text::TextPile::Commander => {
+                piles.commanders.insert(oracle_id, name);
+                continue;
+            }
+            text::TextPile::Companion => {
+                piles.companions.insert(oracle_id, name);
+                continue;
+            }
+            text::TextPile::MainDeck => &mut piles.main_deck,
+            text::TextPile::Sideboard => &mut piles.sideboard,
+            text::TextPile::Maybeboard => &mut piles.maybeboard,
+        };
+        let pile_entry = pile.entry(oracle_id).or_insert_with(|| (name, 0));
+        pile_entry.1 = pile_entry.1.saturating_add(count);
+    }
+    Ok(piles)
+}
+
+/// Creates a new deck from a pasted decklist. See [`text::parse_decklist`] for
+/// the format.
+pub async fn load_deck_from_text<R>(
+    db: &mut PgConnection,
+    redis: &mut R,
+    user: &User,
+    title: String,
+    decklist: &str,
+) -> Result<Deck>
+where
+    R: AsyncCommands,
+{
+    let entries = text::parse_decklist(decklist).map_err(DeckInputError::wrap)?;
+    // Every card is looked up before the deck is saved, so that a typo doesn't
+    // leave an empty deck behind.
+    let piles = piles_from_entries(&mut *db, entries)
+        .await
+        .with_context(|| format!("Failed to load decklist for {:?}", title))?;
+    let url = Url::parse(&format!("{}{}", TEXT_DECK_URL_PREFIX, Uuid::new_v4()))?;
+    let unparsed = UnparsedDeck::save(&mut *db, redis, user, url).await?;
+    debug!("UnparsedDeck saved: {:?}", unparsed);
+    let deck = unparsed.save_cards(db, redis, title, piles).await?;
+    Ok(deck)
+}
@@ -0,0 +1,329 @@
+//! Working out which cards are a deck's commanders: the ones the user picked,
+//! then the ones the deck site marked, and only then a guess.
+
+use anyhow::{Context, Result};
+use sqlx::PgConnection;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use crate::scryfall::{self, ScryfallCard, ScryfallOracleId};
+
+/// Guesses the commanders of a 100 card deck that's legal in Commander, by
+/// picking every legendary creature that matches the deck's color identity.
+pub(super) async fn guess_commanders(
+    db: &mut PgConnection,
+    main_deck: &mut HashMap<ScryfallOracleId, (String, u8)>,
+) -> Result<HashMap<ScryfallOracleId, String>> {
+    fn count_cards<'a>(cards: impl Iterator<Item = &'a (String, u8)>) -> usize {
+        cards.map(|(_, count)| *count as usize).sum()
+    }
+
+    let mut commanders_pile: HashMap<ScryfallOracleId, String> = HashMap::new();
+    if count_cards(main_deck.values()) != 100 {
+        return Ok(commanders_pile);
+    }
+    for (oracle_id, (name, _count)) in main_deck.iter() {
+        let legal_in_commander =
+            scryfall::check_legality_by_oracle_id(&mut *db, *oracle_id, "commander")
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to check whether {} (oracle ID: {}) is legal in commander",
+                        name, oracle_id
+                    )
+                })?;
+        if !legal_in_commander {
+            debug!(
+                "Card {} ({}) disqualified deck from commander format",
+                name, oracle_id
+            );
+            return Ok(commanders_pile);
+        }
+    }
+    let oracle_ids = main_deck.keys().copied().collect::<Vec<ScryfallOracleId>>();
+    let deck_color_identity_owned = scryfall::deck_color_identity(&mut *db, oracle_ids.as_slice())
+        .await
+        .context("Failed to get deck color identity")?
+        .into_iter()
+        .collect::<Vec<String>>();
+    let deck_color_identity_borrowed = deck_color_identity_owned
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<&str>>();
+    let deck_color_identity: &[&str] = deck_color_identity_borrowed.as_slice();
+    debug!("Looks like a commander deck. Searching for the commander now...");
+    // Dig out the commander and put it in its own pile.
+    let mut commander_ids = HashSet::new();
+    for (oracle_id, (name, count)) in main_deck.iter() {
+        if *count != 1 {
+            continue;
+        }
+        debug!(
+            "Checking whether {} (oracle ID: {}) can be a commander with deck color identity {:?}...",
+            name, oracle_id, deck_color_identity,
+        );
+        if scryfall::can_be_a_commander(&mut *db, *oracle_id, deck_color_identity)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to check whether {} (oracle ID: {}) can be a commander",
+                    name, oracle_id
+                )
+            })?
+        {
+            info!("Found potential commander {} ({})", name, oracle_id);
+            commander_ids.insert(*oracle_id);
+        }
+    }
+    for commander_id in commander_ids {
+        let (name, _) = main_deck.remove(&commander_id).unwrap();
+        commanders_pile.insert(commander_id, name);
+    }
+    Ok(commanders_pile)
+}
+
+/// Moves the chosen cards into the commanders pile and every other commander
+/// back into the main deck. If any chosen card is in neither pile, nothing is
+/// moved and those cards are returned.
+pub(super) fn assign_commanders<K, C>(
+    commanders: &mut HashMap<K, C>,
+    main_deck: &mut HashMap<K, (C, u8)>,
+    chosen: &[K],
+) -> Vec<K>
+where
+    K: Copy + Eq + Hash,
+    C: Clone,
+{
+    let missing: Vec<K> = chosen
+        .iter()
+        .filter(|k| !commanders.contains_key(k) && !main_deck.contains_key(k))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        return missing;
+    }
+    let demoted: Vec<K> = commanders
+        .keys()
+        .filter(|k| !chosen.contains(k))
+        .copied()
+        .collect();
+    for key in demoted {
+        let card = commanders.remove(&key).unwrap();
+        main_deck.entry(key).or_insert((card, 0)).1 += 1;
+    }
+    for key in chosen {
+        if commanders.contains_key(key) {
+            continue;
+        }
+        let (card, count) = main_deck.get_mut(key).unwrap();
+        commanders.insert(*key, card.clone());
+        *count -= 1;
+        if *count == 0 {
+            main_deck.remove(key);
+        }
+    }
+    missing
+}
+
+fn has_line(card: &ScryfallCard, keyword: &str) -> bool {
+    let text = card.oracle_text().unwrap_or("");
+    let reminder = format!("{} (", keyword);
+    text.lines()
+        .any(|line| line == keyword || line.starts_with(&reminder))
+}
+
+fn type_line_contains(card: &ScryfallCard, word: &str) -> bool {
+    card.type_line().map_or(false, |tl| tl.contains(word))
+}
+
+/// `planeswalkers` is for Brawl, where a legendary planeswalker can lead the
+/// deck. Backgrounds only count when `paired`.
+pub(super) fn can_be_commander(card: &ScryfallCard, planeswalkers: bool, paired: bool) -> bool {
+    let legendary = type_line_contains(card, "Legendary");
+    (legendary && type_line_contains(card, "Creature"))
+        || (legendary && planeswalkers && type_line_contains(card, "Planeswalker"))
+        || (paired && type_line_contains(card, "Background"))
+        || card
+            .oracle_text()
+            .map_or(false, |text| text.contains("can be your commander"))
+}
+
+/// Whether two cards can be commanders together: partners, a pair with
+/// "Partner with" naming each other, Friends forever, a Background with a
+/// creature that can choose one, or the Doctor with a Doctor's companion.
+pub(super) fn can_pair(first: &ScryfallCard, second: &ScryfallCard) -> bool {
+    let partner_with = |card: &ScryfallCard, other: &ScryfallCard| {
+        has_line(card, &format!("Partner with {}", other.combined_name()))
+    };
+    let background = |card: &ScryfallCard, other: &ScryfallCard| {
+        has_line(card, "Choose a Background") && type_line_contains(other, "Background")
+    };
+    let companion = |card: &ScryfallCard, other: &ScryfallCard| {
+        has_line(card, "Doctor's companion") && type_line_contains(other, "Time Lord Doctor")
+    };
+    (has_line(first, "Partner") && has_line(second, "Partner"))
+        || (has_line(first, "Friends forever") && has_line(second, "Friends forever"))
+        || (partner_with(first, second) && partner_with(second, first))
+        || background(first, second)
+        || background(second, first)
+        || companion(first, second)
+        || companion(second, first)
+}
+
+/// Oathbreaker's planeswalker and its signature spell, which share the
+/// command zone without any pairing rule.
+pub(super) fn is_oathbreaker_pair(first: &ScryfallCard, second: &ScryfallCard) -> bool {
+    let spell = |card: &ScryfallCard| {
+        type_line_contains(card, "Instant") || type_line_contains(card, "Sorcery")
+    };
+    (type_line_contains(first, "Planeswalker") && spell(second))
+        || (type_line_contains(second, "Planeswalker") && spell(first))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    use super::{assign_commanders, can_pair, is_oathbreaker_pair};
+    use crate::test_helpers::test_card;
+
+    const ID: &str = "00000000-0000-0000-0000-000000000001";
+
+    #[test]
+    fn assign_moves_cards_between_piles() {
+        let mut commanders: HashMap<&str, &str> = vec![("ezuri", "Ezuri")].into_iter().collect();
+        let mut main_deck: HashMap<&str, (&str, u8)> = vec![
+            ("thrasios", ("Thrasios", 1)),
+            ("tymna", ("Tymna", 1)),
+            ("forest", ("Forest", 30)),
+        ]
+        .into_iter()
+        .collect();
+        let missing = assign_commanders(&mut commanders, &mut main_deck, &["thrasios", "tymna"]);
+        assert!(missing.is_empty());
+        assert_eq!(commanders.len(), 2);
+        assert_eq!(commanders["thrasios"], "Thrasios");
+        assert_eq!(commanders["tymna"], "Tymna");
+        assert_eq!(main_deck.len(), 2);
+        assert_eq!(main_deck["ezuri"], ("Ezuri", 1));
+        assert_eq!(main_deck["forest"], ("Forest", 30));
+    }
+
+    #[test]
+    fn assign_leaves_piles_alone_when_a_choice_is_missing() {
+        let mut commanders: HashMap<&str, &str> = vec![("ezuri", "Ezuri")].into_iter().collect();
+        let mut main_deck: HashMap<&str, (&str, u8)> =
+            vec![("thrasios", ("Thrasios", 1)), ("forest", ("Forest", 30))]
+                .into_iter()
+                .collect();
+        let missing = assign_commanders(&mut commanders, &mut main_deck, &["thrasios", "tymna"]);
+        assert_eq!(missing, vec!["tymna"]);
+        assert_eq!(commanders.len(), 1);
+        assert_eq!(commanders["ezuri"], "Ezuri");
+        assert_eq!(main_deck.len(), 2);
+        assert_eq!(main_deck["thrasios"], ("Thrasios", 1));
+    }
+
+    #[test]
+    fn assign_nothing_restores_main_deck() {
+        let mut commanders: HashMap<&str, &str> =
+            vec![("thrasios", "Thrasios"), ("tymna", "Tymna")]
+                .into_iter()
+                .collect();
+        let mut main_deck: HashMap<&str, (&str, u8)> =
+            vec![("forest", ("Forest", 98))].into_iter().collect();
+        let missing = assign_commanders(&mut commanders, &mut main_deck, &[]);
+        assert!(missing.is_empty());
+        assert!(commanders.is_empty());
+        assert_eq!(main_deck.len(), 3);
+        assert_eq!(main_deck["thrasios"], ("Thrasios", 1));
+        assert_eq!(main_deck["tymna"], ("Tymna", 1));
+    }
+
+    #[test]
+    fn pairing_rules() {
+        let creature = "Legendary Creature \u{2014} Human";
+        let thrasios = test_card(
+            ID,
+            "Thrasios, Triton Hero",
+            json!({
+                "type_line": creature,
+                "oracle_text": "{4}: Scry 1.\nPartner (You can have two commanders if both have partner.)",
+            }),
+        );
+        let tymna = test_card(
+            ID,
+            "Tymna the Weaver",
+            json!({
+                "type_line": creature,
+                "oracle_text": "Lifelink\nPartner",
+            }),
+        );
+        let pir = test_card(
+            ID,
+            "Pir, Imaginative Rascal",
+            json!({
+                "type_line": creature,
+                "oracle_text": "Partner with Toothy, Imaginary Friend (When this creature enters, ...)",
+            }),
+        );
+        let toothy = test_card(
+            ID,
+            "Toothy, Imaginary Friend",
+            json!({
+                "type_line": creature,
+                "oracle_text": "Partner with Pir, Imaginative Rascal",
+            }),
+        );
+        let wilson = test_card(
+            ID,
+            "Wilson, Refined Grizzly",
+            json!({
+                "type_line": creature,
+                "oracle_text": "Choose a Background (You can have a Background as a second commander.)",
+            }),
+        );
+        let acolyte = test_card(
+            ID,
+            "Acolyte of Bahamut",
+            json!({ "type_line": "Legendary Enchantment \u{2014} Background" }),
+        );
+        let doctor = test_card(
+            ID,
+            "The Thirteenth Doctor",
+            json!({ "type_line": "Legendary Creature \u{2014} Time Lord Doctor" }),
+        );
+        let yaz = test_card(
+            ID,
+            "Yasmin Khan",
+            json!({
+                "type_line": creature,
+                "oracle_text": "Doctor's companion (You can have two commanders if the other is the Doctor.)",
+            }),
+        );
+        let ezuri = test_card(
+            ID,
+            "Ezuri, Claw of Progress",
+            json!({ "type_line": creature }),
+        );
+        let teferi = test_card(
+            ID,
+            "Teferi, Time Raveler",
+            json!({ "type_line": "Legendary Planeswalker \u{2014} Teferi" }),
+        );
+        let spell = test_card(ID, "Dovin's Veto", json!({ "type_line": "Instant" }));
+
+        assert!(can_pair(&thrasios, &tymna));
+        assert!(can_pair(&pir, &toothy));
+        assert!(!can_pair(&pir, &thrasios));
+        assert!(can_pair(&acolyte, &wilson));
+        assert!(can_pair(&doctor, &yaz));
+        assert!(!can_pair(&ezuri, &thrasios));
+        assert!(!can_pair(&ezuri, &acolyte));
+        assert!(is_oathbreaker_pair(&teferi, &spell));
+        assert!(!is_oathbreaker_pair(&teferi, &ezuri));
+    }
+}
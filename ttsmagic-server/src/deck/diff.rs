@@ -0,0 +1,199 @@
+//! Compares the card lists of two decks, or two revisions of the same deck.
+
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+use ttsmagic_types::{CardDiff, DeckDiff, DeckVersion, PileDiff};
+
+use super::Deck;
+use crate::scryfall::{ScryfallCard, ScryfallOracleId};
+
+/// Counts per oracle ID, along with the name to show for the card.
+fn count_by_oracle_id<'a, I>(cards: I) -> Result<HashMap<ScryfallOracleId, (String, u8)>>
+where
+    I: Iterator<Item = (&'a ScryfallCard, u8)>,
+{
+    let mut counts: HashMap<ScryfallOracleId, (String, u8)> = HashMap::new();
+    for (card, count) in cards {
+        let entry = counts
+            .entry(card.oracle_id()?)
+            .or_insert_with(|| (card.combined_name(), 0));
+        entry.1 = entry.1.saturating_add(count);
+    }
+    Ok(counts)
+}
+
+fn diff_pile(
+    old: HashMap<ScryfallOracleId, (String, u8)>,
+    new: HashMap<ScryfallOracleId, (String, u8)>,
+) -> PileDiff {
+    // Sorted by name so the output is stable.
+    let mut names = BTreeMap::new();
+    for (oracle_id, (name, _)) in old.iter().chain(new.iter()) {
+        names.entry(name.clone()).or_insert(*oracle_id);
+    }
+    let mut diff = PileDiff::default();
+    for (name, oracle_id) in names {
+        let old_count = old.get(&oracle_id).map(|(_, n)| *n).unwrap_or(0);
+        let new_count = new.get(&oracle_id).map(|(_, n)| *n).unwrap_or(0);
+        let card = CardDiff {
+            name,
+            old_count,
+            new_count,
+        };
+        match (old_count, new_count) {
+            (a, b) if a == b => (),
+            (0, _) => diff.added.push(card),
+            (_, 0) => diff.removed.push(card),
+            _ => diff.changed.push(card),
+        }
+    }
+    diff
+}
+
+pub fn diff_decks(
+    old_version: DeckVersion,
+    old: &Deck,
+    new_version: DeckVersion,
+    new: &Deck,
+) -> Result<DeckDiff> {
+    let commanders = diff_pile(
+        count_by_oracle_id(old.commanders.values().map(|c| (c, 1)))?,
+        count_by_oracle_id(new.commanders.values().map(|c| (c, 1)))?,
+    );
+    let companions = diff_pile(
+        count_by_oracle_id(old.companions.values().map(|c| (c, 1)))?,
+        count_by_oracle_id(new.companions.values().map(|c| (c, 1)))?,
+    );
+    let main_deck = diff_pile(
+        count_by_oracle_id(old.main_deck.values().map(|(c, n)| (c, *n)))?,
+        count_by_oracle_id(new.main_deck.values().map(|(c, n)| (c, *n)))?,
+    );
+    let sideboard = diff_pile(
+        count_by_oracle_id(old.sideboard.values().map(|(c, n)| (c, *n)))?,
+        count_by_oracle_id(new.sideboard.values().map(|(c, n)| (c, *n)))?,
+    );
+    Ok(DeckDiff {
+        old: old_version,
+        new: new_version,
+        commanders,
+        companions,
+        main_deck,
+        sideboard,
+    })
+}
+
+/// The diff as text for the `diff-decks` command, one line per card.
+pub fn diff_to_text(diff: &DeckDiff) -> String {
+    let mut out = String::new();
+    let piles = [
+        ("Commanders", &diff.commanders),
+        ("Companion", &diff.companions),
+        ("Main deck", &diff.main_deck),
+        ("Sideboard", &diff.sideboard),
+    ];
+    for (label, pile) in piles.iter() {
+        if pile.is_empty() {
+            continue;
+        }
+        writeln!(out, "{}:", label).unwrap();
+        for card in pile.added.iter() {
+            writeln!(out, "  + {} {}", card.new_count, card.name).unwrap();
+        }
+        for card in pile.removed.iter() {
+            writeln!(out, "  - {} {}", card.old_count, card.name).unwrap();
+        }
+        for card in pile.changed.iter() {
+            writeln!(
+                out,
+                "  ~ {} {} (was {})",
+                card.new_count, card.name, card.old_count
+            )
+            .unwrap();
+        }
+    }
+    if out.is_empty() {
+        out.push_str("No differences\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use ttsmagic_types::DeckVersion;
+
+    use super::{diff_decks, diff_to_text};
+    use crate::test_helpers::{test_card, test_deck};
+
+    #[test]
+    fn diff_by_oracle_id() {
+        let ezuri = test_card(
+            "00000000-0000-0000-0000-000000000001",
+            "Ezuri, Claw of Progress",
+            json!({ "oracle_id": "10000000-0000-0000-0000-000000000001" }),
+        );
+        let forest_znr = test_card(
+            "00000000-0000-0000-0000-000000000002",
+            "Forest",
+            json!({ "oracle_id": "10000000-0000-0000-0000-000000000002" }),
+        );
+        let forest_khm = test_card(
+            "00000000-0000-0000-0000-000000000003",
+            "Forest",
+            json!({ "oracle_id": "10000000-0000-0000-0000-000000000002" }),
+        );
+        let island = test_card(
+            "00000000-0000-0000-0000-000000000004",
+            "Island",
+            json!({ "oracle_id": "10000000-0000-0000-0000-000000000004" }),
+        );
+        let sol_ring_c20 = test_card(
+            "00000000-0000-0000-0000-000000000005",
+            "Sol Ring",
+            json!({ "oracle_id": "10000000-0000-0000-0000-000000000005" }),
+        );
+        let sol_ring_cmr = test_card(
+            "00000000-0000-0000-0000-000000000006",
+            "Sol Ring",
+            json!({ "oracle_id": "10000000-0000-0000-0000-000000000005" }),
+        );
+        let treasure_map = test_card(
+            "00000000-0000-0000-0000-000000000007",
+            "Treasure Map",
+            json!({ "oracle_id": "10000000-0000-0000-0000-000000000007" }),
+        );
+
+        let old = test_deck(
+            vec![ezuri.clone()],
+            vec![(forest_znr, 10), (island, 5), (sol_ring_c20, 1)],
+            vec![],
+        );
+        let new = test_deck(
+            vec![ezuri],
+            vec![(forest_khm, 12), (sol_ring_cmr, 1), (treasure_map, 1)],
+            vec![],
+        );
+        let old_version = DeckVersion {
+            deck_id: old.id,
+            revision: Some(1),
+        };
+        let new_version = DeckVersion {
+            deck_id: new.id,
+            revision: None,
+        };
+        let diff = diff_decks(old_version, &old, new_version, &new).unwrap();
+        assert!(diff.commanders.is_empty());
+        assert!(diff.sideboard.is_empty());
+        assert_eq!(
+            diff_to_text(&diff),
+            "Main deck:\n  + 1 Treasure Map\n  - 5 Island\n  ~ 12 Forest (was 10)\n"
+        );
+        assert_eq!(
+            diff_to_text(&diff_decks(old_version, &old, old_version, &old).unwrap()),
+            "No differences\n"
+        );
+    }
+}
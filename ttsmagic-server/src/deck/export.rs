@@ -0,0 +1,257 @@
+//! Decklists in formats other than TTS JSON: plain text, CSV, and the format
+//! MTG Arena imports.
+
+use anyhow::Result;
+use std::fmt::Write;
+
+use super::Deck;
+use crate::scryfall::ScryfallCard;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    PlainText,
+    Csv,
+    Arena,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::PlainText | ExportFormat::Arena => "text/plain; charset=utf-8",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn filename(self, deck_title: &str) -> String {
+        match self {
+            ExportFormat::PlainText => format!("{}.txt", deck_title),
+            ExportFormat::Csv => format!("{}.csv", deck_title),
+            ExportFormat::Arena => format!("{} (Arena).txt", deck_title),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Section {
+    Commander,
+    Companion,
+    MainDeck,
+    Sideboard,
+    Maybeboard,
+}
+
+impl Section {
+    fn label(self, format: ExportFormat) -> &'static str {
+        match (self, format) {
+            (Section::Commander, _) => "Commander",
+            (Section::Companion, _) => "Companion",
+            (Section::MainDeck, ExportFormat::Arena) => "Deck",
+            (Section::MainDeck, _) => "Main deck",
+            (Section::Sideboard, _) => "Sideboard",
+            (Section::Maybeboard, _) => "Maybeboard",
+        }
+    }
+
+    /// Matches the `deck_pile` values in the database.
+    fn csv_value(self) -> &'static str {
+        match self {
+            Section::Commander => "commander",
+            Section::Companion => "companion",
+            Section::MainDeck => "main_deck",
+            Section::Sideboard => "sideboard",
+            Section::Maybeboard => "maybeboard",
+        }
+    }
+}
+
+fn sections(deck: &Deck) -> Vec<(Section, Vec<(&ScryfallCard, u8)>)> {
+    let mut commanders: Vec<_> = deck.commanders.values().map(|c| (c, 1)).collect();
+    let mut companions: Vec<_> = deck.companions.values().map(|c| (c, 1)).collect();
+    let mut main_deck: Vec<_> = deck.main_deck.values().map(|(c, n)| (c, *n)).collect();
+    let mut sideboard: Vec<_> = deck.sideboard.values().map(|(c, n)| (c, *n)).collect();
+    let mut maybeboard: Vec<_> = deck.maybeboard.values().map(|(c, n)| (c, *n)).collect();
+    for pile in [
+        &mut commanders,
+        &mut companions,
+        &mut main_deck,
+        &mut sideboard,
+        &mut maybeboard,
+    ]
+    .iter_mut()
+    {
+        pile.sort_by_key(|(c, _)| c.combined_name());
+    }
+    vec![
+        (Section::Commander, commanders),
+        (Section::Companion, companions),
+        (Section::MainDeck, main_deck),
+        (Section::Sideboard, sideboard),
+        (Section::Maybeboard, maybeboard),
+    ]
+    .into_iter()
+    .filter(|(_, cards)| !cards.is_empty())
+    .collect()
+}
+
+/// Arena only uses the full "A // B" name for cards with both halves on the
+/// front, and the front face's name for everything else.
+fn arena_name(card: &ScryfallCard) -> String {
+    match card.layout() {
+        Ok("split") | Ok("aftermath") => card.combined_name(),
+        _ => card.names().first().clone(),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn export_sections(
+    sections: &[(Section, Vec<(&ScryfallCard, u8)>)],
+    format: ExportFormat,
+) -> Result<String> {
+    let mut out = String::new();
+    if format == ExportFormat::Csv {
+        writeln!(out, "pile,count,name,set,collector_number,scryfall_id")?;
+    }
+    for (i, (section, cards)) in sections.iter().enumerate() {
+        if format != ExportFormat::Csv {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}", section.label(format))?;
+        }
+        for (card, count) in cards.iter() {
+            let set_code = card.set_code()?.to_uppercase();
+            let collector_number = card.collector_number()?;
+            match format {
+                ExportFormat::PlainText => writeln!(
+                    out,
+                    "{} {} ({}) {}",
+                    count,
+                    card.combined_name(),
+                    set_code,
+                    collector_number
+                )?,
+                ExportFormat::Arena => writeln!(
+                    out,
+                    "{} {} ({}) {}",
+                    count,
+                    arena_name(card),
+                    set_code,
+                    collector_number
+                )?,
+                ExportFormat::Csv => writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    section.csv_value(),
+                    count,
+                    csv_field(&card.combined_name()),
+                    set_code,
+                    csv_field(collector_number),
+                    card.id()?
+                )?,
+            }
+        }
+    }
+    Ok(out)
+}
+
+pub fn export_deck(deck: &Deck, format: ExportFormat) -> Result<String> {
+    export_sections(&sections(deck), format)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{export_sections, ExportFormat, Section};
+    use crate::{
+        deck::text::{parse_decklist, TextPile},
+        test_helpers::test_card,
+    };
+
+    #[test]
+    fn export_formats() {
+        let commander = test_card(
+            "00000000-0000-0000-0000-000000000001",
+            "Esika, God of the Tree // The Prismatic Bridge",
+            json!({ "layout": "modal_dfc", "set": "khm", "collector_number": "168" }),
+        );
+        let split = test_card(
+            "00000000-0000-0000-0000-000000000002",
+            "Fire // Ice",
+            json!({ "layout": "split", "set": "mh2", "collector_number": "290" }),
+        );
+        let forest = test_card(
+            "00000000-0000-0000-0000-000000000003",
+            "Forest",
+            json!({ "layout": "normal", "set": "znr", "collector_number": "280" }),
+        );
+        let sections = vec![
+            (Section::Commander, vec![(&commander, 1)]),
+            (Section::MainDeck, vec![(&split, 1), (&forest, 30)]),
+        ];
+
+        assert_eq!(
+            export_sections(&sections, ExportFormat::PlainText).unwrap(),
+            "Commander\n\
+             1 Esika, God of the Tree // The Prismatic Bridge (KHM) 168\n\
+             \n\
+             Main deck\n\
+             1 Fire // Ice (MH2) 290\n\
+             30 Forest (ZNR) 280\n"
+        );
+        assert_eq!(
+            export_sections(&sections, ExportFormat::Arena).unwrap(),
+            "Commander\n\
+             1 Esika, God of the Tree (KHM) 168\n\
+             \n\
+             Deck\n\
+             1 Fire // Ice (MH2) 290\n\
+             30 Forest (ZNR) 280\n"
+        );
+        assert_eq!(
+            export_sections(&sections, ExportFormat::Csv).unwrap(),
+            "pile,count,name,set,collector_number,scryfall_id\n\
+             commander,1,\"Esika, God of the Tree // The Prismatic Bridge\",KHM,168,00000000-0000-0000-0000-000000000001\n\
+             main_deck,1,Fire // Ice,MH2,290,00000000-0000-0000-0000-000000000002\n\
+             main_deck,30,Forest,ZNR,280,00000000-0000-0000-0000-000000000003\n"
+        );
+    }
+
+    #[test]
+    fn maybeboard_survives_a_text_round_trip() {
+        let forest = test_card(
+            "00000000-0000-0000-0000-000000000003",
+            "Forest",
+            json!({ "layout": "normal", "set": "znr", "collector_number": "280" }),
+        );
+        let crypt = test_card(
+            "00000000-0000-0000-0000-000000000004",
+            "Tormod's Crypt",
+            json!({ "layout": "normal", "set": "c21", "collector_number": "258" }),
+        );
+        let sections = vec![
+            (Section::MainDeck, vec![(&forest, 30)]),
+            (Section::Maybeboard, vec![(&crypt, 1)]),
+        ];
+
+        assert!(export_sections(&sections, ExportFormat::Csv)
+            .unwrap()
+            .ends_with(
+                "maybeboard,1,Tormod's Crypt,C21,258,00000000-0000-0000-0000-000000000004\n"
+            ));
+        for format in [ExportFormat::PlainText, ExportFormat::Arena].iter() {
+            let exported = export_sections(&sections, *format).unwrap();
+            let parsed = parse_decklist(&exported).unwrap();
+            assert_eq!(parsed.len(), 2);
+            assert_eq!(parsed[1].pile, TextPile::Maybeboard);
+            assert_eq!(parsed[1].name, "Tormod's Crypt");
+        }
+    }
+}
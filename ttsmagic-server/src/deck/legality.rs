@@ -0,0 +1,431 @@
+//! Checks a deck against the deck building rules of a format.
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use ttsmagic_types::{DeckColorIdentity, Format, LegalityProblem, LegalityReport};
+
+use super::{
+    commanders::{can_be_commander, can_pair},
+    Deck,
+};
+use crate::scryfall::{ScryfallCard, ScryfallOracleId};
+
+struct Rules {
+    min_cards: u32,
+    max_cards: Option<u32>,
+    /// `None` for formats without a sideboard, where it's ignored entirely.
+    max_sideboard: Option<u32>,
+    copy_limit: u32,
+    has_commander: bool,
+}
+
+fn rules(format: Format) -> Rules {
+    match format {
+        Format::Standard
+        | Format::Pioneer
+        | Format::Modern
+        | Format::Legacy
+        | Format::Vintage
+        | Format::Pauper => Rules {
+            min_cards: 60,
+            max_cards: None,
+            max_sideboard: Some(15),
+            copy_limit: 4,
+            has_commander: false,
+        },
+        Format::Commander => Rules {
+            min_cards: 100,
+            max_cards: Some(100),
+            max_sideboard: None,
+            copy_limit: 1,
+            has_commander: true,
+        },
+        Format::Brawl => Rules {
+            min_cards: 60,
+            max_cards: Some(60),
+            max_sideboard: None,
+            copy_limit: 1,
+            has_commander: true,
+        },
+    }
+}
+
+/// Basic lands and cards like Relentless Rats aren't limited, and a few cards
+/// like Seven Dwarves have their own limit.
+fn copy_limit(card: &ScryfallCard, default: u32) -> Option<u32> {
+    if card
+        .type_line()
+        .map_or(false, |tl| tl.starts_with("Basic "))
+    {
+        return None;
+    }
+    let text = card.oracle_text().unwrap_or("");
+    if text.contains("A deck can have any number of cards named") {
+        return None;
+    }
+    const UP_TO: &[(&str, u32)] = &[("seven", 7), ("nine", 9)];
+    for (word, limit) in UP_TO {
+        if text.contains(&format!("A deck can have up to {} cards named", word)) {
+            return Some(*limit);
+        }
+    }
+    Some(default)
+}
+
+fn within_identity(card: DeckColorIdentity, commander: DeckColorIdentity) -> bool {
+    (!card.black || commander.black)
+        && (!card.blue || commander.blue)
+        && (!card.green || commander.green)
+        && (!card.red || commander.red)
+        && (!card.white || commander.white)
+}
+
+fn commander_problems(
+    deck: &Deck,
+    format: Format,
+    problems: &mut Vec<LegalityProblem>,
+) -> Result<()> {
+    let mut commanders: Vec<&ScryfallCard> = deck.commanders.values().collect();
+    commanders.sort_by_key(|c| c.combined_name());
+    match commanders.as_slice() {
+        [] => problems.push(LegalityProblem::MissingCommander),
+        [commander] => {
+            if !can_be_commander(commander, format == Format::Brawl, false) {
+                problems.push(LegalityProblem::InvalidCommander {
+                    card: commander.combined_name(),
+                });
+            }
+        }
+        [first, second] => {
+            for commander in commanders.iter() {
+                if !can_be_commander(commander, format == Format::Brawl, true) {
+                    problems.push(LegalityProblem::InvalidCommander {
+                        card: commander.combined_name(),
+                    });
+                }
+            }
+            if !can_pair(first, second) {
+                problems.push(LegalityProblem::InvalidPartners {
+                    first: first.combined_name(),
+                    second: second.combined_name(),
+                });
+            }
+        }
+        _ => problems.push(LegalityProblem::TooManyCommanders {
+            commanders: commanders.len() as u32,
+        }),
+    }
+    if commanders.is_empty() {
+        return Ok(());
+    }
+
+    let mut identity = DeckColorIdentity::default();
+    for commander in commanders.iter() {
+        let ci = commander.color_identity()?;
+        identity.black |= ci.black;
+        identity.blue |= ci.blue;
+        identity.green |= ci.green;
+        identity.red |= ci.red;
+        identity.white |= ci.white;
+    }
+    let mut outside: Vec<String> = vec![];
+    let cards = deck
+        .main_deck
+        .values()
+        .map(|(c, _)| c)
+        .chain(deck.companions.values());
+    for card in cards {
+        if !within_identity(card.color_identity()?, identity) {
+            outside.push(card.combined_name());
+        }
+    }
+    outside.sort();
+    outside.dedup();
+    problems.extend(
+        outside
+            .into_iter()
+            .map(|card| LegalityProblem::OutsideColorIdentity { card }),
+    );
+    Ok(())
+}
+
+pub fn validate_deck(deck: &Deck, format: Format) -> Result<LegalityReport> {
+    let rules = rules(format);
+    let mut problems = vec![];
+
+    let mut piles: Vec<(&ScryfallCard, u8)> =
+        deck.main_deck.values().map(|(c, n)| (c, *n)).collect();
+    if rules.has_commander {
+        piles.extend(deck.commanders.values().map(|c| (c, 1)));
+    } else {
+        piles.extend(deck.sideboard.values().map(|(c, n)| (c, *n)));
+    }
+    piles.extend(deck.companions.values().map(|c| (c, 1)));
+    // Copies are counted across printings and piles, sorted by name so the
+    // report is stable.
+    let mut copies: HashMap<ScryfallOracleId, (&ScryfallCard, u32)> = HashMap::new();
+    for (card, count) in piles {
+        copies.entry(card.oracle_id()?).or_insert((card, 0)).1 += count as u32;
+    }
+    let by_name: BTreeMap<String, (&ScryfallCard, u32)> = copies
+        .into_iter()
+        .map(|(_, (card, count))| (card.combined_name(), (card, count)))
+        .collect();
+
+    for (name, (card, count)) in by_name.iter() {
+        match card.legality(format.slug())? {
+            "banned" => problems.push(LegalityProblem::Banned { card: name.clone() }),
+            "not_legal" => problems.push(LegalityProblem::NotLegal { card: name.clone() }),
+            "restricted" if *count > 1 => problems.push(LegalityProblem::Restricted {
+                card: name.clone(),
+                copies: *count,
+            }),
+            _ => (),
+        }
+    }
+    for (name, (card, count)) in by_name.iter() {
+        match copy_limit(card, rules.copy_limit) {
+            Some(limit) if *count > limit => problems.push(LegalityProblem::TooManyCopies {
+                card: name.clone(),
+                copies: *count,
+                limit,
+            }),
+            _ => (),
+        }
+    }
+
+    let mut deck_size: u32 = deck.main_deck.values().map(|(_, n)| *n as u32).sum();
+    if rules.has_commander {
+        deck_size += deck.commanders.len() as u32;
+    }
+    if deck_size < rules.min_cards {
+        problems.push(LegalityProblem::DeckTooSmall {
+            cards: deck_size,
+            minimum: rules.min_cards,
+        });
+    }
+    match rules.max_cards {
+        Some(maximum) if deck_size > maximum => problems.push(LegalityProblem::DeckTooLarge {
+            cards: deck_size,
+            maximum,
+        }),
+        _ => (),
+    }
+    if let Some(maximum) = rules.max_sideboard {
+        // A companion starts the game outside the deck, but still takes up a
+        // sideboard slot.
+        let sideboard_size: u32 = deck.sideboard.values().map(|(_, n)| *n as u32).sum::<u32>()
+            + deck.companions.len() as u32;
+        if sideboard_size > maximum {
+            problems.push(LegalityProblem::SideboardTooLarge {
+                cards: sideboard_size,
+                maximum,
+            });
+        }
+    }
+
+    if rules.has_commander {
+        commander_problems(deck, format, &mut problems)?;
+    }
+
+    Ok(LegalityReport { format, problems })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use ttsmagic_types::{Format, LegalityProblem};
+
+    use super::validate_deck;
+    use crate::test_helpers::{test_card, test_deck};
+
+    fn legal() -> Value {
+        json!({"commander": "legal", "modern": "legal", "vintage": "legal"})
+    }
+
+    #[test]
+    fn commander_deck() {
+        let ezuri = test_card(
+            "00000000-0000-0000-0000-000000000001",
+            "Ezuri, Claw of Progress",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000001",
+                "type_line": "Legendary Creature \u{2014} Phyrexian Elf Warrior",
+                "color_identity": ["G", "U"],
+                "legalities": legal(),
+            }),
+        );
+        let forest = test_card(
+            "00000000-0000-0000-0000-000000000002",
+            "Forest",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000002",
+                "type_line": "Basic Land \u{2014} Forest",
+                "color_identity": [],
+                "legalities": legal(),
+            }),
+        );
+        let sol_ring = test_card(
+            "00000000-0000-0000-0000-000000000003",
+            "Sol Ring",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000003",
+                "type_line": "Artifact",
+                "color_identity": [],
+                "legalities": legal(),
+            }),
+        );
+        let bolt = test_card(
+            "00000000-0000-0000-0000-000000000004",
+            "Lightning Bolt",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000004",
+                "type_line": "Instant",
+                "color_identity": ["R"],
+                "legalities": legal(),
+            }),
+        );
+        let rats = test_card(
+            "00000000-0000-0000-0000-000000000005",
+            "Relentless Rats",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000005",
+                "type_line": "Creature \u{2014} Rat",
+                "oracle_text": "A deck can have any number of cards named Relentless Rats.",
+                "color_identity": ["B"],
+                "legalities": legal(),
+            }),
+        );
+        let sway = test_card(
+            "00000000-0000-0000-0000-000000000006",
+            "Sway of the Stars",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000006",
+                "type_line": "Sorcery",
+                "color_identity": ["U"],
+                "legalities": json!({"commander": "banned"}),
+            }),
+        );
+
+        let legal_deck = test_deck(
+            vec![ezuri.clone()],
+            vec![(forest.clone(), 98), (sol_ring.clone(), 1)],
+            vec![],
+        );
+        assert_eq!(
+            validate_deck(&legal_deck, Format::Commander)
+                .unwrap()
+                .problems,
+            vec![]
+        );
+
+        let problems = validate_deck(
+            &test_deck(
+                vec![ezuri],
+                vec![
+                    (forest, 80),
+                    (sol_ring, 2),
+                    (bolt, 1),
+                    (rats, 20),
+                    (sway, 1),
+                ],
+                vec![],
+            ),
+            Format::Commander,
+        )
+        .unwrap()
+        .problems;
+        assert_eq!(
+            problems,
+            vec![
+                LegalityProblem::Banned {
+                    card: "Sway of the Stars".to_string()
+                },
+                LegalityProblem::TooManyCopies {
+                    card: "Sol Ring".to_string(),
+                    copies: 2,
+                    limit: 1
+                },
+                LegalityProblem::DeckTooLarge {
+                    cards: 105,
+                    maximum: 100
+                },
+                LegalityProblem::OutsideColorIdentity {
+                    card: "Lightning Bolt".to_string()
+                },
+                LegalityProblem::OutsideColorIdentity {
+                    card: "Relentless Rats".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn constructed_deck() {
+        let island = test_card(
+            "00000000-0000-0000-0000-000000000001",
+            "Island",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000001",
+                "type_line": "Basic Land \u{2014} Island",
+                "color_identity": [],
+                "legalities": legal(),
+            }),
+        );
+        let ponder = test_card(
+            "00000000-0000-0000-0000-000000000002",
+            "Ponder",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000002",
+                "type_line": "Sorcery",
+                "color_identity": ["U"],
+                "legalities": json!({"modern": "not_legal",
+                "vintage": "restricted"}),
+            }),
+        );
+        let opt = test_card(
+            "00000000-0000-0000-0000-000000000003",
+            "Opt",
+            json!({
+                "oracle_id": "10000000-0000-0000-0000-000000000003",
+                "type_line": "Instant",
+                "color_identity": ["U"],
+                "legalities": legal(),
+            }),
+        );
+
+        let modern = test_deck(
+            vec![],
+            vec![(island.clone(), 50), (opt.clone(), 5)],
+            vec![(opt.clone(), 1), (ponder.clone(), 2), (island.clone(), 13)],
+        );
+        assert_eq!(
+            validate_deck(&modern, Format::Modern).unwrap().problems,
+            vec![
+                LegalityProblem::NotLegal {
+                    card: "Ponder".to_string()
+                },
+                LegalityProblem::TooManyCopies {
+                    card: "Opt".to_string(),
+                    copies: 6,
+                    limit: 4
+                },
+                LegalityProblem::DeckTooSmall {
+                    cards: 55,
+                    minimum: 60
+                },
+                LegalityProblem::SideboardTooLarge {
+                    cards: 16,
+                    maximum: 15
+                },
+            ]
+        );
+        assert_eq!(
+            validate_deck(&modern, Format::Vintage).unwrap().problems[0],
+            LegalityProblem::Restricted {
+                card: "Ponder".to_string(),
+                copies: 2
+            }
+        );
+    }
+}
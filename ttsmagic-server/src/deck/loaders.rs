@@ -4,89 +4,18 @@ use redis::AsyncCommands;
 use scraper::{Html, Selector};
 use serde::Deserialize;
 use sqlx::PgConnection;
-use std::{
-    collections::{HashMap, HashSet},
-    str::FromStr,
-};
+use std::str::FromStr;
 use url::Url;
 
 use crate::{
-    deck::{Deck, DeckMatcher, DeckParser, UnparsedDeck},
-    scryfall::{self, ScryfallOracleId},
+    deck::{
+        oracle_id_for_entry, piles_from_entries,
+        text::{TextEntry, TextPile},
+        CardPiles, Deck, DeckInputError, DeckMatcher, DeckParser, UnparsedDeck,
+    },
+    scryfall::{self, ScryfallId},
 };
 
-async fn extract_commanders(
-    db: &mut PgConnection,
-    main_deck: &mut HashMap<ScryfallOracleId, (String, u8)>,
-) -> Result<HashMap<ScryfallOracleId, String>> {
-    fn count_cards<'a>(cards: impl Iterator<Item = &'a (String, u8)>) -> usize {
-        cards.map(|(_, count)| *count as usize).sum()
-    }
-
-    let mut commanders_pile: HashMap<ScryfallOracleId, String> = HashMap::new();
-    if count_cards(main_deck.values()) != 100 {
-        return Ok(commanders_pile);
-    }
-    for (oracle_id, (name, _count)) in main_deck.iter() {
-        let legal_in_commander =
-            scryfall::check_legality_by_oracle_id(&mut *db, *oracle_id, "commander")
-                .await
-                .with_context(|| {
-                    format!(
-                        "Failed to check whether {} (oracle ID: {}) is legal in commander",
-                        name, oracle_id
-                    )
-                })?;
-        if !legal_in_commander {
-            debug!(
-                "Card {} ({}) disqualified deck from commander format",
-                name, oracle_id
-            );
-            return Ok(commanders_pile);
-        }
-    }
-    let oracle_ids = main_deck.keys().copied().collect::<Vec<ScryfallOracleId>>();
-    let deck_color_identity_owned = scryfall::deck_color_identity(&mut *db, oracle_ids.as_slice())
-        .await
-        .context("Failed to get deck color identity")?
-        .into_iter()
-        .collect::<Vec<String>>();
-    let deck_color_identity_borrowed = deck_color_identity_owned
-        .iter()
-        .map(String::as_str)
-        .collect::<Vec<&str>>();
-    let deck_color_identity: &[&str] = deck_color_identity_borrowed.as_slice();
-    debug!("Looks like a commander deck. Searching for the commander now...");
-    // Dig out the commander and put it in its own pile.
-    let mut commander_ids = HashSet::new();
-    for (oracle_id, (name, count)) in main_deck.iter() {
-        if *count != 1 {
-            continue;
-        }
-        debug!(
-            "Checking whether {} (oracle ID: {}) can be a commander with deck color identity {:?}...",
-            name, oracle_id, deck_color_identity,
-        );
-        if scryfall::can_be_a_commander(&mut *db, *oracle_id, deck_color_identity)
-            .await
-            .with_context(|| {
-                format!(
-                    "Failed to check whether {} (oracle ID: {}) can be a commander",
-                    name, oracle_id
-                )
-            })?
-        {
-            info!("Found potential commander {} ({})", name, oracle_id);
-            commander_ids.insert(*oracle_id);
-        }
-    }
-    for commander_id in commander_ids {
-        let (name, _) = main_deck.remove(&commander_id).unwrap();
-        commanders_pile.insert(commander_id, name);
-    }
-    Ok(commanders_pile)
-}
-
 fn get_text(elem_ref: scraper::ElementRef<'_>) -> String {
     let text_parts: Vec<&str> = elem_ref.text().collect();
     text_parts.join("").trim().to_string()
@@ -96,6 +25,69 @@ pub(crate) struct DeckboxLoader {
     id: u32,
 }
 
+/// Reads the title and cards off a Deckbox set page. Deckbox doesn't mark
+/// commanders or companions, so commanders are always guessed and a companion
+/// stays in the sideboard, where Deckbox users list it.
+fn parse_deckbox_html(html_string: &str) -> Result<(String, Vec<TextEntry>)> {
+    let title_selector = Selector::parse(".page_header > .section_title > span").unwrap();
+    let main_card_rows_selector = Selector::parse("table.set_cards.main tr[id]").unwrap();
+    let sideboard_card_rows_selector = Selector::parse("table.set_cards.sideboard tr[id]").unwrap();
+    let card_name_selector = Selector::parse("td.card_name").unwrap();
+    let card_count_selector = Selector::parse("td.card_count").unwrap();
+
+    let html = Html::parse_document(html_string);
+    let title = {
+        let mut matches = html.select(&title_selector).into_iter();
+        let matched = matches
+            .next()
+            .ok_or_else(|| anyhow!("No match for {:?}", title_selector))?;
+        let s = get_text(matched);
+        anyhow::ensure!(
+            !s.is_empty(),
+            "Found empty string where deck title was expected!"
+        );
+        s
+    };
+
+    let mut entries = Vec::with_capacity(110);
+    let sections = [
+        (&main_card_rows_selector, TextPile::MainDeck),
+        (&sideboard_card_rows_selector, TextPile::Sideboard),
+    ];
+    for (selector, pile) in sections.iter() {
+        for row_ref in html.select(selector) {
+            let row = row_ref.value();
+            let card_id = match row.attr("id") {
+                None => continue,
+                Some(row_id_str) => match u64::from_str(row_id_str) {
+                    Ok(row_id) => row_id,
+                    Err(_) => continue,
+                },
+            };
+            let card_name_ref = row_ref.select(&card_name_selector).next().ok_or_else(|| {
+                anyhow!("No card name found for Deckbox card with ID {}", card_id)
+            })?;
+            let card_name = get_text(card_name_ref);
+
+            let mut card_count: u8 = 0;
+            if let Some(card_count_ref) = row_ref.select(&card_count_selector).next() {
+                let card_count_str = get_text(card_count_ref);
+                match u8::from_str(&card_count_str) {
+                    Ok(c) => card_count = c,
+                    Err(_) => (),
+                };
+            }
+
+            entries.push(TextEntry {
+                pile: *pile,
+                count: card_count,
+                name: card_name.trim().to_string(),
+            });
+        }
+    }
+    Ok((title, entries))
+}
+
 impl DeckMatcher for DeckboxLoader {
     fn match_url(url: &Url) -> Option<Self> {
         match (url.domain(), url.path_segments()) {
@@ -135,89 +127,20 @@ impl<R: AsyncCommands> DeckParser<R> for DeckboxLoader {
         unparsed: UnparsedDeck,
     ) -> LocalBoxFuture<'a, Result<Deck>> {
         Box::pin(async move {
-            let title_selector = Selector::parse(".page_header > .section_title > span").unwrap();
-            let main_card_rows_selector = Selector::parse("table.set_cards.main tr[id]").unwrap();
-            let sideboard_card_rows_selector =
-                Selector::parse("table.set_cards.sideboard tr[id]").unwrap();
-            let card_name_selector = Selector::parse("td.card_name").unwrap();
-            let card_count_selector = Selector::parse("td.card_count").unwrap();
-
             let url = format!("https://deckbox.org/sets/{}", self.id);
             info!("Parsing Deckbox.org deck at {}", url);
             let client = surf::Client::new();
             let request = client.get(&url);
             let mut response = request.await.map_err(Error::msg)?;
             let html_string = response.body_string().await.map_err(Error::msg)?;
-            let html = Html::parse_document(&html_string);
             debug!("Got {:?} bytes of HTML", html_string.len());
-
-            let title = {
-                let mut matches = html.select(&title_selector).into_iter();
-                let matched = matches
-                    .next()
-                    .ok_or_else(|| anyhow!("No match for {:?}", title_selector))?;
-                let s = get_text(matched);
-                anyhow::ensure!(
-                    !s.is_empty(),
-                    "Found empty string where deck title was expected!"
-                );
-                s
-            };
-
-            let mut main_deck = HashMap::with_capacity(110);
-            let mut sideboard = HashMap::with_capacity(20);
-
-            macro_rules! parse_section {
-                ($self:ident, $selector:ident, $pile:ident) => {{
-                    for row_ref in html.select(&$selector) {
-                        let row = row_ref.value();
-                        let card_id = match row.attr("id") {
-                            None => continue,
-                            Some(row_id_str) => match u64::from_str(row_id_str) {
-                                Ok(row_id) => row_id,
-                                Err(_) => continue,
-                            },
-                        };
-                        let card_name_ref =
-                            row_ref.select(&card_name_selector).next().ok_or_else(|| {
-                                anyhow!(
-                                    "No card name found for Deckbox card with ID {} in deck {}",
-                                    card_id,
-                                    $self.id,
-                                )
-                            })?;
-                        let card_name = get_text(card_name_ref);
-
-                        let mut card_count: u8 = 0;
-                        if let Some(card_count_ref) = row_ref.select(&card_count_selector).next() {
-                            let card_count_str = get_text(card_count_ref);
-                            match u8::from_str(&card_count_str) {
-                                Ok(c) => card_count = c,
-                                Err(_) => (),
-                            };
-                        }
-
-                        let card_name = card_name.trim();
-                        debug!("Looking up oracle ID for Deckbox card {:?}", card_name);
-                        let oracle_id = scryfall::oracle_id_by_name(&mut *db, card_name).await?;
-                        if let Some(_before) =
-                            $pile.insert(oracle_id, (card_name.to_string(), card_count))
-                        {
-                            warn!("Found card {} multiple times!", card_name);
-                        }
-                    }
-                }};
-            }
-
-            parse_section!(self, main_card_rows_selector, main_deck);
-            parse_section!(self, sideboard_card_rows_selector, sideboard);
-            let commanders = extract_commanders(&mut *db, &mut main_deck)
+            let (title, entries) = parse_deckbox_html(&html_string)
+                .map_err(DeckInputError::wrap)
+                .with_context(|| format!("Failed to parse Deckbox deck {}", self.id))?;
+            let piles = piles_from_entries(&mut *db, entries)
                 .await
-                .context("Failed to extract commanders from main deck list")?;
-
-            let deck = unparsed
-                .save_cards(db, redis, title, commanders, main_deck, sideboard)
-                .await?;
+                .with_context(|| format!("Failed to load Deckbox deck {}", self.id))?;
+            let deck = unparsed.save_cards(db, redis, title, piles).await?;
 
             Ok(deck)
         })
@@ -228,6 +151,68 @@ pub(crate) struct TappedOutLoader {
     slug: String,
 }
 
+/// Reads the cards out of a TappedOut CSV export. Commanders and companions
+/// are marked with their own columns, whichever board they're on.
+fn parse_tappedout_csv(csv_bytes: &[u8]) -> Result<Vec<TextEntry>> {
+    #[derive(Deserialize)]
+    struct TappedOutCSVRow {
+        #[serde(rename = "Board")]
+        board: String,
+        #[serde(rename = "Qty")]
+        count: u8,
+        #[serde(rename = "Name")]
+        name: String,
+        #[serde(default, rename = "Commander")]
+        commander_col: String,
+        #[serde(default, rename = "Companion")]
+        companion_col: String,
+    }
+
+    let mut entries = Vec::with_capacity(110);
+    let mut csv_reader = csv::Reader::from_reader(csv_bytes);
+    for row_result in csv_reader.deserialize::<TappedOutCSVRow>() {
+        let mut row = row_result?;
+
+        // TappedOut sometimes renders split card names with a single slash,
+        // while we canonicalize them with two slashes.
+        if row.name.contains(" / ") {
+            row.name = row.name.replace(" / ", " // ");
+        }
+
+        let pile = if row.commander_col == "True" {
+            TextPile::Commander
+        } else if row.companion_col == "True" {
+            TextPile::Companion
+        } else {
+            match row.board.as_str() {
+                "main" => TextPile::MainDeck,
+                "maybe" => TextPile::Maybeboard,
+                "acquire" => {
+                    debug!(
+                        "Skipping \"acquire\" row in TappedOut deck for card {}",
+                        row.name
+                    );
+                    continue;
+                }
+                "side" => TextPile::Sideboard,
+                other => {
+                    warn!(
+                        "Unexpected TappedOut \"board\" value for card {}: {:?}",
+                        row.name, other
+                    );
+                    continue;
+                }
+            }
+        };
+        entries.push(TextEntry {
+            pile,
+            count: row.count,
+            name: row.name,
+        });
+    }
+    Ok(entries)
+}
+
 impl DeckMatcher for TappedOutLoader {
     fn match_url(url: &Url) -> Option<Self> {
         match (url.domain(), url.path_segments()) {
@@ -264,18 +249,6 @@ impl<R: AsyncCommands> DeckParser<R> for TappedOutLoader {
         unparsed: UnparsedDeck,
     ) -> LocalBoxFuture<'a, Result<Deck>> {
         Box::pin(async move {
-            #[derive(Deserialize)]
-            struct TappedOutCSVRow {
-                #[serde(rename = "Board")]
-                board: String,
-                #[serde(rename = "Qty")]
-                count: u8,
-                #[serde(rename = "Name")]
-                name: String,
-                #[serde(default, rename = "Commander")]
-                commander_col: String,
-            }
-
             let url = format!("https://tappedout.net/mtg-decks/{}/", self.slug);
             let csv_url = format!("{}?fmt=csv", url);
             let client = surf::Client::new();
@@ -297,75 +270,25 @@ impl<R: AsyncCommands> DeckParser<R> for TappedOutLoader {
                 let html = Html::parse_document(&html_string);
                 let title_selector = Selector::parse(".well.well-jumbotron h2").unwrap();
                 let mut matches = html.select(&title_selector);
-                let matched = matches
-                    .next()
-                    .ok_or(anyhow!("Failed to find title for TappedOut deck"))?;
+                let matched = matches.next().ok_or_else(|| {
+                    DeckInputError("Failed to find title for TappedOut deck".to_string())
+                })?;
                 get_text(matched)
             };
 
-            let mut commanders = HashMap::new();
-            let mut main_deck = HashMap::with_capacity(110);
-            let mut sideboard = HashMap::new();
-
             let request = client
                 .get(&csv_url)
                 .middleware(crate::utils::SurfRedirectMiddleware::new());
             let mut response = request.await.map_err(Error::msg)?;
             let csv_bytes = response.body_bytes().await.map_err(Error::msg)?;
-            let csv_cursor = std::io::Cursor::new(csv_bytes);
-            let mut csv_reader = csv::Reader::from_reader(csv_cursor);
-
-            for row_result in csv_reader.deserialize::<TappedOutCSVRow>() {
-                let mut row = row_result
-                    .with_context(|| format!("Failed to parse TappedOut deck CSV from {}", url))?;
-
-                // TappedOut sometimes renders split card names with a single slash,
-                // while we canonicalize them with two slashes.
-                if row.name.contains(" / ") {
-                    row.name = row.name.replace(" / ", " // ");
-                }
-
-                let oracle_id = scryfall::oracle_id_by_name(db, &row.name)
-                    .await
-                    .with_context(|| format!("Failed to load TappedOut deck {}", self.slug))?;
-                if row.commander_col == "True" {
-                    commanders.insert(oracle_id, row.name.to_string());
-                    continue;
-                }
-                let pile = match row.board.as_str() {
-                    "main" => &mut main_deck,
-                    "maybe" => {
-                        debug!(
-                            "Skipping \"maybe\" row in TappedOut deck for card {}",
-                            row.name
-                        );
-                        continue;
-                    }
-                    "acquire" => {
-                        debug!(
-                            "Skipping \"acquire\" row in TappedOut deck for card {}",
-                            row.name
-                        );
-                        continue;
-                    }
-                    "side" => &mut sideboard,
-                    other => {
-                        warn!(
-                            "Unexpected TappedOut \"board\" value for card {}: {:?}",
-                            row.name, other
-                        );
-                        continue;
-                    }
-                };
-                let entry = pile
-                    .entry(oracle_id)
-                    .or_insert_with(|| (row.name.to_string(), 0));
-                entry.1 += row.count;
-            }
+            let entries = parse_tappedout_csv(&csv_bytes)
+                .map_err(DeckInputError::wrap)
+                .with_context(|| format!("Failed to parse TappedOut deck CSV from {}", url))?;
+            let piles = piles_from_entries(&mut *db, entries)
+                .await
+                .with_context(|| format!("Failed to load TappedOut deck {}", self.slug))?;
 
-            let deck = unparsed
-                .save_cards(db, redis, title, commanders, main_deck, sideboard)
-                .await?;
+            let deck = unparsed.save_cards(db, redis, title, piles).await?;
             Ok(deck)
         })
     }
@@ -375,6 +298,102 @@ pub(crate) struct ArchidektLoader {
     id: u64,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchidektResponse {
+    id: u64,
+    name: String,
+    cards: Vec<ArchidektResponseCardWrapper>,
+    categories: Vec<ArchidektResponseCategory>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchidektResponseCardWrapper {
+    card: ArchidektResponseCard,
+    quantity: u8,
+    categories: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchidektResponseCard {
+    oracle_card: ArchidektResponseOracleCard,
+    uid: uuid::Uuid,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchidektResponseOracleCard {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchidektResponseCategory {
+    name: String,
+    included_in_deck: bool,
+    // is_premier: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct ArchidektEntry {
+    entry: TextEntry,
+    /// The printing the deck uses, which is tried before looking the card up
+    /// by name.
+    card_id: ScryfallId,
+    /// The card's primary category, for main deck cards.
+    category: Option<String>,
+}
+
+/// Reads the deck ID, title and cards out of Archidekt's deck JSON. Cards in
+/// categories that aren't included in the deck go in the sideboard.
+fn parse_archidekt_json(json: &str) -> Result<(u64, String, Vec<ArchidektEntry>)> {
+    let response_value: ArchidektResponse = serde_json::from_str(json)?;
+
+    let excluded_categories: Vec<String> = response_value
+        .categories
+        .into_iter()
+        .filter(|category| !category.included_in_deck)
+        .map(|category| category.name)
+        .collect();
+
+    let mut entries = Vec::with_capacity(response_value.cards.len());
+    for card_wrapper in response_value.cards {
+        let has_category = |name: &str| card_wrapper.categories.iter().any(|c| c == name);
+        let pile = if has_category("Commander") {
+            TextPile::Commander
+        } else if has_category("Companion") {
+            TextPile::Companion
+        } else if has_category("Maybeboard") {
+            TextPile::Maybeboard
+        } else if card_wrapper
+            .categories
+            .iter()
+            .any(|c| excluded_categories.contains(c))
+        {
+            TextPile::Sideboard
+        } else {
+            TextPile::MainDeck
+        };
+        // Archidekt lists a card's primary category first.
+        let category = match pile {
+            TextPile::MainDeck => card_wrapper.categories.first().cloned(),
+            _ => None,
+        };
+        entries.push(ArchidektEntry {
+            entry: TextEntry {
+                pile,
+                count: card_wrapper.quantity,
+                name: card_wrapper.card.oracle_card.name,
+            },
+            card_id: card_wrapper.card.uid.into(),
+            category,
+        });
+    }
+    Ok((response_value.id, response_value.name, entries))
+}
+
 impl DeckMatcher for ArchidektLoader {
     fn match_url(url: &Url) -> Option<Self> {
         match (url.domain(), url.path_segments()) {
@@ -409,44 +428,6 @@ impl<R: AsyncCommands> DeckParser<R> for ArchidektLoader {
         unparsed: UnparsedDeck,
     ) -> LocalBoxFuture<'a, Result<Deck>> {
         Box::pin(async move {
-            #[derive(Deserialize)]
-            #[serde(rename_all = "camelCase")]
-            struct ArchidektResponse {
-                id: u64,
-                name: String,
-                cards: Vec<ArchidektResponseCardWrapper>,
-                categories: Vec<ArchidektResponseCategory>,
-            }
-
-            #[derive(Deserialize)]
-            #[serde(rename_all = "camelCase")]
-            struct ArchidektResponseCardWrapper {
-                card: ArchidektResponseCard,
-                quantity: u8,
-                categories: Vec<String>,
-            }
-
-            #[derive(Deserialize)]
-            #[serde(rename_all = "camelCase")]
-            struct ArchidektResponseCard {
-                oracle_card: ArchidektResponseOracleCard,
-                uid: uuid::Uuid,
-            }
-
-            #[derive(Deserialize)]
-            #[serde(rename_all = "camelCase")]
-            struct ArchidektResponseOracleCard {
-                name: String,
-            }
-
-            #[derive(Deserialize)]
-            #[serde(rename_all = "camelCase")]
-            struct ArchidektResponseCategory {
-                name: String,
-                included_in_deck: bool,
-                // is_premier: bool,
-            }
-
             let json_url = format!("https://archidekt.com/api/decks/{}/small/", self.id);
             let client = surf::Client::new();
             info!("Parsing Archidekt deck at {}", json_url);
@@ -458,88 +439,156 @@ impl<R: AsyncCommands> DeckParser<R> for ArchidektLoader {
                 .await
                 .map_err(Error::msg)
                 .context("Failed to load deck JSON from Archidekt")?;
-            // let response_string = response
-            //     .body_string()
-            //     .await
-            //     .map_err(Error::msg)
-            //     .context("Failed to get response body from Archidekt as a String")?;
-            // debug!("Archidekt response JSON: {:?}", response_string);
-            let response_value = response
-                .body_json::<ArchidektResponse>()
+            let json_string = response
+                .body_string()
                 .await
                 .map_err(Error::msg)
+                .context("Failed to get response body from Archidekt as a String")?;
+            let (id, title, entries) = parse_archidekt_json(&json_string)
+                .map_err(DeckInputError::wrap)
                 .context("Failed to parse deck JSON from Archidekt")?;
 
-            if response_value.id != self.id {
-                return Err(anyhow!("Archidekt API returned a different deck than we asked for! Got {:?}, expected {:?}", response_value.id, self.id));
-            }
-
-            let title = response_value.name;
-
-            let mut commanders = HashMap::new();
-            let mut main_deck = HashMap::with_capacity(110);
-            let mut sideboard = HashMap::new();
-
-            struct ArchidektCategoryInfo {
-                included_in_deck: bool,
+            if id != self.id {
+                return Err(anyhow!("Archidekt API returned a different deck than we asked for! Got {:?}, expected {:?}", id, self.id));
             }
 
-            let categories = {
-                let mut cs = HashMap::new();
-                for category in response_value.categories {
-                    cs.insert(
-                        category.name.clone(),
-                        ArchidektCategoryInfo {
-                            included_in_deck: category.included_in_deck,
-                            // is_premier: category.is_premier,
-                        },
-                    );
-                }
-                cs
-            };
+            let mut piles = CardPiles::default();
+            piles.main_deck.reserve(110);
 
-            for card_wrapper in response_value.cards {
-                let card_name = card_wrapper.card.oracle_card.name;
-                let card_id = card_wrapper.card.uid.into();
+            for ArchidektEntry {
+                entry,
+                card_id,
+                category,
+            } in entries
+            {
+                let card_name = entry.name;
                 let oracle_id = {
                     let raw_card_result = scryfall::card_by_id(db, card_id).await;
                     match raw_card_result {
                         Ok(card) => card.oracle_id().with_context(|| {
                             format!("Failed to get Oracle ID for card {}", card_name.clone())
                         })?,
-                        Err(_) => scryfall::oracle_id_by_name(db, &card_name)
-                            .await
-                            .with_context(|| {
-                                format!(
-                                    "Failed to find a card named {:?} for Archidekt deck {:?}",
-                                    card_name, self.id
-                                )
-                            })?,
+                        Err(_) => oracle_id_for_entry(db, &card_name).await.with_context(|| {
+                            format!(
+                                "Failed to find a card named {:?} for Archidekt deck {:?}",
+                                card_name, self.id
+                            )
+                        })?,
                     }
                 };
 
-                let is_commander = card_wrapper.categories.iter().any(|c| c == "Commander");
-                let is_sideboard =
-                    card_wrapper
-                        .categories
-                        .iter()
-                        .any(|c| match categories.get(c) {
-                            None => false,
-                            Some(cat) => !cat.included_in_deck,
-                        });
-                if is_commander {
-                    commanders.insert(oracle_id, card_name);
-                } else if is_sideboard {
-                    sideboard.insert(oracle_id, (card_name, card_wrapper.quantity));
-                } else {
-                    main_deck.insert(oracle_id, (card_name, card_wrapper.quantity));
+                match entry.pile {
+                    TextPile::Commander => {
+                        piles.commanders.insert(oracle_id, card_name);
+                    }
+                    TextPile::Companion => {
+                        piles.companions.insert(oracle_id, card_name);
+                    }
+                    TextPile::Maybeboard => {
+                        piles.maybeboard.insert(oracle_id, (card_name, entry.count));
+                    }
+                    TextPile::Sideboard => {
+                        piles.sideboard.insert(oracle_id, (card_name, entry.count));
+                    }
+                    TextPile::MainDeck => {
+                        if let Some(category) = category {
+                            piles.categories.insert(oracle_id, category);
+                        }
+                        piles.main_deck.insert(oracle_id, (card_name, entry.count));
+                    }
                 }
             }
 
-            let deck = unparsed
-                .save_cards(db, redis, title, commanders, main_deck, sideboard)
-                .await?;
+            let deck = unparsed.save_cards(db, redis, title, piles).await?;
             Ok(deck)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{parse_archidekt_json, parse_deckbox_html, parse_tappedout_csv};
+    use crate::{
+        deck::text::{TextEntry, TextPile},
+        scryfall::ScryfallId,
+    };
+
+    fn entry(pile: TextPile, count: u8, name: &str) -> TextEntry {
+        TextEntry {
+            pile,
+            count,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn deckbox_page() {
+        let (title, entries) = parse_deckbox_html(include_str!("fixtures/deckbox.html")).unwrap();
+        assert_eq!(title, "Ezuri Counters");
+        assert_eq!(
+            entries,
+            vec![
+                entry(TextPile::MainDeck, 1, "Ezuri, Claw of Progress"),
+                entry(TextPile::MainDeck, 30, "Forest"),
+                entry(TextPile::MainDeck, 1, "Fire // Ice"),
+                // Deckbox has no companion marker, so Lurrus stays here.
+                entry(TextPile::Sideboard, 1, "Lurrus of the Dream-Den"),
+                entry(TextPile::Sideboard, 2, "Tormod's Crypt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tappedout_csv() {
+        let entries = parse_tappedout_csv(include_bytes!("fixtures/tappedout.csv")).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                entry(TextPile::Commander, 1, "Ezuri, Claw of Progress"),
+                entry(TextPile::MainDeck, 30, "Forest"),
+                entry(TextPile::MainDeck, 1, "Fire // Ice"),
+                entry(TextPile::Companion, 1, "Lurrus of the Dream-Den"),
+                entry(TextPile::Sideboard, 2, "Tormod's Crypt"),
+                entry(TextPile::Maybeboard, 1, "Sol Ring"),
+            ]
+        );
+    }
+
+    #[test]
+    fn archidekt_json() {
+        let (id, title, entries) =
+            parse_archidekt_json(include_str!("fixtures/archidekt.json")).unwrap();
+        assert_eq!(id, 1234567);
+        assert_eq!(title, "Ezuri Counters");
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.entry.clone(), e.category.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    entry(TextPile::Commander, 1, "Ezuri, Claw of Progress"),
+                    None
+                ),
+                (entry(TextPile::MainDeck, 30, "Forest"), Some("Land")),
+                (
+                    entry(TextPile::MainDeck, 1, "Swords to Plowshares"),
+                    Some("Removal")
+                ),
+                (
+                    entry(TextPile::Companion, 1, "Lurrus of the Dream-Den"),
+                    None
+                ),
+                (entry(TextPile::Sideboard, 2, "Tormod's Crypt"), None),
+                (entry(TextPile::Maybeboard, 1, "Sol Ring"), None),
+            ]
+        );
+        assert_eq!(
+            entries[0].card_id,
+            ScryfallId::from_str("2c65185b-6cf0-451d-985e-56aa45d9a57d").unwrap()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Decklists pasted in as plain text, in the format we export or the similar
+//! ones MTG Arena and most deck sites produce.
+
+use anyhow::{anyhow, Context, Result};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TextPile {
+    Commander,
+    Companion,
+    MainDeck,
+    Sideboard,
+    Maybeboard,
+}
+
+impl TextPile {
+    fn from_heading(line: &str) -> Option<Self> {
+        let heading = line.trim_end_matches(':').trim().to_lowercase();
+        match heading.as_str() {
+            "commander" | "commanders" => Some(TextPile::Commander),
+            "companion" => Some(TextPile::Companion),
+            "deck" | "main" | "main deck" | "mainboard" => Some(TextPile::MainDeck),
+            "sideboard" => Some(TextPile::Sideboard),
+            "maybe" | "maybeboard" => Some(TextPile::Maybeboard),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextEntry {
+    pub pile: TextPile,
+    pub count: u8,
+    pub name: String,
+}
+
+/// Drops a trailing `(SET) NUMBER` or `(SET)`, since printings are picked the
+/// same way as for every other deck.
+fn strip_printing(name: &str) -> &str {
+    if let Some(open) = name.rfind(" (") {
+        let rest = &name[open + 2..];
+        if let Some(close) = rest.find(')') {
+            let set_code = &rest[..close];
+            let after = rest[close + 1..].trim();
+            if !set_code.is_empty()
+                && set_code.chars().all(|c| c.is_ascii_alphanumeric())
+                && !after.contains(' ')
+            {
+                return name[..open].trim_end();
+            }
+        }
+    }
+    name
+}
+
+fn parse_card_line(line: &str) -> Result<(u8, String)> {
+    let digits_end = line
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| line.len());
+    if digits_end == 0 {
+        return Err(anyhow!("Expected a card count"));
+    }
+    let count = u8::from_str(&line[..digits_end]).context("Invalid card count")?;
+    let rest = line[digits_end..].strip_prefix(|c| c == 'x' || c == 'X');
+    let rest = rest.unwrap_or(&line[digits_end..]);
+    if !rest.starts_with(char::is_whitespace) {
+        return Err(anyhow!("Expected a space after the card count"));
+    }
+    let name = strip_printing(rest.trim());
+    if name.is_empty() {
+        return Err(anyhow!("Missing card name"));
+    }
+    // Some sites write split cards with a single slash, while we canonicalize
+    // them with two.
+    let name = if name.contains(" / ") && !name.contains(" // ") {
+        name.replace(" / ", " // ")
+    } else {
+        name.to_string()
+    };
+    Ok((count, name))
+}
+
+/// Cards before the first heading are in the main deck. Blank lines and lines
+/// starting with `//` or `#` are skipped.
+pub fn parse_decklist(text: &str) -> Result<Vec<TextEntry>> {
+    let mut pile = TextPile::MainDeck;
+    let mut entries = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+            continue;
+        }
+        if let Some(heading) = TextPile::from_heading(line) {
+            pile = heading;
+            continue;
+        }
+        let (count, name) = parse_card_line(line)
+            .with_context(|| format!("Couldn't read line {} of the decklist: {:?}", i + 1, line))?;
+        entries.push(TextEntry { pile, count, name });
+    }
+    if entries.is_empty() {
+        return Err(anyhow!("The decklist doesn't have any cards in it"));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_decklist, TextEntry, TextPile};
+
+    fn entry(pile: TextPile, count: u8, name: &str) -> TextEntry {
+        TextEntry {
+            pile,
+            count,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_exported_and_arena_lists() {
+        let parsed = parse_decklist(
+            "Commander\n\
+             1 Esika, God of the Tree // The Prismatic Bridge (KHM) 168\n\
+             \n\
+             Main deck\n\
+             1 Fire / Ice (MH2) 290\n\
+             30x Forest\n\
+             // Removal\n\
+             1 Swords to Plowshares (STA)\n\
+             \n\
+             Sideboard:\n\
+             2 Tormod's Crypt\n",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                entry(
+                    TextPile::Commander,
+                    1,
+                    "Esika, God of the Tree // The Prismatic Bridge"
+                ),
+                entry(TextPile::MainDeck, 1, "Fire // Ice"),
+                entry(TextPile::MainDeck, 30, "Forest"),
+                entry(TextPile::MainDeck, 1, "Swords to Plowshares"),
+                entry(TextPile::Sideboard, 2, "Tormod's Crypt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert!(parse_decklist("").is_err());
+        assert!(parse_decklist("Sideboard\n").is_err());
+        assert!(parse_decklist("Forest\n").is_err());
+        assert!(parse_decklist("300 Forest\n").is_err());
+        assert!(parse_decklist("4Forest\n").is_err());
+    }
+}
@@ -26,10 +26,12 @@ const FILES_URL_BASE: &'static str = "https://ttsmagic.cards/files/";
 /// * `decks` - created by the old app, contains JSON TTS decks.
 /// * `page` - created by the old app, contains JPGs of TTS deck pages.
 /// * `pages` - created by the new app, contains JPGs of TTS deck pages.
+/// * `thumbnails` - created by the new app, contains PNG previews of decks.
 /// * `tokens` - high resolution card images.
 ///
-/// Of these, we only really need to serve `page` and `pages`. The former is
-/// needed to support existing decks, and the latter to support newer decks.
+/// Of these, we only really need to serve `page`, `pages` and `thumbnails`.
+/// The first is needed to support existing decks, and the others to support
+/// newer decks.
 #[derive(Copy, Clone, Debug)]
 enum FileBucket {
     CardImages,
@@ -45,6 +47,7 @@ impl FileBucket {
             "tokens" => Self::CardImages,
             "page" => Self::DeckPages,
             "pages" => Self::DeckPages,
+            "thumbnails" => Self::DeckPages,
             _ => {
                 warn!("Tried to look up FileBucket for key {:?}", key);
                 return None;
@@ -176,6 +179,20 @@ impl MediaFile {
         format!("{}/{}", self.bucket, self.key)
     }
 
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Refers to a file that has already been saved, without checking that it
+    /// exists.
+    pub fn from_key(key: &str) -> Option<Self> {
+        let bucket = FileBucket::for_key(key)?;
+        Some(MediaFile {
+            bucket,
+            key: key.to_string(),
+        })
+    }
+
     pub fn url(&self) -> Result<Url> {
         let base = Url::parse(FILES_URL_BASE).unwrap();
         Ok(base.join(&self.key)?)
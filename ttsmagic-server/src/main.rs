@@ -7,15 +7,17 @@ extern crate log;
 #[macro_use]
 extern crate pin_utils;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use pretty_env_logger::env_logger::Logger;
 use std::str::FromStr;
 
+mod api_token;
 mod deck;
 mod files;
 mod importer;
 mod migrations;
 mod notify;
+mod proxies;
 mod scryfall;
 mod secrets;
 mod test_helpers;
@@ -153,6 +155,41 @@ async fn main_inner() -> Result<()> {
                 .await
                 .context("Failed to render the deck")?;
         }
+        ("diff-decks", Some(opts)) => {
+            let old = parse_deck_version(opts.value_of("old").unwrap())?;
+            let new = parse_deck_version(opts.value_of("new").unwrap())?;
+            let mut db = db_pool.acquire().await?;
+            let mut decks = Vec::with_capacity(2);
+            for version in [old, new].iter() {
+                let deck = deck::Deck::get_version(&mut db, *version)
+                    .await?
+                    .ok_or_else(|| anyhow!("No deck found for {:?}", version))?;
+                decks.push(deck);
+            }
+            let diff = deck::diff::diff_decks(old, &decks[0], new, &decks[1])?;
+            print!("{}", deck::diff::diff_to_text(&diff));
+        }
+        ("set-commanders", Some(opts)) => {
+            let deck_id = ttsmagic_types::DeckId::from_str(opts.value_of("deck_id").unwrap())?;
+            let mut tx = db_pool.begin().await?;
+            let mut deck = deck::Deck::get_by_id(&mut tx, deck_id)
+                .await?
+                .ok_or_else(|| anyhow!("No deck found with ID {}", deck_id))?;
+            let mut oracle_ids = vec![];
+            for name in opts.values_of("names").into_iter().flatten() {
+                oracle_ids.push(scryfall::oracle_id_by_name(&mut tx, name).await?);
+            }
+            deck.set_commanders(&mut tx, &mut redis_conn, oracle_ids)
+                .await?;
+            tx.commit().await?;
+            println!(
+                "Commanders: {:?}",
+                deck.commanders
+                    .values()
+                    .map(|c| c.combined_name())
+                    .collect::<Vec<_>>()
+            );
+        }
         ("import-old", Some(opts)) => {
             let user_id = match opts.value_of("user_id") {
                 Some(raw) => Some(ttsmagic_types::UserId::from_str(raw)?),
@@ -182,6 +219,40 @@ async fn main_inner() -> Result<()> {
     Ok(())
 }
 
+fn parse_token_copies(raw: &str) -> Result<ttsmagic_types::TokenCopies> {
+    use ttsmagic_types::TokenCopies;
+    if raw == "estimate" {
+        return Ok(TokenCopies::Estimated);
+    }
+    let copies = std::num::NonZeroU8::from_str(raw).with_context(|| {
+        format!(
+            "Invalid token copies {:?}, expected a number or \"estimate\"",
+            raw
+        )
+    })?;
+    if copies.get() == 1 {
+        Ok(TokenCopies::Single)
+    } else {
+        Ok(TokenCopies::Fixed(copies))
+    }
+}
+
+/// Parses `DECK_ID` for a deck's current card list, or `DECK_ID@REVISION`
+/// for one of its past revisions.
+fn parse_deck_version(raw: &str) -> Result<ttsmagic_types::DeckVersion> {
+    let (raw_id, raw_revision) = match raw.find('@') {
+        Some(at) => (&raw[..at], Some(&raw[at + 1..])),
+        None => (raw, None),
+    };
+    let deck_id = ttsmagic_types::DeckId::from_str(raw_id)
+        .with_context(|| format!("Invalid deck ID {:?}", raw_id))?;
+    let revision = match raw_revision {
+        Some(r) => Some(u32::from_str(r).with_context(|| format!("Invalid revision {:?}", r))?),
+        None => None,
+    };
+    Ok(ttsmagic_types::DeckVersion { deck_id, revision })
+}
+
 async fn render_deck_command(
     scryfall_api: std::sync::Arc<scryfall::api::ScryfallApi>,
     db_pool: sqlx::PgPool,
@@ -200,12 +271,33 @@ async fn render_deck_command(
         deck = deck::load_deck(&mut tx, redis, &user, url)
             .await
             .context("Failed to load deck")?;
+        let mut options = deck.render_options.clone();
+        if let Some(raw_copies) = opts.value_of("token_copies") {
+            options.token_copies = parse_token_copies(raw_copies)?;
+        }
+        if let Some(raw_layout) = opts.value_of("layout") {
+            options.layout = ttsmagic_types::LayoutPreset::from_slug(raw_layout)
+                .ok_or_else(|| anyhow!("Unknown layout preset {:?}", raw_layout))?;
+        }
+        if let Some(raw_mode) = opts.value_of("export_mode") {
+            options.export_mode = ttsmagic_types::ExportMode::from_slug(raw_mode)
+                .ok_or_else(|| anyhow!("Unknown export mode {:?}", raw_mode))?;
+        }
+        if opts.is_present("maybeboard") {
+            options.include_maybeboard = true;
+        }
+        if opts.is_present("split_categories") {
+            options.split_categories = true;
+        }
+        if options != deck.render_options {
+            deck.set_render_options(&mut tx, redis, options).await?;
+        }
         tx.commit().await?;
     }
     let rendered;
     {
         let mut tx = db_pool.begin().await?;
-        rendered = deck.render(scryfall_api, &mut tx, redis).await?;
+        rendered = deck.render(scryfall_api.clone(), &mut tx, redis).await?;
         tx.commit().await?;
     }
 
@@ -213,6 +305,20 @@ async fn render_deck_command(
     for (i, page) in rendered.pages.iter().enumerate() {
         info!("Page {}: {}", i, page.image.path());
     }
+    info!("Thumbnail: {}", rendered.thumbnail.path());
+    if let Some(pdf_path) = opts.value_of("pdf_file") {
+        let options = proxies::ProxyOptions {
+            cut_marks: opts.is_present("cut_marks"),
+            bleed: opts.is_present("bleed"),
+            tokens: opts.is_present("proxy_tokens"),
+        };
+        let mut db = db_pool.acquire().await?;
+        let pdf = proxies::render_proxies(scryfall_api, &mut db, &deck, options)
+            .await
+            .context("Failed to render proxies")?;
+        info!("Writing proxies PDF to {}", pdf_path);
+        async_std::fs::write(pdf_path, pdf).await?;
+    }
     async_std::task::spawn_blocking({
         let json: serde_json::Value = rendered.json_description.clone();
         let output_file_path: String = opts.value_of("output_file").unwrap().to_owned();
@@ -431,6 +537,108 @@ fn get_args<'a>(current_dir: &'a std::path::Path) -> clap::ArgMatches<'a> {
                         .long("pretty")
                         .takes_value(false)
                         .help("Pretty-print the JSON output"),
+                )
+                .arg(
+                    Arg::with_name("token_copies")
+                        .long("token-copies")
+                        .takes_value(true)
+                        .value_name("COPIES")
+                        .help(
+                            "Copies of each token to render: a number, or \"estimate\" \
+                             to base it on the cards in the deck that create it",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("layout")
+                        .long("layout")
+                        .takes_value(true)
+                        .value_name("PRESET")
+                        .possible_values(&["row", "1v1-mat", "commander-pod", "compact"])
+                        .help("Where to place the deck's piles on the table"),
+                )
+                .arg(
+                    Arg::with_name("export_mode")
+                        .long("export")
+                        .takes_value(true)
+                        .value_name("MODE")
+                        .possible_values(&["loose", "saved-object"])
+                        .help("Write loose piles, or a saved object with the piles in a bag"),
+                )
+                .arg(
+                    Arg::with_name("maybeboard")
+                        .long("maybeboard")
+                        .takes_value(false)
+                        .help("Include the deck's maybeboard as a face-up pile"),
+                )
+                .arg(
+                    Arg::with_name("split_categories")
+                        .long("split-categories")
+                        .takes_value(false)
+                        .help("Put each of the deck site's categories in its own pile"),
+                )
+                .arg(
+                    Arg::with_name("pdf_file")
+                        .long("pdf")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Also write a PDF of printable proxies to this file"),
+                )
+                .arg(
+                    Arg::with_name("cut_marks")
+                        .long("cut-marks")
+                        .takes_value(false)
+                        .requires("pdf_file")
+                        .help("Add cut marks to the proxies PDF"),
+                )
+                .arg(
+                    Arg::with_name("bleed")
+                        .long("bleed")
+                        .takes_value(false)
+                        .requires("pdf_file")
+                        .help("Add bleed around the cards in the proxies PDF"),
+                )
+                .arg(
+                    Arg::with_name("proxy_tokens")
+                        .long("proxy-tokens")
+                        .takes_value(false)
+                        .requires("pdf_file")
+                        .help("Include tokens and extras in the proxies PDF"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff-decks")
+                .about("Show the cards added, removed and changed between two decks")
+                .arg(
+                    Arg::with_name("old")
+                        .takes_value(true)
+                        .value_name("DECK[@REVISION]")
+                        .required(true)
+                        .help("Deck ID to compare from, optionally with a revision number"),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .takes_value(true)
+                        .value_name("DECK[@REVISION]")
+                        .required(true)
+                        .help("Deck ID to compare to, optionally with a revision number"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("set-commanders")
+                .about("Choose a deck's commanders, or go back to detecting them")
+                .arg(
+                    Arg::with_name("deck_id")
+                        .takes_value(true)
+                        .value_name("DECK")
+                        .required(true)
+                        .help("ID of the deck"),
+                )
+                .arg(
+                    Arg::with_name("names")
+                        .takes_value(true)
+                        .multiple(true)
+                        .value_name("NAME")
+                        .help("Card names of the commanders, none to detect them again"),
                 ),
         )
         .subcommand(
@@ -14,6 +14,16 @@ static MIGRATIONS: &[(&'static str, &'static str)] = &[
     migration!("0003_deck_multiple_piles"),
     migration!("0004_fix_deck_entry_pk"),
     migration!("0005_case_insensitive_card_name_search"),
+    migration!("0006_deck_render_options"),
+    migration!("0007_deck_thumbnail"),
+    migration!("0008_deck_revision"),
+    migration!("0009_deck_chosen_commander"),
+    migration!("0010_deck_companion_pile"),
+    migration!("0011_deck_maybeboard_pile"),
+    migration!("0012_deck_entry_category"),
+    migration!("0013_deck_share_token"),
+    migration!("0014_api_token"),
+    migration!("0015_deck_modified"),
 ];
 
 async fn apply_migration(
@@ -0,0 +1,201 @@
+//! Printable proxies: every card in a deck at true size, nine to a page.
+
+use anyhow::{anyhow, Result};
+use async_std::sync::Arc;
+use image::{codecs::jpeg::JpegEncoder, ColorType, RgbImage};
+use serde::Deserialize;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+use crate::{
+    deck::Deck,
+    scryfall::{api::ScryfallApi, ScryfallCard, ScryfallId},
+};
+
+mod pdf;
+use pdf::{JpegImage, PageContent, PdfDocument, MM};
+
+// US Letter. The bleed and cut marks are small enough that the cut marks still
+// fit in the margins when both are turned on.
+const PAGE_WIDTH: f64 = 8.5 * 72.0;
+const PAGE_HEIGHT: f64 = 11.0 * 72.0;
+const CARD_WIDTH: f64 = 63.0 * MM;
+const CARD_HEIGHT: f64 = 88.0 * MM;
+const BLEED: f64 = 1.0 * MM;
+const CUT_MARK_LENGTH: f64 = 3.0 * MM;
+const CUT_MARK_GAP: f64 = 1.0 * MM;
+const COLUMNS: usize = 3;
+const ROWS: usize = 3;
+const JPEG_QUALITY: u8 = 90;
+
+#[derive(Copy, Clone, Debug, Default, Deserialize)]
+pub struct ProxyOptions {
+    /// Short lines in the page margins along every cut.
+    #[serde(default)]
+    pub cut_marks: bool,
+    /// Space between the cards, filled with the color of each card's border,
+    /// so that a slightly-off cut doesn't leave a white edge.
+    #[serde(default)]
+    pub bleed: bool,
+    /// Also print the token and extras piles, which are left out by default.
+    #[serde(default)]
+    pub tokens: bool,
+}
+
+/// Bottom left corners of the cards on a page, in reading order.
+fn card_positions(options: ProxyOptions) -> Vec<(f64, f64)> {
+    let bleed = if options.bleed { BLEED } else { 0.0 };
+    let cell_width = CARD_WIDTH + 2.0 * bleed;
+    let cell_height = CARD_HEIGHT + 2.0 * bleed;
+    let left = (PAGE_WIDTH - cell_width * COLUMNS as f64) / 2.0 + bleed;
+    let top = (PAGE_HEIGHT + cell_height * ROWS as f64) / 2.0 - bleed;
+    let mut positions = Vec::with_capacity(COLUMNS * ROWS);
+    for row in 0..ROWS {
+        for column in 0..COLUMNS {
+            positions.push((
+                left + column as f64 * cell_width,
+                top - CARD_HEIGHT - row as f64 * cell_height,
+            ));
+        }
+    }
+    positions
+}
+
+fn add_cut_marks(page: &mut PageContent, positions: &[(f64, f64)]) {
+    let min_x = positions.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let max_x = positions
+        .iter()
+        .map(|p| p.0)
+        .fold(f64::NEG_INFINITY, f64::max)
+        + CARD_WIDTH;
+    let min_y = positions.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_y = positions
+        .iter()
+        .map(|p| p.1)
+        .fold(f64::NEG_INFINITY, f64::max)
+        + CARD_HEIGHT;
+    let mut xs: Vec<f64> = positions
+        .iter()
+        .flat_map(|p| vec![p.0, p.0 + CARD_WIDTH])
+        .collect();
+    let mut ys: Vec<f64> = positions
+        .iter()
+        .flat_map(|p| vec![p.1, p.1 + CARD_HEIGHT])
+        .collect();
+    for v in [&mut xs, &mut ys].iter_mut() {
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        v.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+    }
+    for x in xs {
+        let above = max_y + CUT_MARK_GAP;
+        let below = min_y - CUT_MARK_GAP;
+        page.line((x, above), (x, above + CUT_MARK_LENGTH));
+        page.line((x, below), (x, below - CUT_MARK_LENGTH));
+    }
+    for y in ys {
+        let left = min_x - CUT_MARK_GAP;
+        let right = max_x + CUT_MARK_GAP;
+        page.line((left, y), (left - CUT_MARK_LENGTH, y));
+        page.line((right, y), (right + CUT_MARK_LENGTH, y));
+    }
+}
+
+fn encode_jpeg(image: &RgbImage) -> Result<JpegImage> {
+    let mut data = vec![];
+    JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY).encode(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        ColorType::Rgb8,
+    )?;
+    Ok(JpegImage {
+        width: image.width(),
+        height: image.height(),
+        data,
+    })
+}
+
+/// Scryfall's card images have rounded corners, so the border color is taken
+/// from the middle of the top edge.
+fn border_color(image: &RgbImage) -> [u8; 3] {
+    let y = (image.height() / 100).max(1).min(image.height() - 1);
+    image.get_pixel(image.width() / 2, y).0
+}
+
+/// Cards are loaded as the page they're first on is built, and written into
+/// the PDF straight away, so at most a page's worth of images is in memory.
+pub async fn render_proxies(
+    api: Arc<ScryfallApi>,
+    db: &mut PgConnection,
+    deck: &Deck,
+    options: ProxyOptions,
+) -> Result<Vec<u8>> {
+    let cards = crate::tts::cards_to_print(db, deck, options.tokens).await?;
+    if cards.is_empty() {
+        return Err(anyhow!("Deck {} has no cards to print", deck.id));
+    }
+    let print_order: Vec<&ScryfallCard> = cards
+        .iter()
+        .flat_map(|(card, count)| std::iter::repeat(card).take(*count as usize))
+        .collect();
+
+    let mut doc = PdfDocument::new(PAGE_WIDTH, PAGE_HEIGHT);
+    let mut images: HashMap<ScryfallId, (pdf::ImageId, [u8; 3])> = HashMap::new();
+    let positions = card_positions(options);
+    for page_cards in print_order.chunks(positions.len()) {
+        let mut page = PageContent::default();
+        for (card, (x, y)) in page_cards.iter().zip(positions.iter()) {
+            let card_id = card.id()?;
+            let (image, color) = match images.get(&card_id) {
+                Some(loaded) => *loaded,
+                None => {
+                    debug!("Loading {} for proxies", card.combined_name());
+                    let image = card.ensure_image(&api).await?;
+                    let (jpeg, color) = async_std::task::spawn_blocking(move || {
+                        encode_jpeg(&image).map(|jpeg| (jpeg, border_color(&image)))
+                    })
+                    .await?;
+                    let loaded = (doc.add_image(&jpeg), color);
+                    images.insert(card_id, loaded);
+                    loaded
+                }
+            };
+            if options.bleed {
+                page.fill_rect(
+                    color,
+                    x - BLEED,
+                    y - BLEED,
+                    CARD_WIDTH + 2.0 * BLEED,
+                    CARD_HEIGHT + 2.0 * BLEED,
+                );
+            }
+            page.image(image, *x, *y, CARD_WIDTH, CARD_HEIGHT);
+        }
+        if options.cut_marks {
+            add_cut_marks(&mut page, &positions);
+        }
+        doc.add_page(page);
+    }
+    Ok(doc.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{card_positions, ProxyOptions, CARD_HEIGHT, CARD_WIDTH, PAGE_HEIGHT, PAGE_WIDTH};
+
+    #[test]
+    fn nine_cards_fit_on_a_page() {
+        for &bleed in &[false, true] {
+            let positions = card_positions(ProxyOptions {
+                cut_marks: true,
+                bleed,
+                tokens: false,
+            });
+            assert_eq!(positions.len(), 9);
+            for (x, y) in positions {
+                assert!(x > 0.0 && x + CARD_WIDTH < PAGE_WIDTH);
+                assert!(y > 0.0 && y + CARD_HEIGHT < PAGE_HEIGHT);
+            }
+        }
+    }
+}
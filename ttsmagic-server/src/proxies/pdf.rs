@@ -0,0 +1,223 @@
+//! Just enough of a PDF writer to put JPEG images and lines on pages.
+
+use std::fmt::Write as _;
+
+/// Points per millimeter. PDF user space is in points, 72 to the inch.
+pub const MM: f64 = 72.0 / 25.4;
+
+#[derive(Clone, Debug)]
+pub struct JpegImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// Object number of an image added with `PdfDocument::add_image`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ImageId(usize);
+
+/// Drawing operations for a single page, in points from the bottom left corner.
+#[derive(Clone, Debug, Default)]
+pub struct PageContent {
+    ops: String,
+    images: Vec<ImageId>,
+}
+
+impl PageContent {
+    pub fn image(&mut self, image: ImageId, x: f64, y: f64, width: f64, height: f64) {
+        if !self.images.contains(&image) {
+            self.images.push(image);
+        }
+        writeln!(
+            self.ops,
+            "q {:.3} 0 0 {:.3} {:.3} {:.3} cm /Im{} Do Q",
+            width, height, x, y, image.0
+        )
+        .unwrap();
+    }
+
+    pub fn fill_rect(&mut self, rgb: [u8; 3], x: f64, y: f64, width: f64, height: f64) {
+        writeln!(
+            self.ops,
+            "{:.3} {:.3} {:.3} rg {:.3} {:.3} {:.3} {:.3} re f",
+            rgb[0] as f64 / 255.0,
+            rgb[1] as f64 / 255.0,
+            rgb[2] as f64 / 255.0,
+            x,
+            y,
+            width,
+            height
+        )
+        .unwrap();
+    }
+
+    pub fn line(&mut self, from: (f64, f64), to: (f64, f64)) {
+        writeln!(
+            self.ops,
+            "0.5 w 0 0 0 RG {:.3} {:.3} m {:.3} {:.3} l S",
+            from.0, from.1, to.0, to.1
+        )
+        .unwrap();
+    }
+}
+
+/// Images and pages are written out as soon as they're added, so the caller
+/// only has to keep the images for the page it's building in memory.
+#[derive(Debug)]
+pub struct PdfDocument {
+    width: f64,
+    height: f64,
+    out: Vec<u8>,
+    /// Byte offset of each object, by object number. Object 0 is the free list
+    /// head, 1 the catalog and 2 the page tree, which are written last.
+    offsets: Vec<usize>,
+    pages: Vec<usize>,
+}
+
+impl PdfDocument {
+    /// All pages share the same size, in points.
+    pub fn new(width: f64, height: f64) -> Self {
+        PdfDocument {
+            width,
+            height,
+            out: b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec(),
+            offsets: vec![0; 3],
+            pages: vec![],
+        }
+    }
+
+    fn begin_object(&mut self) -> usize {
+        let id = self.offsets.len();
+        self.offsets.push(self.out.len());
+        self.out.extend(format!("{} 0 obj\n", id).as_bytes());
+        id
+    }
+
+    fn write_object(&mut self, id: usize, body: &str) {
+        self.offsets[id] = self.out.len();
+        self.out
+            .extend(format!("{} 0 obj\n{}\nendobj\n", id, body).as_bytes());
+    }
+
+    pub fn add_image(&mut self, image: &JpegImage) -> ImageId {
+        let id = self.begin_object();
+        self.out.extend(
+            format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode \
+                 /Length {} >>\nstream\n",
+                image.width,
+                image.height,
+                image.data.len()
+            )
+            .as_bytes(),
+        );
+        self.out.extend(&image.data);
+        self.out.extend(b"\nendstream\nendobj\n");
+        ImageId(id)
+    }
+
+    pub fn add_page(&mut self, page: PageContent) {
+        let xobjects: Vec<String> = page
+            .images
+            .iter()
+            .map(|image| format!("/Im{} {} 0 R", image.0, image.0))
+            .collect();
+        let page_id = self.offsets.len();
+        let content_id = page_id + 1;
+        self.offsets.extend(&[0, 0]);
+        self.write_object(
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.3} {:.3}] \
+                 /Resources << /XObject << {} >> >> /Contents {} 0 R >>",
+                self.width,
+                self.height,
+                xobjects.join(" "),
+                content_id
+            ),
+        );
+        self.write_object(
+            content_id,
+            &format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                page.ops.len(),
+                page.ops
+            ),
+        );
+        self.pages.push(page_id);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.write_object(1, "<< /Type /Catalog /Pages 2 0 R >>");
+        let kids: Vec<String> = self.pages.iter().map(|id| format!("{} 0 R", id)).collect();
+        self.write_object(
+            2,
+            &format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.pages.len()
+            ),
+        );
+
+        let object_count = self.offsets.len();
+        let xref_offset = self.out.len();
+        let mut out = self.out;
+        out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", object_count).as_bytes());
+        for offset in self.offsets.iter().skip(1) {
+            out.extend(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                object_count, xref_offset
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JpegImage, PageContent, PdfDocument};
+
+    #[test]
+    fn xref_offsets_point_at_objects() {
+        let mut doc = PdfDocument::new(612.0, 792.0);
+        let image = doc.add_image(&JpegImage {
+            width: 1,
+            height: 1,
+            data: vec![0xff, 0xd8, 0xff, 0xd9],
+        });
+        for _ in 0..2 {
+            let mut page = PageContent::default();
+            page.image(image, 10.0, 10.0, 100.0, 140.0);
+            page.line((0.0, 0.0), (10.0, 10.0));
+            doc.add_page(page);
+        }
+        let bytes = doc.finish();
+        // Only the header and image data are binary, so the trailer and xref
+        // table can be read as text.
+        let trailer = std::str::from_utf8(&bytes[bytes.len() - 60..]).unwrap();
+        let startxref = trailer.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let xref_offset: usize = trailer[startxref..]
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let xref = std::str::from_utf8(&bytes[xref_offset..]).unwrap();
+        assert!(xref.starts_with("xref\n0 8\n"));
+
+        for (i, entry) in xref.lines().skip(3).take(7).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            let expected = format!("{} 0 obj\n", i + 1);
+            assert!(
+                bytes[offset..].starts_with(expected.as_bytes()),
+                "xref entry for object {} is wrong",
+                i + 1
+            );
+        }
+    }
+}
@@ -14,9 +14,12 @@ use std::{
     fmt,
     str::FromStr,
 };
+use ttsmagic_types::DeckColorIdentity;
+use url::Url;
 use uuid::Uuid;
 
 pub mod api;
+pub mod search;
 
 use self::api::ScryfallApi;
 
@@ -66,6 +69,12 @@ impl ScryfallOracleId {
     }
 }
 
+impl From<Uuid> for ScryfallOracleId {
+    fn from(uuid: Uuid) -> ScryfallOracleId {
+        ScryfallOracleId(uuid)
+    }
+}
+
 impl FromStr for ScryfallOracleId {
     type Err = <Uuid as FromStr>::Err;
     fn from_str(id: &str) -> Result<Self, Self::Err> {
@@ -222,6 +231,12 @@ RETURNING updated_at
             .await
     }
 
+    pub async fn ensure_art_crop(&self, api: &ScryfallApi) -> Result<RgbImage> {
+        let id = self.id()?;
+        api.get_image_by_id(id, api::ImageFormat::ArtCrop, api::ImageFace::Front)
+            .await
+    }
+
     pub fn description(&self) -> Result<String> {
         match (self.cost(), self.type_line(), self.oracle_text()) {
             (Ok(cost), Ok(tl), Ok(text)) => Ok(format!("{}\n\n{}\n\n{}", cost, tl, text)),
@@ -258,12 +273,191 @@ RETURNING updated_at
             .as_str()
             .ok_or_else(|| anyhow!("Card JSON \"oracle_text\" field is not a string"))
     }
+
+    pub fn set_code(&self) -> Result<&str> {
+        self.json
+            .get("set")
+            .ok_or_else(|| anyhow!("Card JSON missing \"set\" field"))?
+            .as_str()
+            .ok_or_else(|| anyhow!("Card JSON \"set\" field is not a string"))
+    }
+
+    pub fn collector_number(&self) -> Result<&str> {
+        self.json
+            .get("collector_number")
+            .ok_or_else(|| anyhow!("Card JSON missing \"collector_number\" field"))?
+            .as_str()
+            .ok_or_else(|| anyhow!("Card JSON \"collector_number\" field is not a string"))
+    }
+
+    pub fn layout(&self) -> Result<&str> {
+        self.json
+            .get("layout")
+            .ok_or_else(|| anyhow!("Card JSON missing \"layout\" field"))?
+            .as_str()
+            .ok_or_else(|| anyhow!("Card JSON \"layout\" field is not a string"))
+    }
+
+    /// Scryfall's image URL in the given size ("small", "normal", "large",
+    /// "png", "art_crop" or "border_crop"), using the front face for cards
+    /// with a separate image for each face.
+    pub fn image_url(&self, size: &str) -> Result<Url> {
+        let image_uris = match self.json.get("image_uris") {
+            Some(uris) => uris,
+            None => self
+                .json
+                .pointer("/card_faces/0/image_uris")
+                .ok_or_else(|| anyhow!("Card JSON missing \"image_uris\" field"))?,
+        };
+        let url = image_uris
+            .get(size)
+            .ok_or_else(|| anyhow!("Card JSON has no {:?} image", size))?
+            .as_str()
+            .ok_or_else(|| anyhow!("Card JSON {:?} image URL is not a string", size))?;
+        Ok(Url::parse(url)?)
+    }
+
+    /// One of "legal", "not_legal", "restricted" or "banned".
+    pub fn legality(&self, format: &str) -> Result<&str> {
+        self.json
+            .get("legalities")
+            .ok_or_else(|| anyhow!("Card JSON missing \"legalities\" field"))?
+            .get(format)
+            .ok_or_else(|| anyhow!("Card JSON has no legality for format {:?}", format))?
+            .as_str()
+            .ok_or_else(|| anyhow!("Card JSON legality for {:?} is not a string", format))
+    }
+
+    pub fn color_identity(&self) -> Result<DeckColorIdentity> {
+        let colors = self
+            .json
+            .get("color_identity")
+            .ok_or_else(|| anyhow!("Card JSON missing \"color_identity\" field"))?
+            .as_array()
+            .ok_or_else(|| anyhow!("Card JSON \"color_identity\" field is not an array"))?;
+        let mut ci = DeckColorIdentity::default();
+        for color in colors {
+            match color.as_str() {
+                Some("B") => ci.black = true,
+                Some("U") => ci.blue = true,
+                Some("G") => ci.green = true,
+                Some("R") => ci.red = true,
+                Some("W") => ci.white = true,
+                other => {
+                    return Err(anyhow!(
+                        "Got an unexpected color in a card's color identity: {:?}",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(ci)
+    }
+
+    /// How this printing looks, for choosing between printings.
+    pub fn printing(&self) -> Result<ttsmagic_types::CardPrinting> {
+        let text = |key: &str| self.json.get(key).and_then(Value::as_str);
+        let flag = |key: &str| self.json.get(key).and_then(Value::as_bool);
+        Ok(ttsmagic_types::CardPrinting {
+            scryfall_id: self.id()?.as_uuid(),
+            set_code: self.set_code()?.to_string(),
+            set_name: text("set_name").unwrap_or("").to_string(),
+            collector_number: self.collector_number()?.to_string(),
+            released_at: text("released_at").and_then(|date| date.parse().ok()),
+            frame: text("frame").unwrap_or("").to_string(),
+            full_art: flag("full_art").unwrap_or(false),
+            borderless: text("border_color") == Some("borderless"),
+            image_url: self.image_url("small").ok(),
+        })
+    }
+
+    /// The parts of the card's Scryfall data that the API and frontend show.
+    pub fn summary(&self) -> Result<ttsmagic_types::Card> {
+        fn face(json: &Value) -> Result<ttsmagic_types::CardFace> {
+            let text = |key: &str| json.get(key).and_then(Value::as_str).unwrap_or("");
+            let image_urls = match json.get("image_uris") {
+                Some(uris) => Some(serde_json::from_value(uris.clone())?),
+                None => None,
+            };
+            Ok(ttsmagic_types::CardFace {
+                name: text("name").to_string(),
+                mana_cost: text("mana_cost").to_string(),
+                type_line: text("type_line").to_string(),
+                oracle_text: text("oracle_text").to_string(),
+                image_urls,
+            })
+        }
+        let faces = match self.json.get("card_faces").and_then(Value::as_array) {
+            Some(faces) => faces.iter().map(face).collect::<Result<Vec<_>>>()?,
+            None => vec![face(&self.json)?],
+        };
+        let image_urls = match self.json.get("image_uris") {
+            Some(uris) => Some(serde_json::from_value(uris.clone())?),
+            None => faces.first().and_then(|f| f.image_urls.clone()),
+        };
+        let legalities = match self.json.get("legalities") {
+            Some(legalities) => serde_json::from_value(legalities.clone())
+                .context("Card JSON has an unexpected legality")?,
+            None => Default::default(),
+        };
+        Ok(ttsmagic_types::Card {
+            scryfall_id: self.id()?.as_uuid(),
+            oracle_id: self.oracle_id().ok().map(ScryfallOracleId::as_uuid),
+            name: self.combined_name(),
+            faces,
+            set_code: self.set_code()?.to_string(),
+            collector_number: self.collector_number()?.to_string(),
+            legalities,
+            image_urls,
+        })
+    }
+}
+
+/// Every English printing of a card, newest first.
+pub async fn printings_by_oracle_id<'db, 'a: 'db, DB>(
+    db: &'a mut DB,
+    oracle_id: ScryfallOracleId,
+) -> Result<Vec<ScryfallCard>>
+where
+    &'a mut DB: Executor<'db, Database = Postgres>,
+{
+    debug!("Checking database for printings of oracle ID {}", oracle_id);
+    let rows: Vec<ScryfallCardRow> = sqlx::query_as(
+        "\
+SELECT json::text, updated_at FROM scryfall_card
+WHERE
+    (json ->> 'oracle_id')::uuid = $1
+AND (json ->> 'lang') = 'en'
+ORDER BY
+    (json ->> 'released_at')::date DESC,
+    json ->> 'set' ASC,
+    json ->> 'collector_number' ASC
+;",
+    )
+    .bind(oracle_id.as_uuid())
+    .fetch_all(db)
+    .await?;
+    rows.into_iter().map(ScryfallCard::try_from).collect()
 }
 
 pub async fn card_by_id<'db, 'a: 'db, DB: 'db>(
     db: &'a mut DB,
     id: ScryfallId,
 ) -> Result<ScryfallCard>
+where
+    &'a mut DB: Executor<'db, Database = Postgres>,
+{
+    find_card_by_id(db, id)
+        .await?
+        .ok_or_else(|| anyhow!("Failed to find a matching card with Scryfall ID {}", id))
+}
+
+/// Like [`card_by_id`], but a card that isn't in the database is `None`
+/// instead of an error.
+pub async fn find_card_by_id<'db, 'a: 'db, DB: 'db>(
+    db: &'a mut DB,
+    id: ScryfallId,
+) -> Result<Option<ScryfallCard>>
 where
     &'a mut DB: Executor<'db, Database = Postgres>,
 {
@@ -278,16 +472,7 @@ WHERE
     .bind(id.as_uuid())
     .fetch_optional(db)
     .await?;
-    match row_opt {
-        None => Err(anyhow!(
-            "Failed to find a matching card with Scryfall ID {}",
-            id
-        )),
-        Some(row) => match ScryfallCard::try_from(row) {
-            Ok(card) => Ok(card),
-            Err(e) => Err(e),
-        },
-    }
+    row_opt.map(ScryfallCard::try_from).transpose()
 }
 
 pub async fn oracle_id_by_name<'db, 'a: 'db, DB>(
@@ -320,6 +505,42 @@ LIMIT 1
     Ok(ScryfallOracleId(oracle_id))
 }
 
+/// The newest English printing of the card with the given name. Art series
+/// cards never match, and tokens and emblems only match if `tokens` is set.
+pub async fn card_by_name<'db, 'a: 'db, DB>(
+    db: &'a mut DB,
+    name: &str,
+    tokens: bool,
+) -> Result<Option<ScryfallCard>>
+where
+    &'a mut DB: Executor<'db, Database = Postgres>,
+{
+    debug!("Checking database for the newest card named \"{}\"", name);
+    let row_opt: Option<ScryfallCardRow> = sqlx::query_as(
+        "\
+SELECT json::text, updated_at FROM scryfall_card
+WHERE
+    string_to_array(lower(json ->> 'name'), ' // ') @> string_to_array(lower($1), ' // ')
+AND (json ->> 'lang') = 'en'
+AND (json ->> 'layout') IS DISTINCT FROM 'art_series'
+AND ($2 OR (json ->> 'layout') NOT IN ('token', 'double_faced_token', 'emblem'))
+ORDER BY
+    (json ->> 'released_at')::date DESC,
+    json ->> 'collector_number' ASC
+LIMIT 1
+;",
+    )
+    .bind(name)
+    .bind(tokens)
+    .fetch_optional(db)
+    .await
+    .with_context(|| format!("Failed to get card with name {:?} from the database", name))?;
+    match row_opt {
+        None => Ok(None),
+        Some(row) => Ok(Some(ScryfallCard::try_from(row)?)),
+    }
+}
+
 pub async fn load_bulk<P: AsRef<Path>>(
     api: &ScryfallApi,
     db: &mut PgConnection,
@@ -727,3 +948,112 @@ LIMIT 1
     let opt_row = query.fetch_optional(db).await?;
     Ok(opt_row.is_some())
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use ttsmagic_types::CardLegality;
+
+    use super::card_by_name;
+    use crate::test_helpers::{run_with_test_db, test_card};
+
+    fn image_uris(name: &str) -> serde_json::Value {
+        let mut uris = serde_json::Map::new();
+        for size in &["small", "normal", "large", "png", "art_crop", "border_crop"] {
+            let url = format!("https://cards.scryfall.io/{}/{}.jpg", size, name);
+            uris.insert(size.to_string(), json!(url));
+        }
+        uris.into()
+    }
+
+    #[test]
+    fn double_faced_card_summary() {
+        let card = test_card(
+            "00000000-0000-0000-0000-000000000001",
+            "Esika, God of the Tree // The Prismatic Bridge",
+            json!({
+                "oracle_id": "00000000-0000-0000-0000-0000000000aa",
+                "layout": "modal_dfc",
+                "set": "khm",
+                "collector_number": "168",
+                "legalities": { "commander": "legal", "standard": "not_legal" },
+                "card_faces": [
+                    {
+                        "name": "Esika, God of the Tree",
+                        "mana_cost": "{1}{G}{G}",
+                        "type_line": "Legendary Creature — God",
+                        "oracle_text": "Vigilance",
+                        "image_uris": image_uris("esika"),
+                    },
+                    {
+                        "name": "The Prismatic Bridge",
+                        "mana_cost": "{W}{U}{B}{R}{G}",
+                        "type_line": "Legendary Enchantment",
+                        "oracle_text": "",
+                        "image_uris": image_uris("bridge"),
+                    },
+                ],
+            }),
+        );
+        let summary = card.summary().unwrap();
+        assert_eq!(
+            summary.name,
+            "Esika, God of the Tree // The Prismatic Bridge"
+        );
+        assert_eq!(summary.faces.len(), 2);
+        assert_eq!(summary.faces[1].mana_cost, "{W}{U}{B}{R}{G}");
+        assert_eq!(
+            summary.legalities.get("standard"),
+            Some(&CardLegality::NotLegal)
+        );
+        assert_eq!(
+            summary.image_urls.unwrap().small.as_str(),
+            "https://cards.scryfall.io/small/esika.jpg"
+        );
+    }
+
+    #[test]
+    fn card_by_name_skips_art_series_and_tokens() {
+        run_with_test_db(|db| {
+            Box::pin(async move {
+                let mut conn = db.acquire().await.unwrap();
+                let cards = [
+                    ("1", "Sol Ring", "normal", "2020-01-01"),
+                    ("2", "Sol Ring // Sol Ring", "art_series", "2021-01-01"),
+                    ("3", "Treasure", "token", "2021-01-01"),
+                ];
+                for (id, name, layout, released_at) in cards.iter() {
+                    sqlx::query("INSERT INTO scryfall_card ( json ) VALUES ( $1::jsonb );")
+                        .bind(
+                            json!({
+                                "id": format!("00000000-0000-0000-0000-00000000000{}", id),
+                                "name": name,
+                                "lang": "en",
+                                "layout": layout,
+                                "released_at": released_at,
+                                "collector_number": "1",
+                            })
+                            .to_string(),
+                        )
+                        .execute(&mut conn)
+                        .await
+                        .unwrap();
+                }
+
+                let sol_ring = card_by_name(&mut conn, "Sol Ring", false)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(sol_ring.combined_name(), "Sol Ring");
+                assert!(card_by_name(&mut conn, "Treasure", false)
+                    .await
+                    .unwrap()
+                    .is_none());
+                assert!(card_by_name(&mut conn, "Treasure", true)
+                    .await
+                    .unwrap()
+                    .is_some());
+            })
+        });
+    }
+}
@@ -22,7 +22,6 @@ pub enum ImageFormat {
     PNG,
     #[allow(unused)]
     BorderCrop,
-    #[allow(unused)]
     ArtCrop,
     Large,
     Normal,
@@ -135,8 +134,13 @@ impl ScryfallApi {
     ) -> Result<RgbImage> {
         let mut format_opt = Some(format);
         let mut last_error = None;
-        // Look for existing files first.
-        for format in &[ImageFormat::PNG, ImageFormat::Large] {
+        // Look for existing files first. Any full card image will do, but a
+        // crop of the art has to be the real thing.
+        let existing_formats: &[ImageFormat] = match format {
+            ImageFormat::ArtCrop => &[],
+            _ => &[ImageFormat::PNG, ImageFormat::Large],
+        };
+        for format in existing_formats {
             let rel_filename = Self::card_image_rel_filename(id, *format);
             if let Some(mut f) = MediaFile::open_if_exists(&rel_filename).await? {
                 let mut buffer = vec![];
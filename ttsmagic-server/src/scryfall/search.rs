@@ -0,0 +1,412 @@
+//! Searching the local copy of Scryfall's cards with a subset of Scryfall's
+//! own search syntax. Terms are separated by spaces and all have to match,
+//! and a leading `-` negates a term. Bare words and quoted phrases search
+//! card names, and these keywords are supported:
+//!
+//! - `t:` / `type:` searches the type line
+//! - `o:` / `oracle:` searches the rules text
+//! - `c:` / `color:` and `id:` / `identity:` compare colors, like `c:rg`
+//! - `cmc` / `mv` compares mana value, like `cmc<=3`
+//! - `s:` / `set:` / `e:` matches a set code
+//! - `f:` / `format:` matches cards that are legal in a format
+
+use anyhow::{anyhow, ensure, Result};
+use sqlx::{Executor, Postgres};
+use std::{convert::TryFrom, fmt::Write, str::FromStr};
+
+use super::{ScryfallCard, ScryfallCardRow};
+
+/// How many cards a search returns at most.
+pub const RESULT_LIMIT: i64 = 60;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn sql(self) -> &'static str {
+        match self {
+            Comparison::Equal => "=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    Name(String),
+    Type(String),
+    Oracle(String),
+    /// Colors are a string of `WUBRG` letters, in that order. An empty string
+    /// is colorless.
+    Colors(Comparison, String),
+    Identity(Comparison, String),
+    ManaValue(Comparison, f64),
+    Set(String),
+    Format(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Term {
+    pub negated: bool,
+    pub filter: Filter,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+/// A value to bind to a query parameter, in the order of the `$N`s.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Bind {
+    Text(String),
+    Number(f64),
+}
+
+/// Splits the query on whitespace outside of double quotes, dropping the
+/// quotes.
+fn split_terms(query: &str) -> Result<Vec<String>> {
+    let mut terms = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in query.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    ensure!(!in_quotes, "Unclosed quote in search");
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    Ok(terms)
+}
+
+/// Splits `key<op>value` terms. Bare words have no key.
+fn split_keyword(term: &str) -> Option<(&str, Option<Comparison>, &str)> {
+    let key_len = term.find(|c: char| !c.is_ascii_alphabetic())?;
+    if key_len == 0 {
+        return None;
+    }
+    let (key, rest) = term.split_at(key_len);
+    const OPERATORS: &[(&str, Option<Comparison>)] = &[
+        (":", None),
+        ("<=", Some(Comparison::LessOrEqual)),
+        (">=", Some(Comparison::GreaterOrEqual)),
+        ("=", Some(Comparison::Equal)),
+        ("<", Some(Comparison::Less)),
+        (">", Some(Comparison::Greater)),
+    ];
+    OPERATORS
+        .iter()
+        .find(|(op, _)| rest.starts_with(op))
+        .map(|(op, comparison)| (key, *comparison, &rest[op.len()..]))
+}
+
+fn parse_colors(value: &str) -> Result<String> {
+    let value = value.to_lowercase();
+    let letters = match value.as_str() {
+        "c" | "colorless" => "",
+        "white" => "w",
+        "blue" => "u",
+        "black" => "b",
+        "red" => "r",
+        "green" => "g",
+        letters => letters,
+    };
+    if let Some(bad) = letters.chars().find(|c| !"wubrg".contains(*c)) {
+        return Err(anyhow!("{:?} isn't a color", bad));
+    }
+    Ok("WUBRG"
+        .chars()
+        .filter(|c| letters.contains(c.to_ascii_lowercase()))
+        .collect())
+}
+
+fn parse_term(term: &str) -> Result<Term> {
+    let (negated, term) = match term.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, term),
+    };
+    let (key, comparison, value) = match split_keyword(term) {
+        Some(parts) => parts,
+        None => {
+            return Ok(Term {
+                negated,
+                filter: Filter::Name(term.to_lowercase()),
+            })
+        }
+    };
+    ensure!(!value.is_empty(), "Missing a value after {:?}", key);
+    let key = key.to_lowercase();
+    let text_only = |filter: fn(String) -> Filter| match comparison {
+        None => Ok(filter(value.to_lowercase())),
+        Some(_) => Err(anyhow!("{:?} can only be used with \":\"", key)),
+    };
+    let filter = match key.as_str() {
+        "t" | "type" => text_only(Filter::Type)?,
+        "o" | "oracle" => text_only(Filter::Oracle)?,
+        "s" | "set" | "e" | "edition" => text_only(Filter::Set)?,
+        "f" | "format" => text_only(Filter::Format)?,
+        "c" | "color" => {
+            let colors = parse_colors(value)?;
+            let default = if colors.is_empty() {
+                Comparison::Equal
+            } else {
+                Comparison::GreaterOrEqual
+            };
+            Filter::Colors(comparison.unwrap_or(default), colors)
+        }
+        "id" | "identity" => {
+            let colors = parse_colors(value)?;
+            Filter::Identity(comparison.unwrap_or(Comparison::LessOrEqual), colors)
+        }
+        "cmc" | "mv" => {
+            let mana_value =
+                f64::from_str(value).map_err(|_| anyhow!("{:?} isn't a mana value", value))?;
+            Filter::ManaValue(comparison.unwrap_or(Comparison::Equal), mana_value)
+        }
+        _ => return Err(anyhow!("Unknown search keyword {:?}", key)),
+    };
+    Ok(Term { negated, filter })
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        let terms = split_terms(query)?
+            .iter()
+            .map(|term| parse_term(term))
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(!terms.is_empty(), "Search for something");
+        Ok(Query { terms })
+    }
+}
+
+/// Compares a JSONB array of color letters with a set of colors.
+fn colors_sql(column: &str, comparison: Comparison, param: usize) -> String {
+    let colors = format!("${}::jsonb", param);
+    match comparison {
+        Comparison::Equal => format!("({0} @> {1} AND {0} <@ {1})", column, colors),
+        Comparison::GreaterOrEqual => format!("{} @> {}", column, colors),
+        Comparison::LessOrEqual => format!("{} <@ {}", column, colors),
+        Comparison::Greater => format!("({0} @> {1} AND NOT {0} <@ {1})", column, colors),
+        Comparison::Less => format!("({0} <@ {1} AND NOT {0} @> {1})", column, colors),
+    }
+}
+
+fn colors_json(colors: &str) -> String {
+    let letters: Vec<String> = colors.chars().map(|c| c.to_string()).collect();
+    serde_json::to_string(&letters).unwrap()
+}
+
+impl Query {
+    /// The condition on `scryfall_card` rows this query stands for, along
+    /// with the values for its parameters, which start at `$first_param`.
+    pub fn to_sql(&self, first_param: usize) -> (String, Vec<Bind>) {
+        let mut sql = String::new();
+        let mut binds = vec![];
+        for term in self.terms.iter() {
+            let param = first_param + binds.len();
+            let (condition, bind) = match &term.filter {
+                Filter::Name(name) => (
+                    format!("strpos(lower(json ->> 'name'), ${}) > 0", param),
+                    Bind::Text(name.clone()),
+                ),
+                Filter::Type(type_line) => (
+                    format!("strpos(lower(json ->> 'type_line'), ${}) > 0", param),
+                    Bind::Text(type_line.clone()),
+                ),
+                Filter::Oracle(text) => (
+                    format!(
+                        "strpos(lower(concat_ws(E'\\n', json ->> 'oracle_text', \
+                         json #>> '{{card_faces,0,oracle_text}}', \
+                         json #>> '{{card_faces,1,oracle_text}}')), ${}) > 0",
+                        param
+                    ),
+                    Bind::Text(text.clone()),
+                ),
+                Filter::Colors(comparison, colors) => (
+                    colors_sql(
+                        "COALESCE(json -> 'colors', json #> '{card_faces,0,colors}')",
+                        *comparison,
+                        param,
+                    ),
+                    Bind::Text(colors_json(colors)),
+                ),
+                Filter::Identity(comparison, colors) => (
+                    colors_sql("(json -> 'color_identity')", *comparison, param),
+                    Bind::Text(colors_json(colors)),
+                ),
+                Filter::ManaValue(comparison, mana_value) => (
+                    format!("(json ->> 'cmc')::float8 {} ${}", comparison.sql(), param),
+                    Bind::Number(*mana_value),
+                ),
+                Filter::Set(set_code) => (
+                    format!("lower(json ->> 'set') = ${}", param),
+                    Bind::Text(set_code.clone()),
+                ),
+                Filter::Format(format) => (
+                    format!(
+                        "(json -> 'legalities' ->> ${}) IN ('legal', 'restricted')",
+                        param
+                    ),
+                    Bind::Text(format.clone()),
+                ),
+            };
+            if !sql.is_empty() {
+                sql.push_str("\nAND ");
+            }
+            if term.negated {
+                write!(sql, "NOT COALESCE({}, FALSE)", condition).unwrap();
+            } else {
+                sql.push_str(&condition);
+            }
+            binds.push(bind);
+        }
+        (sql, binds)
+    }
+}
+
+/// The newest English printing of each card matching the query, sorted by
+/// name.
+pub async fn search<'db, 'a: 'db, DB>(
+    db: &'a mut DB,
+    query: &Query,
+    limit: i64,
+) -> Result<Vec<ScryfallCard>>
+where
+    &'a mut DB: Executor<'db, Database = Postgres>,
+{
+    debug!("Searching for cards matching {:?}", query);
+    let (condition, binds) = query.to_sql(2);
+    let sql = format!(
+        "\
+SELECT json::text, updated_at FROM (
+  SELECT DISTINCT ON (json ->> 'oracle_id') json, updated_at
+  FROM scryfall_card
+  WHERE
+      (json ->> 'lang') = 'en'
+  AND (json ->> 'oracle_id') IS NOT NULL
+  AND {}
+  ORDER BY
+      json ->> 'oracle_id',
+      (json ->> 'released_at')::date DESC,
+      json ->> 'collector_number' ASC
+) AS matching
+ORDER BY json ->> 'name'
+LIMIT $1
+;",
+        condition
+    );
+    let mut query = sqlx::query_as(&sql).bind(limit);
+    for bind in binds {
+        query = match bind {
+            Bind::Text(text) => query.bind(text),
+            Bind::Number(number) => query.bind(number),
+        };
+    }
+    let rows: Vec<ScryfallCardRow> = query.fetch_all(db).await?;
+    rows.into_iter().map(ScryfallCard::try_from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bind, Comparison, Filter, Query, Term};
+    use std::str::FromStr;
+
+    fn term(filter: Filter) -> Term {
+        Term {
+            negated: false,
+            filter,
+        }
+    }
+
+    #[test]
+    fn parses_keywords_and_names() {
+        let query = Query::from_str(
+            "\"Lightning Bolt\" t:instant -o:draw c:RG id<=wubrg cmc>=2 s:M10 f:modern",
+        )
+        .unwrap();
+        assert_eq!(
+            query.terms,
+            vec![
+                term(Filter::Name("lightning bolt".to_string())),
+                term(Filter::Type("instant".to_string())),
+                Term {
+                    negated: true,
+                    filter: Filter::Oracle("draw".to_string()),
+                },
+                term(Filter::Colors(Comparison::GreaterOrEqual, "RG".to_string())),
+                term(Filter::Identity(
+                    Comparison::LessOrEqual,
+                    "WUBRG".to_string()
+                )),
+                term(Filter::ManaValue(Comparison::GreaterOrEqual, 2.0)),
+                term(Filter::Set("m10".to_string())),
+                term(Filter::Format("modern".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn defaults_comparisons_like_scryfall() {
+        let query = Query::from_str("c:c id:gw mv:3").unwrap();
+        assert_eq!(
+            query.terms,
+            vec![
+                term(Filter::Colors(Comparison::Equal, String::new())),
+                term(Filter::Identity(Comparison::LessOrEqual, "WG".to_string())),
+                term(Filter::ManaValue(Comparison::Equal, 3.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        assert!(Query::from_str("").is_err());
+        assert!(Query::from_str("   ").is_err());
+        assert!(Query::from_str("\"unclosed").is_err());
+        assert!(Query::from_str("pow:3").is_err());
+        assert!(Query::from_str("c:purple").is_err());
+        assert!(Query::from_str("cmc:x").is_err());
+        assert!(Query::from_str("t>creature").is_err());
+        assert!(Query::from_str("t:").is_err());
+    }
+
+    #[test]
+    fn builds_sql() {
+        let query = Query::from_str("bolt -c>=r cmc<2").unwrap();
+        let (sql, binds) = query.to_sql(2);
+        assert_eq!(
+            sql,
+            "strpos(lower(json ->> 'name'), $2) > 0\n\
+             AND NOT COALESCE(COALESCE(json -> 'colors', json #> '{card_faces,0,colors}') \
+             @> $3::jsonb, FALSE)\n\
+             AND (json ->> 'cmc')::float8 < $4"
+        );
+        assert_eq!(
+            binds,
+            vec![
+                Bind::Text("bolt".to_string()),
+                Bind::Text("[\"R\"]".to_string()),
+                Bind::Number(2.0),
+            ]
+        );
+    }
+}
@@ -2,7 +2,17 @@
 #![allow(unused)]
 
 use async_std::prelude::*;
+use chrono::prelude::*;
 use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::{collections::HashMap, convert::TryInto, str::FromStr};
+use ttsmagic_types::{DeckId, RenderOptions, UserId};
+use url::Url;
+
+use crate::{
+    deck::Deck,
+    scryfall::{ScryfallCard, ScryfallCardRow},
+};
 
 pub fn init_logging() {
     let mut builder = pretty_env_logger::formatted_timed_builder();
@@ -15,6 +25,58 @@ pub fn init_logging() {
     let _ = builder.try_init();
 }
 
+/// A card with the given Scryfall ID and name, plus whatever other Scryfall
+/// fields the test needs from `fields`, which has to be a JSON object.
+pub(crate) fn test_card(id: &str, name: &str, fields: Value) -> ScryfallCard {
+    let mut json = json!({
+        "id": id,
+        "name": name,
+    });
+    let object = json.as_object_mut().unwrap();
+    for (key, value) in fields.as_object().expect("Card fields should be an object") {
+        object.insert(key.clone(), value.clone());
+    }
+    let row = ScryfallCardRow {
+        json: json.to_string(),
+        updated_at: Utc.ymd(2021, 1, 1).and_hms(0, 0, 0),
+    };
+    row.try_into().unwrap()
+}
+
+/// A deck with the given cards and nothing else.
+pub(crate) fn test_deck(
+    commanders: Vec<ScryfallCard>,
+    main_deck: Vec<(ScryfallCard, u8)>,
+    sideboard: Vec<(ScryfallCard, u8)>,
+) -> Deck {
+    let by_id = |cards: Vec<(ScryfallCard, u8)>| -> HashMap<_, _> {
+        cards
+            .into_iter()
+            .map(|(c, n)| (c.id().unwrap(), (c, n)))
+            .collect()
+    };
+    Deck {
+        id: DeckId::from_str("00000000-0000-0000-0000-000000000000").unwrap(),
+        user_id: UserId::from_str("1").unwrap(),
+        title: "Test".to_string(),
+        url: Url::parse("https://example.com/deck").unwrap(),
+        commanders: commanders
+            .into_iter()
+            .map(|c| (c.id().unwrap(), c))
+            .collect(),
+        companions: HashMap::new(),
+        main_deck: by_id(main_deck),
+        sideboard: by_id(sideboard),
+        maybeboard: HashMap::new(),
+        categories: HashMap::new(),
+        render_options: RenderOptions::default(),
+        rendered_json: None,
+        thumbnail: None,
+        share_token: None,
+        modified: false,
+    }
+}
+
 #[inline]
 pub(crate) fn run_with_test_db<F, T>(f: F) -> T
 where
@@ -8,14 +8,19 @@ use serde_json::{json, Value};
 use smallvec::SmallVec;
 use sqlx::PgConnection;
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     convert::{TryFrom, TryInto},
     num::NonZeroU16,
     str::FromStr,
 };
-use ttsmagic_types::server_to_frontend as s2f;
+use ttsmagic_types::{server_to_frontend as s2f, ExportMode, RenderOptions, TokenCopies};
 
+mod game_helpers;
+mod layout;
 mod render_coordinator;
+mod scripting;
+mod thumbnail;
+use layout::PileKind;
 use render_coordinator::wait_for_lock;
 
 use crate::{
@@ -31,6 +36,7 @@ pub struct RenderedDeck {
     pub json_description: Value,
     pub rendered_at: DateTime<Utc>,
     pub pages: Vec<RenderedPage>,
+    pub thumbnail: MediaFile,
 }
 
 #[derive(Clone, Debug)]
@@ -41,14 +47,22 @@ pub struct RenderedPage {
     card_mapping: HashMap<ScryfallId, u8>,
 }
 
-fn get_parts(card: ScryfallCard) -> Box<dyn Iterator<Item = ScryfallId> + Send> {
-    let all_parts: Vec<Value> = match card.raw_json().get("all_parts").map(Value::as_array) {
-        None => return Box::new(std::iter::empty()),
-        Some(None) => return Box::new(std::iter::empty()),
-        Some(Some(all_parts)) => all_parts.clone(),
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum RelatedComponent {
+    Token,
+    MeldPart,
+    MeldResult,
+    ComboPiece,
+}
+
+fn get_parts(card: &ScryfallCard) -> Vec<(ScryfallId, RelatedComponent)> {
+    let all_parts: &Vec<Value> = match card.raw_json().get("all_parts").map(Value::as_array) {
+        None => return vec![],
+        Some(None) => return vec![],
+        Some(Some(all_parts)) => all_parts,
     };
     let card_name = card.combined_name();
-    let iter = all_parts.into_iter().enumerate().filter_map(move |(i, part_json)| {
+    let iter = all_parts.iter().enumerate().filter_map(|(i, part_json)| {
         let raw_part_id_value = match part_json.get("id") {
             Some(raw_id) => raw_id,
             None => {
@@ -97,23 +111,29 @@ fn get_parts(card: ScryfallCard) -> Box<dyn Iterator<Item = ScryfallId> + Send>
                     }
                 }
         };
-        match component_type {
+        let component = match component_type {
             "combo_piece" => {
                 debug!(
                     "Found combo piece {} related to card {}",
                     part_id, card_name
                 );
-                return None;
+                RelatedComponent::ComboPiece
             }
             "meld_part" => {
                 debug!("Found meld part {} related to card {}", part_id, card_name);
-                return None;
+                RelatedComponent::MeldPart
+            }
+            "meld_result" => {
+                debug!(
+                    "Found meld result {} related to card {}",
+                    part_id, card_name
+                );
+                RelatedComponent::MeldResult
+            }
+            "token" => {
+                debug!("Found token {} related to card {}", part_id, card_name);
+                RelatedComponent::Token
             }
-            "meld_result" => debug!(
-                "Found meld result {} related to card {}",
-                part_id, card_name
-            ),
-            "token" => debug!("Found token {} related to card {}", part_id, card_name),
             other => {
                 warn!(
                     "Found unexpected related card component type {:?} for {} related to card {}",
@@ -122,31 +142,86 @@ fn get_parts(card: ScryfallCard) -> Box<dyn Iterator<Item = ScryfallId> + Send>
                 return None;
             }
         };
-        Some(part_id)
+        Some((part_id, component))
     });
-    Box::new(iter)
+    iter.collect()
+}
+
+/// Cards that aren't part of the deck list, but that the deck needs on the
+/// table anyway.
+#[derive(Clone, Debug, Default)]
+struct RelatedCards {
+    /// Tokens created by cards in the deck (or by other tokens), along with
+    /// the number of copies of the cards in the deck that create them.
+    tokens: Vec<(ScryfallCard, u8)>,
+    /// Helper cards like dungeons and emblems. These go in the token pile, but
+    /// one of each is always enough.
+    helpers: Vec<ScryfallCard>,
+    /// Meld partners, meld results, and combo pieces (Un-set hosts and
+    /// augments, dungeons, etc.) that aren't already in the deck.
+    extras: Vec<(ScryfallCard, u8)>,
 }
 
-async fn get_tokens<I>(db: &mut PgConnection, cards: I) -> Result<Vec<ScryfallCard>>
+async fn get_related_cards<I>(db: &mut PgConnection, cards: I) -> Result<RelatedCards>
 where
-    I: Iterator<Item = ScryfallCard>,
+    I: Iterator<Item = (ScryfallCard, u8)>,
 {
-    let (size_hint, _) = cards.size_hint();
-    let mut work_queue = VecDeque::with_capacity(size_hint);
-    for card in cards {
-        work_queue.push_back(card);
+    let deck_cards: Vec<(ScryfallCard, u8)> = cards.collect();
+    let mut work_queue = VecDeque::with_capacity(deck_cards.len());
+    let mut deck_oracle_ids = HashSet::with_capacity(deck_cards.len());
+    for (card, count) in deck_cards.iter() {
+        deck_oracle_ids.insert(card.oracle_id()?);
+        // Only cards from the deck list itself follow meld and combo piece
+        // relations. Tokens list every card that creates them as a combo
+        // piece, so following those from a Treasure would pull in hundreds of
+        // unrelated cards.
+        work_queue.push_back((card.clone(), *count, true));
     }
     let mut seen_ids = HashSet::with_capacity(work_queue.len());
-    let mut parts: HashMap<ScryfallOracleId, ScryfallCard> =
+    let mut token_cards: HashMap<ScryfallOracleId, ScryfallCard> =
         HashMap::with_capacity(work_queue.len());
+    // Oracle IDs of the tokens fetched so far, by the ID of the printing.
+    let mut token_ids: HashMap<ScryfallId, ScryfallOracleId> = HashMap::new();
+    let mut token_sources = TokenSources::default();
+    let mut extras: HashMap<ScryfallOracleId, (ScryfallCard, u8)> = HashMap::new();
 
-    while let Some(card) = work_queue.pop_front() {
+    while let Some((card, count, from_deck)) = work_queue.pop_front() {
         if !seen_ids.insert(card.id()?) {
             // We've seen this card before, don't reprocess it.
             continue;
         }
         let card_name = card.combined_name();
-        for part_id in get_parts(card.clone()) {
+        for (part_id, component) in get_parts(&card) {
+            if component == RelatedComponent::Token {
+                let part_oracle_id = match token_ids.get(&part_id) {
+                    Some(oracle_id) => *oracle_id,
+                    None => {
+                        let part_card = scryfall::card_by_id(&mut *db, part_id)
+                            .await
+                            .with_context(|| {
+                                format!("Failed to get token {} for {}", part_id, card_name)
+                            })?;
+                        let part_oracle_id = part_card.oracle_id()?;
+                        token_ids.insert(part_id, part_oracle_id);
+                        if !deck_oracle_ids.contains(&part_oracle_id) {
+                            work_queue.push_back((part_card.clone(), 0, false));
+                            token_cards.entry(part_oracle_id).or_insert(part_card);
+                        }
+                        part_oracle_id
+                    }
+                };
+                if deck_oracle_ids.contains(&part_oracle_id) {
+                    debug!("Token {} is already in the deck", part_id);
+                } else if from_deck {
+                    token_sources.add_deck_card(part_oracle_id, count);
+                } else {
+                    token_sources.add_token(part_oracle_id, card.oracle_id()?);
+                }
+                continue;
+            }
+            if !from_deck {
+                continue;
+            }
             if seen_ids.contains(&part_id) {
                 debug!("Already seen part {}", part_id);
                 continue;
@@ -157,41 +232,160 @@ where
                     format!("Failed to get related card {} for {}", part_id, card_name)
                 })?;
             let part_oracle_id = part_card.oracle_id()?;
-            work_queue.push_back(part_card.clone());
-            parts.entry(part_oracle_id).or_insert(part_card);
+            if deck_oracle_ids.contains(&part_oracle_id) {
+                // This covers both the card listing itself as a meld part and
+                // a meld partner that's already in the deck.
+                debug!(
+                    "Related card {} is already in the deck",
+                    part_card.combined_name()
+                );
+                continue;
+            }
+            // Every copy of the source card could need its own copy of the
+            // extra, but two different sources don't stack.
+            let entry = extras.entry(part_oracle_id).or_insert((part_card, count));
+            entry.1 = entry.1.max(count);
+        }
+    }
+    // Token counts can only be worked out once every card that makes each
+    // token has been found, since tokens that make tokens pass on their own
+    // counts.
+    let token_counts = token_sources.counts();
+    let tokens: HashMap<ScryfallOracleId, (ScryfallCard, u8)> = token_cards
+        .into_iter()
+        .map(|(oracle_id, card)| {
+            let count = token_counts.get(&oracle_id).copied().unwrap_or(0);
+            (oracle_id, (card, count))
+        })
+        .collect();
+    let helpers = game_helpers::get_helper_cards(&mut *db, deck_cards.iter().map(|(c, _)| c))
+        .await
+        .context("Failed to get helper cards for the deck's mechanics")?;
+    let mut helper_oracle_ids = HashSet::with_capacity(helpers.len());
+    let mut helpers_vec = Vec::with_capacity(helpers.len());
+    for helper in helpers {
+        let helper_oracle_id = helper.oracle_id()?;
+        if deck_oracle_ids.contains(&helper_oracle_id) || tokens.contains_key(&helper_oracle_id) {
+            continue;
+        }
+        if helper_oracle_ids.insert(helper_oracle_id) {
+            helpers_vec.push(helper);
         }
     }
-    let mut tokens: Vec<ScryfallCard> = parts.into_iter().map(|(_k, v)| v).collect();
-    tokens.sort_by_key(|c| c.combined_name());
-    Ok(tokens)
+    let helpers = helpers_vec;
+    // Some cards show up as a token for one card and as a combo piece for
+    // another; they only need to be on the table once.
+    extras.retain(|oracle_id, _| {
+        !tokens.contains_key(oracle_id) && !helper_oracle_ids.contains(oracle_id)
+    });
+
+    let mut tokens: Vec<(ScryfallCard, u8)> = tokens.into_iter().map(|(_k, v)| v).collect();
+    tokens.sort_by_key(|(c, _)| c.combined_name());
+    let mut extras: Vec<(ScryfallCard, u8)> = extras.into_iter().map(|(_k, v)| v).collect();
+    extras.sort_by_key(|(c, _)| c.combined_name());
+    Ok(RelatedCards {
+        tokens,
+        helpers,
+        extras,
+    })
+}
+
+/// What makes each token: copies of cards in the deck, and other tokens.
+#[derive(Clone, Debug)]
+struct TokenSources<K> {
+    from_deck: HashMap<K, u8>,
+    from_tokens: HashMap<K, HashSet<K>>,
+}
+
+impl<K> Default for TokenSources<K> {
+    fn default() -> Self {
+        TokenSources {
+            from_deck: HashMap::new(),
+            from_tokens: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + std::hash::Hash> TokenSources<K> {
+    fn add_deck_card(&mut self, token: K, copies: u8) {
+        let entry = self.from_deck.entry(token).or_insert(0);
+        *entry = entry.saturating_add(copies);
+    }
+
+    fn add_token(&mut self, token: K, made_by: K) {
+        if token != made_by {
+            self.from_tokens.entry(token).or_default().insert(made_by);
+        }
+    }
+
+    /// How many cards make each token. A token that other tokens make counts
+    /// once for every card that makes those. Loops between tokens don't add
+    /// anything.
+    fn counts(&self) -> HashMap<K, u8> {
+        // Token graphs are tiny, so there's no need to remember counts
+        // between tokens, which would also make loops count differently
+        // depending on where they're entered.
+        fn count<K: Copy + Eq + std::hash::Hash>(
+            sources: &TokenSources<K>,
+            token: K,
+            visiting: &mut HashSet<K>,
+        ) -> u8 {
+            if !visiting.insert(token) {
+                return 0;
+            }
+            let mut total = sources.from_deck.get(&token).copied().unwrap_or(0);
+            if let Some(makers) = sources.from_tokens.get(&token) {
+                for maker in makers {
+                    total = total.saturating_add(count(sources, *maker, visiting));
+                }
+            }
+            visiting.remove(&token);
+            total
+        }
+
+        self.from_deck
+            .keys()
+            .chain(self.from_tokens.keys())
+            .map(|token| (*token, count(self, *token, &mut HashSet::new())))
+            .collect()
+    }
+}
+
+/// The number of copies of a token to render, given how many cards in the deck
+/// create it.
+fn token_copies(option: TokenCopies, created_by: u8) -> u8 {
+    match option {
+        TokenCopies::Single => 1,
+        TokenCopies::Fixed(copies) => copies.get(),
+        TokenCopies::Estimated => created_by.max(1),
+    }
 }
 
 #[derive(Clone, Debug)]
 struct Pile {
     cards: Vec<(ScryfallCard, u8)>,
-    face_up: bool,
+    kind: PileKind,
+    /// Names the pile in TTS instead of the deck title.
+    label: Option<String>,
 }
 
 impl Pile {
-    fn new_face_up(cards: Vec<(ScryfallCard, u8)>) -> Result<Self> {
+    fn new(kind: PileKind, cards: Vec<(ScryfallCard, u8)>) -> Result<Self> {
         if cards.is_empty() {
             Err(anyhow!("Cannot make a pile of zero cards"))
         } else {
             Ok(Pile {
                 cards,
-                face_up: true,
+                kind,
+                label: None,
             })
         }
     }
 
-    fn new_face_down(cards: Vec<(ScryfallCard, u8)>) -> Result<Self> {
-        if cards.is_empty() {
-            Err(anyhow!("Cannot make a pile of zero cards"))
-        } else {
-            Ok(Pile {
-                cards,
-                face_up: false,
-            })
+    fn with_label(self, label: String) -> Self {
+        Pile {
+            label: Some(label),
+            ..self
         }
     }
 }
@@ -199,7 +393,8 @@ impl Pile {
 #[derive(Clone, Debug)]
 struct LinearPile {
     cards: Vec<(ScryfallCard, u16)>,
-    face_up: bool,
+    kind: PileKind,
+    label: Option<String>,
 }
 
 impl TryFrom<(Pile, &'_ [RenderedPage])> for LinearPile {
@@ -229,12 +424,52 @@ impl TryFrom<(Pile, &'_ [RenderedPage])> for LinearPile {
         }
         Ok(LinearPile {
             cards,
-            face_up: pile.face_up,
+            kind: pile.kind,
+            label: pile.label,
         })
     }
 }
 
-type Piles = SmallVec<[Pile; 4]>;
+type Piles = SmallVec<[Pile; 7]>;
+
+/// Splits the main deck into the library and a pile for each category. If
+/// every card has a category, the biggest category is the library, since the
+/// mulligan buttons need one to go on.
+fn split_categories(
+    main_deck: Vec<(ScryfallCard, u8)>,
+    categories: &HashMap<ScryfallId, String>,
+) -> Result<Vec<Pile>> {
+    let mut uncategorized = vec![];
+    let mut by_category: BTreeMap<&str, Vec<(ScryfallCard, u8)>> = BTreeMap::new();
+    for (card, count) in main_deck {
+        match categories.get(&card.id()?) {
+            Some(category) => by_category.entry(category).or_default().push((card, count)),
+            None => uncategorized.push((card, count)),
+        }
+    }
+    let mut piles = Vec::with_capacity(by_category.len() + 1);
+    if !uncategorized.is_empty() {
+        piles.push(Pile::new(PileKind::Library, uncategorized)?);
+    } else {
+        let biggest = by_category
+            .iter()
+            .max_by_key(|(_, cards)| {
+                cards
+                    .iter()
+                    .map(|(_, count)| *count as usize)
+                    .sum::<usize>()
+            })
+            .map(|(category, _)| *category);
+        if let Some(category) = biggest {
+            let cards = by_category.remove(category).unwrap();
+            piles.push(Pile::new(PileKind::Library, cards)?.with_label(category.to_string()));
+        }
+    }
+    for (category, cards) in by_category {
+        piles.push(Pile::new(PileKind::Category, cards)?.with_label(category.to_string()));
+    }
+    Ok(piles)
+}
 
 async fn collect_card_piles(db: &mut PgConnection, deck: &Deck) -> Result<Piles> {
     let deck_url = deck.url.clone();
@@ -248,6 +483,12 @@ async fn collect_card_piles(db: &mut PgConnection, deck: &Deck) -> Result<Piles>
         pile
     };
 
+    let companions_pile = {
+        let mut pile: Vec<_> = deck.companions.values().map(|c| (c.clone(), 1)).collect();
+        pile.sort_by_key(|(c, _)| c.combined_name());
+        pile
+    };
+
     let main_deck = {
         let mut pile = Vec::with_capacity(deck.main_deck.len());
         for (_, (card, count)) in deck.main_deck.iter() {
@@ -285,42 +526,108 @@ async fn collect_card_piles(db: &mut PgConnection, deck: &Deck) -> Result<Piles>
             .map(|(c, count)| format!("{}x {}", count, c.combined_name()))
             .collect::<Vec<_>>(),
     );
-    let tokens = get_tokens(
+    let maybeboard = if deck.render_options.include_maybeboard {
+        let mut pile = Vec::with_capacity(deck.maybeboard.len());
+        for (_, (card, count)) in deck.maybeboard.iter() {
+            pile.push((card.clone(), count.clone()));
+        }
+        pile.sort_by_key(|(c, _)| c.combined_name());
+        pile
+    } else {
+        vec![]
+    };
+    let RelatedCards {
+        tokens,
+        helpers,
+        extras,
+    } = get_related_cards(
         db,
-        main_deck
+        commanders_pile
             .iter()
+            .chain(companions_pile.iter())
+            .chain(main_deck.iter())
             .chain(sideboard.iter())
-            .map(|(c, _)| c.clone()),
+            .chain(maybeboard.iter())
+            .cloned(),
     )
     .await
     .with_context(|| format!("Failed to get tokens for deck {}", deck_url))?;
-    let tokens: Vec<_> = tokens.into_iter().map(|t| (t, 1)).collect();
+    let tokens = {
+        let token_option = deck.render_options.token_copies;
+        let mut pile: Vec<(ScryfallCard, u8)> = tokens
+            .into_iter()
+            .map(|(card, created_by)| (card, token_copies(token_option, created_by)))
+            .chain(helpers.into_iter().map(|card| (card, 1)))
+            .collect();
+        pile.sort_by_key(|(c, _)| c.combined_name());
+        pile
+    };
     if !tokens.is_empty() {
         debug!(
             "Found tokens: {:?}",
             tokens
                 .iter()
-                .map(|(c, _)| c.combined_name())
+                .map(|(c, count)| format!("{}x {}", count, c.combined_name()))
+                .collect::<Vec<_>>()
+        );
+    }
+    if !extras.is_empty() {
+        debug!(
+            "Found extras: {:?}",
+            extras
+                .iter()
+                .map(|(c, count)| format!("{}x {}", count, c.combined_name()))
                 .collect::<Vec<_>>()
         );
     }
     let mut piles = SmallVec::new();
 
     if !commanders_pile.is_empty() {
-        piles.push(Pile::new_face_up(commanders_pile)?);
+        piles.push(Pile::new(PileKind::Commanders, commanders_pile)?);
+    }
+    if !companions_pile.is_empty() {
+        piles.push(Pile::new(PileKind::Companion, companions_pile)?);
     }
     assert!(!main_deck.is_empty()); // checked earlier
-    piles.push(Pile::new_face_down(main_deck)?);
+    if deck.render_options.split_categories && !deck.categories.is_empty() {
+        piles.extend(split_categories(main_deck, &deck.categories)?);
+    } else {
+        piles.push(Pile::new(PileKind::Library, main_deck)?);
+    }
     if !sideboard.is_empty() {
-        piles.push(Pile::new_face_up(sideboard)?);
+        piles.push(Pile::new(PileKind::Sideboard, sideboard)?);
+    }
+    if !maybeboard.is_empty() {
+        piles.push(Pile::new(PileKind::Maybeboard, maybeboard)?);
     }
     if !tokens.is_empty() {
-        piles.push(Pile::new_face_up(tokens)?);
+        piles.push(Pile::new(PileKind::Tokens, tokens)?);
+    }
+    if !extras.is_empty() {
+        piles.push(Pile::new(PileKind::Extras, extras)?);
     }
 
     Ok(piles)
 }
 
+/// Every card that the deck puts on the table, pile by pile, with the number
+/// of copies of each. The token and extras piles are only included if asked
+/// for.
+pub async fn cards_to_print(
+    db: &mut PgConnection,
+    deck: &Deck,
+    include_tokens: bool,
+) -> Result<Vec<(ScryfallCard, u8)>> {
+    let piles = collect_card_piles(db, deck)
+        .await
+        .context("Failed to collect and sort cards")?;
+    Ok(piles
+        .into_iter()
+        .filter(|pile| include_tokens || !matches!(pile.kind, PileKind::Tokens | PileKind::Extras))
+        .flat_map(|pile| pile.cards)
+        .collect())
+}
+
 struct Page {
     width: u32,
     height: u32,
@@ -583,10 +890,61 @@ async fn save_pages<R: AsyncCommands>(
     Ok(saved_pages)
 }
 
+/// The Tabletop Simulator version that saved objects claim to come from.
+const TTS_VERSION: &str = "v13.2.2";
+
+/// Wraps the piles in a bag, with the same top-level fields TTS writes when
+/// saving an object, so the file can go straight into the "Saved Objects"
+/// folder.
+fn saved_object_json(deck_title: &str, stacks: Vec<Value>, saved_at: DateTime<Utc>) -> Value {
+    let bag = json!({
+        "Name": "Bag",
+        "Nickname": deck_title,
+        "Description": "",
+        "ColorDiffuse": {"r": 0.7, "g": 0.7, "b": 0.7},
+        "Grid": true,
+        "Locked": false,
+        "Snap": true,
+        "Transform": {
+            "posX": 0.0,
+            "posY": 0.0,
+            "posZ": 0.0,
+            "rotX": 0.0,
+            "rotY": 0.0,
+            "rotZ": 0.0,
+            "scaleX": 1.0,
+            "scaleY": 1.0,
+            "scaleZ": 1.0,
+        },
+        "Bag": {"Order": 0},
+        "ContainedObjects": stacks,
+    });
+    json!({
+        "SaveName": deck_title,
+        "GameMode": "",
+        "Gravity": 0.5,
+        "PlayArea": 0.5,
+        "Date": saved_at.format("%-m/%-d/%Y %-I:%M:%S %p").to_string(),
+        "EpochTime": saved_at.timestamp(),
+        "VersionNumber": TTS_VERSION,
+        "Table": "",
+        "Sky": "",
+        "Note": "",
+        "Rules": "",
+        "XmlUI": "",
+        "LuaScript": "",
+        "LuaScriptState": "",
+        "ObjectStates": [bag],
+        "TabStates": {},
+    })
+}
+
 fn render_piles_to_json<'a>(
     deck_title: &str,
+    options: &RenderOptions,
     piles: Piles,
     pages: &'a [RenderedPage],
+    rendered_at: DateTime<Utc>,
 ) -> Result<Value> {
     let base_transform = json!({
         "posX": 0.0,
@@ -620,11 +978,18 @@ fn render_piles_to_json<'a>(
         .collect::<Result<Vec<_>>>()
         .context("Failed to linearize piles")?;
     let mut stacks = Vec::with_capacity(linear_piles.len());
+    let mut category_index = 0;
     for (i, pile) in linear_piles.iter().enumerate() {
+        let placement = layout::placement(options.layout, pile.kind, i, category_index);
+        if pile.kind == PileKind::Category {
+            category_index += 1;
+        }
         let root_transform: Value = {
             let mut t = base_transform.clone();
-            t["posX"] = json!(3.0 * (i as f64));
-            t["rotZ"] = if pile.face_up {
+            t["posX"] = json!(placement.pos_x);
+            t["posZ"] = json!(placement.pos_z);
+            t["rotY"] = json!(placement.rot_y);
+            t["rotZ"] = if placement.face_up {
                 json!(0.0)
             } else {
                 json!(180.0)
@@ -650,7 +1015,7 @@ fn render_piles_to_json<'a>(
             "ColorDiffuse": color,
             "CustomDeck": decks_json.clone(),
             "Grid": true,
-            "Locked": false,
+            "Locked": placement.locked,
             "Snap": true,
             "Transform": root_transform,
         });
@@ -667,8 +1032,8 @@ fn render_piles_to_json<'a>(
             cards => {
                 let card_count = cards.len();
                 stack["Name"] = json!("Deck");
-                stack["Nickname"] = json!(deck_title);
-                stack["Description"] = json!(format!("Generated at {}", Utc::now().to_rfc2822()));
+                stack["Nickname"] = json!(pile.label.as_deref().unwrap_or(deck_title));
+                stack["Description"] = json!(format!("Generated at {}", rendered_at.to_rfc2822()));
                 let mut deck_ids = Vec::with_capacity(card_count);
                 let mut contained_objects = Vec::with_capacity(card_count);
                 for (card, deck_id) in pile.cards.iter() {
@@ -690,12 +1055,20 @@ fn render_piles_to_json<'a>(
                 stack["ContainedObjects"] = Value::from(contained_objects);
             }
         }
+        let is_deck = pile.cards.len() > 1;
+        if let Some(script) = scripting::script_for_pile(options.scripts, pile.kind, is_deck) {
+            stack["LuaScript"] = json!(script.lua_script());
+            stack["LuaScriptState"] = json!("");
+        }
         stacks.push(stack);
     }
 
-    Ok(json!({
-        "ObjectStates": stacks,
-    }))
+    match options.export_mode {
+        ExportMode::LooseObjects => Ok(json!({
+            "ObjectStates": stacks,
+        })),
+        ExportMode::SavedObject => Ok(saved_object_json(deck_title, stacks, rendered_at)),
+    }
 }
 
 pub async fn render_deck(
@@ -716,8 +1089,18 @@ pub async fn render_deck(
     let saved_pages = save_pages(redis, &deck, rendered_pages)
         .await
         .context("Failed to save pages")?;
-    let json = render_piles_to_json(&deck.title, piles, saved_pages.as_slice())
-        .context("Failed to render piles to TTS JSON format")?;
+    let thumbnail = thumbnail::save_thumbnail(Arc::clone(&api), &deck, &piles)
+        .await
+        .context("Failed to save thumbnail")?;
+    let rendered_at = Utc::now();
+    let json = render_piles_to_json(
+        &deck.title,
+        &deck.render_options,
+        piles,
+        saved_pages.as_slice(),
+        rendered_at,
+    )
+    .context("Failed to render piles to TTS JSON format")?;
     notify_user(
         redis,
         deck.user_id,
@@ -730,7 +1113,61 @@ pub async fn render_deck(
 
     Ok(RenderedDeck {
         json_description: json,
-        rendered_at: Utc::now(),
+        rendered_at,
         pages: saved_pages,
+        thumbnail,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    use super::{layout::PileKind, split_categories, TokenSources};
+    use crate::test_helpers::test_card;
+
+    #[test]
+    fn every_card_in_a_category_keeps_a_library() {
+        let ramp = test_card(
+            "00000000-0000-0000-0000-000000000001",
+            "Sol Ring",
+            json!({}),
+        );
+        let land = test_card("00000000-0000-0000-0000-000000000002", "Forest", json!({}));
+        let mut categories = HashMap::new();
+        categories.insert(ramp.id().unwrap(), "Ramp".to_string());
+        categories.insert(land.id().unwrap(), "Lands".to_string());
+
+        let piles = split_categories(vec![(ramp, 1), (land, 30)], &categories).unwrap();
+        assert_eq!(piles.len(), 2);
+        assert_eq!(piles[0].kind, PileKind::Library);
+        assert_eq!(piles[0].label.as_deref(), Some("Lands"));
+        assert_eq!(piles[0].cards[0].0.combined_name(), "Forest");
+        assert_eq!(piles[1].kind, PileKind::Category);
+        assert_eq!(piles[1].label.as_deref(), Some("Ramp"));
+    }
+
+    #[test]
+    fn tokens_made_by_tokens() {
+        let mut sources = TokenSources::default();
+        // Two cards make Saprolings, and a later card makes one more.
+        sources.add_deck_card("saproling", 2);
+        // Every Saproling can make a Treasure, whichever order they're found in.
+        sources.add_token("treasure", "saproling");
+        sources.add_deck_card("saproling", 1);
+        sources.add_deck_card("treasure", 1);
+        // A token that copies itself doesn't count itself.
+        sources.add_token("saproling", "saproling");
+        // Neither does a loop between two tokens.
+        sources.add_token("germ", "spawn");
+        sources.add_token("spawn", "germ");
+        sources.add_deck_card("spawn", 1);
+
+        let counts = sources.counts();
+        assert_eq!(counts["saproling"], 3);
+        assert_eq!(counts["treasure"], 4);
+        assert_eq!(counts["spawn"], 1);
+        assert_eq!(counts["germ"], 1);
+    }
+}
@@ -0,0 +1,168 @@
+//! Find the helper cards (dungeons, The Monarch, emblems, etc.) that a deck's
+//! mechanics need on the table. Scryfall only sometimes lists these in a
+//! card's `all_parts`, so we look for them based on the rules text instead.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use sqlx::PgConnection;
+use std::collections::BTreeSet;
+
+use crate::scryfall::{self, ScryfallCard};
+
+struct HelperRule {
+    /// Matched exactly against the card's Scryfall `keywords`.
+    keywords: &'static [&'static str],
+    /// Matched case-insensitively against the card's oracle text.
+    text: &'static [&'static str],
+    /// Names of the helper cards to put in the token pile.
+    cards: &'static [&'static str],
+}
+
+const HELPER_RULES: &[HelperRule] = &[
+    HelperRule {
+        keywords: &["Venture into the dungeon"],
+        text: &["venture into the dungeon"],
+        cards: &[
+            "Lost Mine of Phandelver",
+            "Dungeon of the Mad Mage",
+            "Tomb of Annihilation",
+        ],
+    },
+    HelperRule {
+        keywords: &["Initiative"],
+        text: &["take the initiative", "have the initiative"],
+        cards: &["The Initiative", "Undercity"],
+    },
+    HelperRule {
+        keywords: &["Monarch"],
+        text: &["become the monarch", "becomes the monarch"],
+        cards: &["The Monarch"],
+    },
+    HelperRule {
+        keywords: &["The Ring Tempts You"],
+        text: &["the ring tempts you"],
+        cards: &["The Ring"],
+    },
+    HelperRule {
+        keywords: &["Daybound", "Nightbound"],
+        text: &["it becomes day", "it becomes night"],
+        cards: &["Day // Night"],
+    },
+];
+
+fn helper_card_names(keywords: &[&str], oracle_text: &str, card_names: &[String]) -> Vec<String> {
+    let oracle_text = oracle_text.to_lowercase();
+    let mut names = vec![];
+    for rule in HELPER_RULES {
+        let keyword_match = rule.keywords.iter().any(|k| keywords.contains(k));
+        let text_match = rule.text.iter().any(|t| oracle_text.contains(t));
+        if keyword_match || text_match {
+            names.extend(rule.cards.iter().map(|c| c.to_string()));
+        }
+    }
+    // Scryfall names emblems after the planeswalker that creates them, e.g.
+    // "Chandra, Torch of Defiance Emblem".
+    if oracle_text.contains("you get an emblem") {
+        names.extend(card_names.iter().map(|n| format!("{} Emblem", n)));
+    }
+    names
+}
+
+fn card_keywords(card: &ScryfallCard) -> Vec<&str> {
+    card.raw_json()
+        .get("keywords")
+        .and_then(Value::as_array)
+        .map(|ks| ks.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// Oracle text of the card and all of its faces, since double-faced cards
+/// don't have a top-level `oracle_text` field.
+fn card_text(card: &ScryfallCard) -> String {
+    let mut texts = vec![];
+    if let Ok(text) = card.oracle_text() {
+        texts.push(text);
+    }
+    if let Some(faces) = card.raw_json().get("card_faces").and_then(Value::as_array) {
+        for face in faces {
+            if let Some(text) = face.get("oracle_text").and_then(Value::as_str) {
+                texts.push(text);
+            }
+        }
+    }
+    texts.join("\n")
+}
+
+pub(super) async fn get_helper_cards<'c, I>(
+    db: &mut PgConnection,
+    cards: I,
+) -> Result<Vec<ScryfallCard>>
+where
+    I: Iterator<Item = &'c ScryfallCard>,
+{
+    let mut wanted = BTreeSet::new();
+    for card in cards {
+        let names = card.names();
+        let card_names: Vec<String> = names.iter().cloned().collect();
+        for helper_name in helper_card_names(&card_keywords(card), &card_text(card), &card_names) {
+            if wanted.insert(helper_name.clone()) {
+                debug!(
+                    "Card {} needs helper card {}",
+                    card.combined_name(),
+                    helper_name
+                );
+            }
+        }
+    }
+
+    let mut helpers = Vec::with_capacity(wanted.len());
+    for name in wanted {
+        let card_opt = scryfall::card_by_name(&mut *db, &name, true)
+            .await
+            .with_context(|| format!("Failed to look up helper card {:?}", name))?;
+        match card_opt {
+            Some(card) => helpers.push(card),
+            None => debug!("No helper card named {:?} in the database", name),
+        }
+    }
+    Ok(helpers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::helper_card_names;
+
+    #[test]
+    fn helper_cards_from_rules_text() {
+        let names = helper_card_names(
+            &["Venture into the dungeon"],
+            "When this creature enters the battlefield, venture into the dungeon.",
+            &["Nadaar, Selfless Paladin".to_string()],
+        );
+        assert_eq!(
+            names,
+            vec![
+                "Lost Mine of Phandelver",
+                "Dungeon of the Mad Mage",
+                "Tomb of Annihilation"
+            ]
+        );
+
+        let names = helper_card_names(
+            &[],
+            "When Palace Jailer enters the battlefield, you become the monarch.",
+            &["Palace Jailer".to_string()],
+        );
+        assert_eq!(names, vec!["The Monarch"]);
+
+        let names = helper_card_names(
+            &[],
+            "\u{2212}7: You get an emblem with \"Whenever you cast a spell, this emblem deals 5 damage to any target.\"",
+            &["Chandra, Torch of Defiance".to_string()],
+        );
+        assert_eq!(names, vec!["Chandra, Torch of Defiance Emblem"]);
+
+        let names = helper_card_names(&["Flying"], "Flying", &["Serra Angel".to_string()]);
+        assert!(names.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+//! Table positions for the piles of a rendered deck, for each of the layout
+//! presets users can pick from.
+
+use ttsmagic_types::LayoutPreset;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub(super) enum PileKind {
+    Commanders,
+    Companion,
+    Library,
+    /// Part of the main deck, split out by the deck site's category.
+    Category,
+    Sideboard,
+    Maybeboard,
+    Tokens,
+    Extras,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(super) struct PilePlacement {
+    pub pos_x: f64,
+    pub pos_z: f64,
+    pub rot_y: f64,
+    pub face_up: bool,
+    pub locked: bool,
+}
+
+/// Facing the player, which is what most piles want.
+const FACING: f64 = 180.0;
+/// Turned sideways, for piles that shouldn't be mistaken for the library.
+const SIDEWAYS: f64 = 90.0;
+
+impl PilePlacement {
+    const fn new(pos_x: f64, pos_z: f64, rot_y: f64, face_up: bool, locked: bool) -> Self {
+        PilePlacement {
+            pos_x,
+            pos_z,
+            rot_y,
+            face_up,
+            locked,
+        }
+    }
+}
+
+/// `index` is the position of the pile among all of the deck's piles. The row
+/// layout uses it for every pile; the other presets place each kind of pile in
+/// a fixed spot, except for category piles, which go in a row of their own at
+/// `category_index`, their position among the deck's category piles.
+pub(super) fn placement(
+    preset: LayoutPreset,
+    kind: PileKind,
+    index: usize,
+    category_index: usize,
+) -> PilePlacement {
+    use PileKind::*;
+    let category_index = category_index as f64;
+    match preset {
+        LayoutPreset::Row => {
+            let face_up = kind != Library && kind != Category;
+            PilePlacement::new(3.0 * (index as f64), 0.0, FACING, face_up, false)
+        }
+        LayoutPreset::OneVsOneMat => match kind {
+            Commanders => PilePlacement::new(-8.0, -6.0, FACING, true, false),
+            Companion => PilePlacement::new(-12.0, -6.0, FACING, true, false),
+            Library => PilePlacement::new(8.0, -6.0, FACING, false, false),
+            Category => PilePlacement::new(-8.0 + 3.0 * category_index, -2.0, FACING, false, false),
+            Sideboard => PilePlacement::new(-16.0, -10.0, FACING, false, false),
+            Maybeboard => PilePlacement::new(-20.0, -10.0, FACING, true, false),
+            Tokens => PilePlacement::new(12.0, -10.0, SIDEWAYS, false, false),
+            Extras => PilePlacement::new(16.0, -10.0, SIDEWAYS, false, false),
+        },
+        LayoutPreset::CommanderPod => match kind {
+            Commanders => PilePlacement::new(0.0, -12.0, FACING, true, false),
+            Companion => PilePlacement::new(-4.0, -12.0, FACING, true, false),
+            Library => PilePlacement::new(6.0, -12.0, FACING, false, false),
+            Category => PilePlacement::new(3.0 * category_index, -8.0, FACING, false, false),
+            Sideboard => PilePlacement::new(-12.0, -16.0, FACING, false, false),
+            Maybeboard => PilePlacement::new(-16.0, -16.0, FACING, true, false),
+            Tokens => PilePlacement::new(12.0, -16.0, SIDEWAYS, false, true),
+            Extras => PilePlacement::new(16.0, -16.0, SIDEWAYS, false, true),
+        },
+        LayoutPreset::Compact => match kind {
+            Commanders => PilePlacement::new(-2.5, 0.0, FACING, true, false),
+            Companion => PilePlacement::new(2.5, 0.0, FACING, true, false),
+            Library => PilePlacement::new(0.0, 0.0, FACING, false, false),
+            Category => PilePlacement::new(-2.5 + 2.5 * category_index, 3.5, FACING, false, false),
+            Sideboard => PilePlacement::new(-2.5, -3.5, FACING, false, false),
+            Maybeboard => PilePlacement::new(-5.0, -3.5, FACING, true, false),
+            Tokens => PilePlacement::new(0.0, -3.5, FACING, false, false),
+            Extras => PilePlacement::new(2.5, -3.5, FACING, false, false),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{placement, PileKind};
+    use ttsmagic_types::LayoutPreset;
+
+    #[test]
+    fn presets_keep_piles_apart() {
+        let kinds = [
+            PileKind::Commanders,
+            PileKind::Companion,
+            PileKind::Library,
+            PileKind::Category,
+            PileKind::Category,
+            PileKind::Sideboard,
+            PileKind::Maybeboard,
+            PileKind::Tokens,
+            PileKind::Extras,
+        ];
+        for preset in LayoutPreset::ALL.iter().copied() {
+            let placements: Vec<_> = kinds
+                .iter()
+                .enumerate()
+                .map(|(i, kind)| {
+                    let category_index = kinds[..i]
+                        .iter()
+                        .filter(|k| **k == PileKind::Category)
+                        .count();
+                    placement(preset, *kind, i, category_index)
+                })
+                .collect();
+            for (i, a) in placements.iter().enumerate() {
+                for b in placements[i + 1..].iter() {
+                    // Cards are a little over 2 units wide and 3 units tall.
+                    let apart =
+                        (a.pos_x - b.pos_x).abs() >= 2.5 || (a.pos_z - b.pos_z).abs() >= 3.5;
+                    assert!(apart, "Piles overlap in {:?}: {:?} {:?}", preset, a, b);
+                }
+            }
+            let library = placement(preset, PileKind::Library, 1, 0);
+            assert!(!library.face_up, "Library is face up in {:?}", preset);
+        }
+    }
+
+    #[test]
+    fn category_row_ignores_other_piles() {
+        for preset in LayoutPreset::ALL.iter().copied() {
+            if preset == LayoutPreset::Row {
+                continue;
+            }
+            // Whether or not the deck has commanders and a library before its
+            // categories, the first category is in the same spot.
+            let after_library = placement(preset, PileKind::Category, 1, 0);
+            let after_more = placement(preset, PileKind::Category, 3, 0);
+            assert_eq!(
+                after_library, after_more,
+                "Category row moved in {:?}",
+                preset
+            );
+        }
+    }
+
+    #[test]
+    fn mat_presets_turn_tokens_sideways() {
+        for preset in [LayoutPreset::OneVsOneMat, LayoutPreset::CommanderPod].iter() {
+            let library = placement(*preset, PileKind::Library, 0, 0);
+            let tokens = placement(*preset, PileKind::Tokens, 0, 0);
+            assert_eq!(library.rot_y, 180.0);
+            assert_eq!(tokens.rot_y, 90.0, "Tokens aren't sideways in {:?}", preset);
+        }
+        let row_tokens = placement(LayoutPreset::Row, PileKind::Tokens, 3, 0);
+        assert_eq!(row_tokens.rot_y, 180.0);
+    }
+}
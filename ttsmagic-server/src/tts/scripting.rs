@@ -0,0 +1,171 @@
+//! Optional Lua helpers for the rendered piles. Each script is versioned, and
+//! the version is written into the emitted `LuaScript` so that decks rendered
+//! with an older version can be told apart.
+
+use ttsmagic_types::DeckScripts;
+
+use super::layout::PileKind;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) struct Script {
+    name: &'static str,
+    version: u32,
+    source: &'static str,
+}
+
+impl Script {
+    pub fn lua_script(&self) -> String {
+        format!(
+            "-- ttsmagic.cards {} v{}\n{}",
+            self.name, self.version, self.source
+        )
+    }
+}
+
+const MULLIGAN_BUTTONS: Script = Script {
+    name: "mulligan_buttons",
+    version: 1,
+    source: include_str!("scripts/mulligan_buttons.v1.lua"),
+};
+
+const LIFE_COUNTER: Script = Script {
+    name: "life_counter",
+    version: 1,
+    source: include_str!("scripts/life_counter.v1.lua"),
+};
+
+const TOKEN_SPAWNER: Script = Script {
+    name: "token_spawner",
+    version: 1,
+    source: include_str!("scripts/token_spawner.v1.lua"),
+};
+
+/// `is_deck` is false for piles with a single card, which TTS spawns as a
+/// `Card` rather than a container.
+pub(super) fn script_for_pile(
+    scripts: DeckScripts,
+    kind: PileKind,
+    is_deck: bool,
+) -> Option<Script> {
+    match kind {
+        PileKind::Library if scripts.mulligan_buttons => Some(MULLIGAN_BUTTONS),
+        PileKind::Commanders if scripts.life_counter => Some(LIFE_COUNTER),
+        PileKind::Tokens if scripts.token_spawner && is_deck => Some(TOKEN_SPAWNER),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::prelude::*;
+    use serde_json::{json, Value};
+    use smallvec::smallvec;
+    use std::collections::HashMap;
+    use ttsmagic_types::{DeckScripts, ExportMode, LayoutPreset, RenderOptions};
+
+    use super::super::{layout::PileKind, render_piles_to_json, Pile, RenderedPage};
+    use crate::{files::MediaFile, test_helpers::test_card};
+
+    fn timestamp(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn render(options: &RenderOptions) -> Value {
+        let commander = test_card(
+            "00000000-0000-0000-0000-000000000001",
+            "Ezuri, Claw of Progress",
+            json!({
+                "oracle_id": "00000000-0000-0000-0000-000000000001",
+                "type_line": "Legendary Creature \u{2014} Phyrexian Elf Warrior",
+            }),
+        );
+        let forest = test_card(
+            "00000000-0000-0000-0000-000000000002",
+            "Forest",
+            json!({
+                "oracle_id": "00000000-0000-0000-0000-000000000002",
+                "type_line": "Basic Land \u{2014} Forest",
+            }),
+        );
+        let island = test_card(
+            "00000000-0000-0000-0000-000000000003",
+            "Island",
+            json!({
+                "oracle_id": "00000000-0000-0000-0000-000000000003",
+                "type_line": "Basic Land \u{2014} Island",
+            }),
+        );
+        let treasure = test_card(
+            "00000000-0000-0000-0000-000000000004",
+            "Treasure",
+            json!({
+                "oracle_id": "00000000-0000-0000-0000-000000000004",
+                "type_line": "Token Artifact \u{2014} Treasure",
+            }),
+        );
+        let mut card_mapping = HashMap::new();
+        for (i, c) in [&commander, &forest, &island, &treasure].iter().enumerate() {
+            card_mapping.insert(c.id().unwrap(), i as u8);
+        }
+        let pages = vec![RenderedPage {
+            width: 3,
+            height: 2,
+            image: MediaFile::from_key("pages/00/00/deck_0.jpg").unwrap(),
+            card_mapping,
+        }];
+        let piles = smallvec![
+            Pile::new(PileKind::Commanders, vec![(commander, 1)]).unwrap(),
+            Pile::new(PileKind::Library, vec![(forest, 2), (island, 1)]).unwrap(),
+            Pile::new(PileKind::Tokens, vec![(treasure, 2)]).unwrap(),
+        ];
+        let rendered_at = timestamp("2021-06-01T12:30:00Z");
+        render_piles_to_json("Ezuri", options, piles, &pages, rendered_at).unwrap()
+    }
+
+    fn assert_snapshot(value: &Value, snapshot: &str) {
+        let rendered = serde_json::to_string_pretty(value).unwrap();
+        assert_eq!(rendered.trim_end(), snapshot.trim_end());
+    }
+
+    #[test]
+    fn no_scripts_snapshot() {
+        let options = RenderOptions::default();
+        assert_snapshot(&render(&options), include_str!("snapshots/no_scripts.json"));
+    }
+
+    #[test]
+    fn all_scripts_snapshot() {
+        let options = RenderOptions {
+            scripts: DeckScripts {
+                mulligan_buttons: true,
+                life_counter: true,
+                token_spawner: true,
+            },
+            ..RenderOptions::default()
+        };
+        assert_snapshot(
+            &render(&options),
+            include_str!("snapshots/all_scripts.json"),
+        );
+    }
+
+    #[test]
+    fn saved_object_scripts_snapshot() {
+        let options = RenderOptions {
+            layout: LayoutPreset::CommanderPod,
+            export_mode: ExportMode::SavedObject,
+            scripts: DeckScripts {
+                mulligan_buttons: true,
+                life_counter: false,
+                token_spawner: true,
+            },
+            ..RenderOptions::default()
+        };
+        assert_snapshot(
+            &render(&options),
+            include_str!("snapshots/saved_object_scripts.json"),
+        );
+    }
+}
@@ -0,0 +1,34 @@
+-- A life counter above the commander. Click to gain life, right-click to lose
+-- it. The total is kept when the game is saved.
+
+local STARTING_LIFE = 40
+local life = STARTING_LIFE
+
+function onLoad(saved_state)
+  if saved_state ~= nil and saved_state ~= "" then
+    life = tonumber(saved_state) or STARTING_LIFE
+  end
+  self.createButton({
+    click_function = "changeLife",
+    function_owner = self,
+    label = tostring(life),
+    tooltip = "Click to gain life, right-click to lose life",
+    position = {0, 0.5, -1.9},
+    width = 600,
+    height = 300,
+    font_size = 250,
+  })
+end
+
+function onSave()
+  return tostring(life)
+end
+
+function changeLife(obj, player_color, alt_click)
+  if alt_click then
+    life = life - 1
+  else
+    life = life + 1
+  end
+  self.editButton({index = 0, label = tostring(life)})
+end
@@ -0,0 +1,42 @@
+-- Buttons on the library to shuffle and draw an opening hand, and to shuffle
+-- the current hand back in and draw a new one.
+
+local HAND_SIZE = 7
+
+function onLoad()
+  -- The library is face down, so the buttons go on its underside.
+  self.createButton({
+    click_function = "drawOpeningHand",
+    function_owner = self,
+    label = "Opening hand",
+    tooltip = "Shuffle and draw " .. HAND_SIZE .. " cards",
+    position = {0, -0.5, -1.9},
+    rotation = {0, 0, 180},
+    width = 1100,
+    height = 250,
+    font_size = 140,
+  })
+  self.createButton({
+    click_function = "mulligan",
+    function_owner = self,
+    label = "Mulligan",
+    tooltip = "Shuffle your hand into the library and draw " .. HAND_SIZE .. " new cards",
+    position = {0, -0.5, 1.9},
+    rotation = {0, 0, 180},
+    width = 1100,
+    height = 250,
+    font_size = 140,
+  })
+end
+
+function drawOpeningHand(obj, player_color)
+  self.shuffle()
+  self.deal(HAND_SIZE, player_color)
+end
+
+function mulligan(obj, player_color)
+  for _, card in ipairs(Player[player_color].getHandObjects()) do
+    self.putObject(card)
+  end
+  Wait.time(function() drawOpeningHand(obj, player_color) end, 0.5)
+end
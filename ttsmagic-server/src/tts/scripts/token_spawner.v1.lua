@@ -0,0 +1,32 @@
+-- Context menu entries on the token pile to spawn a token by name. A copy of
+-- the token goes back into the pile, so it never runs out.
+
+function onLoad()
+  local seen = {}
+  for _, token in ipairs(self.getObjects()) do
+    local name = token.name
+    if not seen[name] then
+      seen[name] = true
+      self.addContextMenuItem("Spawn " .. name, function(player_color)
+        spawnToken(name, player_color)
+      end)
+    end
+  end
+end
+
+function spawnToken(name, player_color)
+  for _, token in ipairs(self.getObjects()) do
+    if token.name == name then
+      self.takeObject({
+        guid = token.guid,
+        position = self.getPosition() + Vector(0, 1, -4),
+        smooth = false,
+        callback_function = function(spawned)
+          self.putObject(spawned.clone({position = self.getPosition() + Vector(0, 2, 0)}))
+        end,
+      })
+      return
+    end
+  end
+  broadcastToColor("There is no " .. name .. " token in this pile", player_color, {1, 0.3, 0.3})
+end
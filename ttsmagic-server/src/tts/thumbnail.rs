@@ -0,0 +1,123 @@
+//! The deck's cover image, shown in the deck list and used as the preview
+//! Tabletop Simulator shows for a saved object. TTS needs it next to the JSON
+//! file with the same name, so it's also downloadable from `/decks/$ID.png`.
+
+use anyhow::{anyhow, Result};
+use async_std::sync::Arc;
+use image::{imageops, RgbImage, Rgba, RgbaImage};
+
+use super::{layout::PileKind, Piles};
+use crate::{
+    deck::Deck,
+    files::MediaFile,
+    scryfall::{api::ScryfallApi, ScryfallCard},
+};
+
+const THUMBNAIL_SIZE: u32 = 256;
+/// Decks without a commander get a 2x2 collage of their first few cards.
+const COLLAGE_CARDS: usize = 4;
+
+/// Scales the image to fit inside a `size`-pixel square, keeping its aspect
+/// ratio.
+fn fit(image: &RgbImage, size: u32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let (scaled_width, scaled_height) = if width > height {
+        (size, (height * size / width).max(1))
+    } else {
+        ((width * size / height).max(1), size)
+    };
+    imageops::resize(
+        &image::DynamicImage::ImageRgb8(image.clone()).to_rgba8(),
+        scaled_width,
+        scaled_height,
+        imageops::FilterType::Lanczos3,
+    )
+}
+
+/// Crops the middle of the art to a square and scales it to fill the
+/// thumbnail.
+fn thumbnail_from_art(art: RgbImage) -> RgbaImage {
+    let (width, height) = art.dimensions();
+    let side = width.min(height);
+    let mut art = art;
+    let square = imageops::crop(
+        &mut art,
+        (width - side) / 2,
+        (height - side) / 2,
+        side,
+        side,
+    )
+    .to_image();
+    fit(&square, THUMBNAIL_SIZE)
+}
+
+fn thumbnail_from_cards(cards: Vec<RgbImage>) -> RgbaImage {
+    let mut thumbnail = RgbaImage::from_pixel(THUMBNAIL_SIZE, THUMBNAIL_SIZE, Rgba([0, 0, 0, 0]));
+    let (cell_size, columns) = if cards.len() == 1 {
+        (THUMBNAIL_SIZE, 1)
+    } else {
+        (THUMBNAIL_SIZE / 2, 2)
+    };
+    for (i, card) in cards.iter().enumerate() {
+        let card = fit(card, cell_size);
+        let (width, height) = card.dimensions();
+        let cell_x = (i as u32 % columns) * cell_size;
+        let cell_y = (i as u32 / columns) * cell_size;
+        imageops::overlay(
+            &mut thumbnail,
+            &card,
+            cell_x + (cell_size - width) / 2,
+            cell_y + (cell_size - height) / 2,
+        );
+    }
+    thumbnail
+}
+
+/// Uses the art of the first commander if the deck has one, and a collage of
+/// the first few cards otherwise.
+pub(super) async fn save_thumbnail(
+    api: Arc<ScryfallApi>,
+    deck: &Deck,
+    piles: &Piles,
+) -> Result<MediaFile> {
+    let commander: Option<&ScryfallCard> = piles
+        .iter()
+        .find(|pile| pile.kind == PileKind::Commanders)
+        .and_then(|pile| pile.cards.first())
+        .map(|(card, _)| card);
+    let thumbnail = match commander {
+        Some(commander) => {
+            let art = commander.ensure_art_crop(&api).await?;
+            async_std::task::spawn_blocking(move || thumbnail_from_art(art)).await
+        }
+        None => {
+            let mut images = Vec::with_capacity(COLLAGE_CARDS);
+            let cards = piles
+                .iter()
+                .flat_map(|pile| pile.cards.iter())
+                .take(COLLAGE_CARDS);
+            for (card, _count) in cards {
+                images.push(card.ensure_image(&api).await?);
+            }
+            if images.is_empty() {
+                return Err(anyhow!(
+                    "Tried to make a thumbnail for a deck with no cards"
+                ));
+            }
+            async_std::task::spawn_blocking(move || thumbnail_from_cards(images)).await
+        }
+    };
+
+    let deck_uuid = format!("{}", deck.id.as_uuid());
+    let thumbnail_filename = format!(
+        "thumbnails/{}/{}/{}.png",
+        &deck_uuid[0..2],
+        &deck_uuid[2..4],
+        deck_uuid,
+    );
+    let f = MediaFile::create(&thumbnail_filename).await?;
+    thumbnail.save(&f.path())?;
+    let saved = f.finalize().await?;
+    debug!("Saved thumbnail image {}", saved.path());
+    Ok(saved)
+}
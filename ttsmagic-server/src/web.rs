@@ -1,6 +1,7 @@
 use anyhow::Result;
 use async_std::{net::IpAddr, path::PathBuf, sync::Arc};
 
+mod api;
 mod app;
 mod deck;
 mod session;
@@ -64,7 +65,22 @@ pub async fn run_server(
     app.with(session::SessionMiddleware::new());
 
     app.at("/").get(app::home_page);
-    app.at("/decks/:deck_id").get(deck::download_deck_json);
+    app.at("/decks/:deck_id").get(deck::download_deck);
+    app.at("/decks/:deck_id/revisions/:revision")
+        .get(deck::download_deck_revision);
+    app.at("/shared/:token").get(deck::download_shared_deck);
+    app.at("/api/cards/named").get(api::get_card_named);
+    app.at("/api/cards/search").get(api::search_cards);
+    app.at("/api/cards/:scryfall_id").get(api::get_card);
+    app.at("/api/v1/decks")
+        .get(api::list_decks)
+        .post(api::create_deck);
+    app.at("/api/v1/decks/:deck_id")
+        .get(api::get_deck)
+        .delete(api::delete_deck);
+    app.at("/api/v1/decks/:deck_id/render")
+        .get(api::get_render_status)
+        .post(api::render_deck);
     app.at("/static/*path").get(app::static_files);
     app.at("/files/*path").get(uploaded_files::get);
     #[cfg(debug_assertions)]
@@ -0,0 +1,282 @@
+//! JSON versions of what the frontend does over the websocket, under
+//! `/api/v1/`, for scripts. Requests are authenticated the same way as the
+//! rest of the site, and errors come back as [`ApiError`] bodies. Card data
+//! under `/api/cards/` is public.
+
+use async_std::{
+    sync::Mutex,
+    task::{block_on, spawn, spawn_blocking},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, str::FromStr};
+use tide::{Body, Request, Response, Result, StatusCode};
+use ttsmagic_types::{
+    api::{ApiError, CreateDeck, RenderState, RenderStatus},
+    DeckId,
+};
+
+use super::AppState;
+use crate::{
+    deck::{get_decks_for_user, load_deck, load_deck_from_text, Deck, DeckInputError},
+    scryfall::{self, search, ScryfallCard, ScryfallId},
+    web::session::SessionGetExt,
+};
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Result {
+    let mut resp = Response::new(status);
+    resp.set_body(Body::from_json(body)?);
+    Ok(resp)
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> Result {
+    let error = ApiError {
+        error: message.into(),
+    };
+    json_response(status, &error)
+}
+
+macro_rules! require_user {
+    ($req:expr) => {
+        match $req.get_session().await.and_then(|s| s.user) {
+            Some(user) => user,
+            None => return error_response(StatusCode::Unauthorized, "Not logged in"),
+        }
+    };
+}
+
+/// Loads the deck named by the `:deck_id` parameter, answering with a 404 if
+/// it doesn't exist or belongs to someone else.
+macro_rules! require_deck {
+    ($req:expr, $db_conn:expr, $user:expr) => {{
+        let raw_id: &str = $req.param("deck_id").unwrap();
+        let deck_id = match DeckId::from_str(raw_id) {
+            Ok(id) => id,
+            Err(_) => return error_response(StatusCode::NotFound, "Invalid deck ID"),
+        };
+        match Deck::get_by_id(&mut *$db_conn, deck_id).await? {
+            Some(deck) if deck.user_id == $user.id => deck,
+            _ => return error_response(StatusCode::NotFound, "Invalid deck ID"),
+        }
+    }};
+}
+
+lazy_static::lazy_static! {
+    /// Renders the API is running, and the ones that failed, by deck. A render
+    /// that succeeds is forgotten, since the deck then records it.
+    static ref RENDERS: Mutex<HashMap<DeckId, RenderState>> = Mutex::new(HashMap::new());
+}
+
+async fn render_status(deck: &Deck) -> RenderStatus {
+    let rendered = deck.rendered_json.is_some();
+    // The deck itself is the authority on whether it's rendered, since it can
+    // be rendered or edited over the websocket after an API render finishes.
+    let mut renders = RENDERS.lock().await;
+    let state = match renders.get(&deck.id) {
+        Some(RenderState::Pending) => Some(RenderState::Pending),
+        _ if rendered => {
+            renders.remove(&deck.id);
+            Some(RenderState::Done)
+        }
+        Some(RenderState::Failed { message }) => Some(RenderState::Failed {
+            message: message.clone(),
+        }),
+        _ => None,
+    };
+    drop(renders);
+    RenderStatus {
+        deck_id: deck.id,
+        rendered,
+        state,
+        json_url: if rendered {
+            Some(format!("/decks/{}.json", deck.id))
+        } else {
+            None
+        },
+        thumbnail_url: deck
+            .thumbnail
+            .as_deref()
+            .and_then(crate::files::MediaFile::from_key)
+            .and_then(|file| file.url().ok()),
+    }
+}
+
+/// Renders the deck in the background, unless the API is already rendering
+/// it. Clients find out how it went by polling the render status.
+async fn spawn_render(state: AppState, mut deck: Deck) {
+    {
+        let mut renders = RENDERS.lock().await;
+        if renders.get(&deck.id) == Some(&RenderState::Pending) {
+            return;
+        }
+        renders.insert(deck.id, RenderState::Pending);
+    }
+    spawn(async move {
+        let render = async {
+            let mut db_conn = state.db_pool.acquire().await?;
+            let mut redis_conn = state.redis.get_async_connection().await?;
+            deck.render(state.scryfall_api.clone(), &mut *db_conn, &mut redis_conn)
+                .await?;
+            anyhow::Result::<()>::Ok(())
+        };
+        let result = render.await;
+        let mut renders = RENDERS.lock().await;
+        match result {
+            Ok(()) => {
+                renders.remove(&deck.id);
+            }
+            Err(e) => {
+                sentry::integrations::anyhow::capture_anyhow(&e);
+                error!("Failed to render deck {} for the API: {:?}", deck.id, e);
+                let message = format!("{:#}", e);
+                renders.insert(deck.id, RenderState::Failed { message });
+            }
+        }
+    });
+}
+
+/// `GET /api/cards/:scryfall_id`
+pub async fn get_card(req: Request<AppState>) -> Result {
+    let raw_id: &str = req.param("scryfall_id").unwrap();
+    let card_id = match ScryfallId::from_str(raw_id) {
+        Ok(id) => id,
+        Err(_) => return error_response(StatusCode::NotFound, "Invalid Scryfall ID"),
+    };
+    let mut db_conn = req.state().db_pool.acquire().await?;
+    match scryfall::find_card_by_id(&mut *db_conn, card_id).await? {
+        Some(card) => json_response(StatusCode::Ok, &card.summary()?),
+        None => error_response(StatusCode::NotFound, "No card with that Scryfall ID"),
+    }
+}
+
+/// `GET /api/cards/named?exact=NAME` finds the newest English printing of the
+/// card. The name of a single face of the card works too.
+pub async fn get_card_named(req: Request<AppState>) -> Result {
+    #[derive(Deserialize)]
+    struct NamedQuery {
+        exact: String,
+    }
+    let query: NamedQuery = match req.query() {
+        Ok(q) => q,
+        Err(_) => return error_response(StatusCode::BadRequest, "Missing \"exact\" parameter"),
+    };
+    let mut db_conn = req.state().db_pool.acquire().await?;
+    match scryfall::card_by_name(&mut *db_conn, &query.exact, false).await? {
+        Some(card) => json_response(StatusCode::Ok, &card.summary()?),
+        None => error_response(StatusCode::NotFound, "No card with that name"),
+    }
+}
+
+/// `GET /api/cards/search?q=QUERY` takes a subset of Scryfall's search syntax,
+/// described in [`search`], and answers with the newest printing of each
+/// matching card.
+pub async fn search_cards(req: Request<AppState>) -> Result {
+    #[derive(Deserialize)]
+    struct SearchQuery {
+        q: String,
+    }
+    let query: SearchQuery = match req.query() {
+        Ok(q) => q,
+        Err(_) => return error_response(StatusCode::BadRequest, "Missing \"q\" parameter"),
+    };
+    let parsed = match search::Query::from_str(&query.q) {
+        Ok(parsed) => parsed,
+        Err(e) => return error_response(StatusCode::BadRequest, format!("{}", e)),
+    };
+    let mut db_conn = req.state().db_pool.acquire().await?;
+    let cards = search::search(&mut *db_conn, &parsed, search::RESULT_LIMIT).await?;
+    let cards = cards
+        .iter()
+        .map(ScryfallCard::summary)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    json_response(StatusCode::Ok, &cards)
+}
+
+/// `GET /api/v1/decks`
+pub async fn list_decks(req: Request<AppState>) -> Result {
+    let user = require_user!(req);
+    let mut db_conn = req.state().db_pool.acquire().await?;
+    let decks = get_decks_for_user(&mut *db_conn, user.id).await?;
+    json_response(StatusCode::Ok, &decks)
+}
+
+/// `POST /api/v1/decks` with a [`CreateDeck`] body. Answers with the parsed
+/// deck once it's loaded and starts rendering it.
+pub async fn create_deck(mut req: Request<AppState>) -> Result {
+    let user = require_user!(req);
+    let body: CreateDeck = match req.body_json().await {
+        Ok(body) => body,
+        Err(e) => return error_response(StatusCode::BadRequest, format!("{}", e)),
+    };
+    let state = req.state().clone();
+    let mut db_conn = state.db_pool.acquire().await?;
+    let mut redis_conn = state.redis.get_async_connection().await?;
+    let loaded = spawn_blocking(move || {
+        block_on(async move {
+            let deck = match body {
+                CreateDeck::Url { url } => {
+                    load_deck(&mut *db_conn, &mut redis_conn, &user, url).await?
+                }
+                CreateDeck::Text { title, decklist } => {
+                    load_deck_from_text(&mut *db_conn, &mut redis_conn, &user, title, &decklist)
+                        .await?
+                }
+            };
+            let detail = deck.detail(&mut *db_conn).await?;
+            anyhow::Result::<_>::Ok((deck, detail))
+        })
+    })
+    .await;
+    let (deck, detail) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) if e.downcast_ref::<DeckInputError>().is_some() => {
+            return error_response(StatusCode::BadRequest, format!("{:#}", e))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    spawn_render(state, deck).await;
+    json_response(StatusCode::Created, &detail)
+}
+
+/// `GET /api/v1/decks/:deck_id`
+pub async fn get_deck(req: Request<AppState>) -> Result {
+    let user = require_user!(req);
+    let mut db_conn = req.state().db_pool.acquire().await?;
+    let deck = require_deck!(req, db_conn, user);
+    let detail = deck.detail(&mut *db_conn).await?;
+    json_response(StatusCode::Ok, &detail)
+}
+
+/// `DELETE /api/v1/decks/:deck_id`
+pub async fn delete_deck(req: Request<AppState>) -> Result {
+    let user = require_user!(req);
+    let state = req.state();
+    let mut db_conn = state.db_pool.acquire().await?;
+    let deck = require_deck!(req, db_conn, user);
+    let mut redis_conn = state.redis.get_async_connection().await?;
+    let deck_id = deck.id;
+    deck.delete(&mut *db_conn, &mut redis_conn).await?;
+    RENDERS.lock().await.remove(&deck_id);
+    Ok(Response::new(StatusCode::NoContent))
+}
+
+/// `GET /api/v1/decks/:deck_id/render`
+pub async fn get_render_status(req: Request<AppState>) -> Result {
+    let user = require_user!(req);
+    let mut db_conn = req.state().db_pool.acquire().await?;
+    let deck = require_deck!(req, db_conn, user);
+    json_response(StatusCode::Ok, &render_status(&deck).await)
+}
+
+/// `POST /api/v1/decks/:deck_id/render` starts rendering the deck unless it's
+/// already rendered or being rendered. Changing the deck or its render options
+/// is what makes a new render necessary.
+pub async fn render_deck(req: Request<AppState>) -> Result {
+    let user = require_user!(req);
+    let mut db_conn = req.state().db_pool.acquire().await?;
+    let deck = require_deck!(req, db_conn, user);
+    if deck.rendered_json.is_some() {
+        return json_response(StatusCode::Ok, &render_status(&deck).await);
+    }
+    spawn_render(req.state().clone(), deck.clone()).await;
+    json_response(StatusCode::Accepted, &render_status(&deck).await)
+}
@@ -1,3 +1,5 @@
+use async_std::io::ReadExt;
+use sqlx::PgConnection;
 use std::str::FromStr;
 use tide::{
     http::{headers::HeaderName, mime::Mime},
@@ -6,58 +8,85 @@ use tide::{
 use ttsmagic_types::DeckId;
 
 use super::AppState;
-use crate::{deck::Deck, web::session::SessionGetExt};
+use crate::{
+    deck::{
+        export::{export_deck, ExportFormat},
+        Deck,
+    },
+    files::MediaFile,
+    proxies::{render_proxies, ProxyOptions},
+    web::session::SessionGetExt,
+};
 
-pub async fn download_deck_json(req: Request<AppState>) -> Result {
-    macro_rules! ensure_404 {
-        ($cond:expr, $msg:literal, $($arg:expr),* $(,)*) => {
-            if !$cond {
-                error!($msg, $($arg,)*);
-                return Err(tide::Error::from_str(StatusCode::NotFound, "Invalid deck"));
-            }
+macro_rules! ensure_404 {
+    ($cond:expr, $msg:literal, $($arg:expr),* $(,)*) => {
+        if !$cond {
+            error!($msg, $($arg,)*);
+            return Err(tide::Error::from_str(StatusCode::NotFound, "Invalid deck"));
         }
     }
-    macro_rules! opt_404 {
-        ($opt:expr) => {
-            match $opt {
-                Some(x) => x,
-                None => return Err(tide::Error::from_str(StatusCode::NotFound, "Invalid deck")),
-            }
-        };
-    }
-    macro_rules! result_404 {
-        ($result:expr, $msg:literal, $($arg:expr),* $(,)*) => {
-            match $result {
-                Ok(x) => x,
-                Err(e) => {
-                    error!($msg, $($arg,)* e);
-                    let e = anyhow::Error::from(e);
-                    let tide_error = tide::Error::new(StatusCode::NotFound, e.context("Invalid deck"));
-                    return Err(tide_error);
-                }
+}
+macro_rules! opt_404 {
+    ($opt:expr) => {
+        match $opt {
+            Some(x) => x,
+            None => return Err(tide::Error::from_str(StatusCode::NotFound, "Invalid deck")),
+        }
+    };
+}
+macro_rules! result_404 {
+    ($result:expr, $msg:literal, $($arg:expr),* $(,)*) => {
+        match $result {
+            Ok(x) => x,
+            Err(e) => {
+                error!($msg, $($arg,)* e);
+                let e = anyhow::Error::from(e);
+                let tide_error = tide::Error::new(StatusCode::NotFound, e.context("Invalid deck"));
+                return Err(tide_error);
             }
-        };
-    }
+        }
+    };
+}
+
+async fn render_deck(state: &AppState, db_conn: &mut PgConnection, deck: &mut Deck) -> Result<()> {
+    let mut redis_conn = result_404!(
+        state.redis.get_async_connection().await,
+        "Failed to create Redis connection: {}",
+    );
+    let rendered_result = deck
+        .render(state.scryfall_api.clone(), &mut *db_conn, &mut redis_conn)
+        .await;
+    result_404!(rendered_result, "Failed to render deck {}: {}", deck.id);
+    Ok(())
+}
+
+/// Serves the rendered deck as `$UUID.json`, its thumbnail as `$UUID.png`,
+/// printable proxies as `$UUID.pdf`, or the decklist as `$UUID.txt`,
+/// `$UUID.csv` or `$UUID.arena.txt`.
+pub async fn download_deck(req: Request<AppState>) -> Result {
     let user = {
         let session_opt_future = req.get_session();
         let session_opt = session_opt_future.await;
         opt_404!(session_opt.and_then(|s| s.user))
     };
 
-    let deck_id: DeckId = {
+    let (deck_id, extension): (DeckId, &str) = {
         let param: &str = req.param("deck_id").unwrap();
+        // Deck IDs never contain a dot, but `arena.txt` does.
+        let dot_index = opt_404!(param.find('.'));
+        let (raw_id, extension) = (&param[..dot_index], &param[dot_index + 1..]);
         ensure_404!(
-            param.ends_with(".json") && param.len() > 5,
+            ["json", "png", "pdf", "txt", "csv", "arena.txt"].contains(&extension),
             "Invalid deck ID (expected something like {:?}), got {:?}",
             "$UUID.json",
             param,
         );
-        let raw_id = &param[..param.len() - 5];
-        result_404!(
+        let deck_id = result_404!(
             DeckId::from_str(raw_id),
-            "Failed to parse deck ID from {:?} in download_deck_json view: {}",
+            "Failed to parse deck ID from {:?} in download_deck view: {}",
             raw_id,
-        )
+        );
+        (deck_id, extension)
     };
     let state = req.state();
     let mut db_conn = state.db_pool.acquire().await?;
@@ -69,32 +98,176 @@ pub async fn download_deck_json(req: Request<AppState>) -> Result {
         user.id,
         deck.user_id,
     );
-    let deck_json = match deck.rendered_json {
-        Some(j) => j,
-        None => {
-            let mut redis_conn = result_404!(
-                state.redis.get_async_connection().await,
-                "Failed to create Redis connection: {}",
+
+    // Decks rendered before thumbnails existed need to be rendered again to
+    // get one.
+    let needs_render = match extension {
+        "json" => deck.rendered_json.is_none(),
+        "png" => deck.thumbnail.is_none(),
+        _ => false,
+    };
+    if needs_render {
+        render_deck(state, &mut *db_conn, &mut deck).await?;
+    }
+
+    let mut resp = Response::new(StatusCode::Ok);
+    let mut filename = format!("{}.{}", deck.title, extension);
+    match extension {
+        "png" => {
+            let thumbnail = opt_404!(deck.thumbnail.as_ref());
+            let file_opt = result_404!(
+                MediaFile::open_if_exists(thumbnail).await,
+                "Failed to open thumbnail {:?}: {}",
+                thumbnail,
+            );
+            let mut file = opt_404!(file_opt);
+            let mut image = vec![];
+            file.read_to_end(&mut image).await?;
+            let png_mime: Mime = "image/png".parse().unwrap();
+            resp.set_body(image);
+            resp.set_content_type(png_mime);
+        }
+        "pdf" => {
+            let options: ProxyOptions = result_404!(
+                serde_qs::from_str(req.url().query().unwrap_or("")),
+                "Invalid proxy options for deck {}: {}",
+                deck.id,
             );
-            let rendered_result = deck
-                .render(state.scryfall_api.clone(), &mut *db_conn, &mut redis_conn)
-                .await;
-            let rendered = result_404!(rendered_result, "Failed to render deck {}: {}", deck.id);
-            rendered.json_description
+            let pdf =
+                render_proxies(state.scryfall_api.clone(), &mut *db_conn, &deck, options).await?;
+            let pdf_mime: Mime = "application/pdf".parse().unwrap();
+            resp.set_body(pdf);
+            resp.set_content_type(pdf_mime);
+        }
+        "txt" | "csv" | "arena.txt" => {
+            let format = match extension {
+                "txt" => ExportFormat::PlainText,
+                "csv" => ExportFormat::Csv,
+                _ => ExportFormat::Arena,
+            };
+            let decklist = result_404!(
+                export_deck(&deck, format),
+                "Failed to export deck {}: {}",
+                deck.id,
+            );
+            let mime: Mime = format.content_type().parse().unwrap();
+            resp.set_body(decklist);
+            resp.set_content_type(mime);
+            filename = format.filename(&deck.title);
+        }
+        _ => {
+            let json_mime: Mime = "application/json".parse().unwrap();
+            let deck_json = opt_404!(deck.rendered_json.as_ref());
+            let rendered_json = serde_json::to_string_pretty(deck_json).unwrap();
+            resp.set_body(rendered_json);
+            resp.set_content_type(json_mime);
+        }
+    }
+    resp.insert_header(
+        HeaderName::from_bytes(b"Content-Disposition".to_vec()).unwrap(),
+        format!("attachment; filename=\"{}\"", filename.replace('"', "'")),
+    );
+    Ok(resp)
+}
+
+/// Serves the card list of a past revision as `$REVISION.txt`, `$REVISION.csv`
+/// or `$REVISION.arena.txt`. Only the current revision has a rendered deck.
+pub async fn download_deck_revision(req: Request<AppState>) -> Result {
+    let user = {
+        let session_opt_future = req.get_session();
+        let session_opt = session_opt_future.await;
+        opt_404!(session_opt.and_then(|s| s.user))
+    };
+
+    let deck_id: DeckId = {
+        let raw_id: &str = req.param("deck_id").unwrap();
+        result_404!(
+            DeckId::from_str(raw_id),
+            "Failed to parse deck ID from {:?} in download_deck_revision view: {}",
+            raw_id,
+        )
+    };
+    let (revision, format): (u32, ExportFormat) = {
+        let param: &str = req.param("revision").unwrap();
+        let dot_index = opt_404!(param.find('.'));
+        let (raw_revision, extension) = (&param[..dot_index], &param[dot_index + 1..]);
+        let format = match extension {
+            "txt" => ExportFormat::PlainText,
+            "csv" => ExportFormat::Csv,
+            "arena.txt" => ExportFormat::Arena,
+            _ => {
+                error!(
+                    "Invalid revision (expected something like {:?}), got {:?}",
+                    "$REVISION.txt", param,
+                );
+                return Err(tide::Error::from_str(StatusCode::NotFound, "Invalid deck"));
+            }
+        };
+        let revision = result_404!(
+            u32::from_str(raw_revision),
+            "Failed to parse revision from {:?} in download_deck_revision view: {}",
+            raw_revision,
+        );
+        (revision, format)
+    };
+    let state = req.state();
+    let mut db_conn = state.db_pool.acquire().await?;
+    let deck_opt = Deck::get_revision(&mut *db_conn, deck_id, revision).await?;
+    let deck = opt_404!(deck_opt);
+    ensure_404!(
+        deck.user_id == user.id,
+        "Attempted to access another user's deck (current user is {}, deck's owner is {})",
+        user.id,
+        deck.user_id,
+    );
+
+    let decklist = result_404!(
+        export_deck(&deck, format),
+        "Failed to export revision {} of deck {}: {}",
+        revision,
+        deck.id,
+    );
+    let mut resp = Response::new(StatusCode::Ok);
+    let mime: Mime = format.content_type().parse().unwrap();
+    resp.set_body(decklist);
+    resp.set_content_type(mime);
+    let filename = format.filename(&format!("{} (revision {})", deck.title, revision));
+    resp.insert_header(
+        HeaderName::from_bytes(b"Content-Disposition".to_vec()).unwrap(),
+        format!("attachment; filename=\"{}\"", filename.replace('"', "'")),
+    );
+    Ok(resp)
+}
+
+/// Serves the rendered deck to anyone with its share token, as `$TOKEN.json`.
+/// Only the owner's session renders decks, so until it has this is a 409.
+pub async fn download_shared_deck(req: Request<AppState>) -> Result {
+    let token: &str = {
+        let param: &str = req.param("token").unwrap();
+        opt_404!(param.strip_suffix(".json"))
+    };
+    let state = req.state();
+    let mut db_conn = state.db_pool.acquire().await?;
+    let deck_opt = Deck::get_by_share_token(&mut *db_conn, token).await?;
+    let deck = opt_404!(deck_opt);
+    let deck_json = match deck.rendered_json.as_ref() {
+        Some(json) => json,
+        None => {
+            return Err(tide::Error::from_str(
+                StatusCode::Conflict,
+                "This deck hasn't been rendered since it was last changed",
+            ))
         }
     };
 
-    let json_mime: Mime = "application/json".parse().unwrap();
-    let rendered_json = serde_json::to_string_pretty(&deck_json).unwrap();
     let mut resp = Response::new(StatusCode::Ok);
-    resp.set_body(rendered_json);
+    let json_mime: Mime = "application/json".parse().unwrap();
+    resp.set_body(serde_json::to_string_pretty(deck_json).unwrap());
     resp.set_content_type(json_mime);
+    let filename = format!("{}.json", deck.title);
     resp.insert_header(
         HeaderName::from_bytes(b"Content-Disposition".to_vec()).unwrap(),
-        format!(
-            "attachment; filename=\"{}.json\"",
-            deck.title.replace('"', "'")
-        ),
+        format!("attachment; filename=\"{}\"", filename.replace('"', "'")),
     );
     Ok(resp)
 }
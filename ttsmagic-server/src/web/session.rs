@@ -5,18 +5,23 @@ use ring::hmac;
 use sqlx::{PgConnection, Postgres};
 use std::str::FromStr;
 use tide::{
-    http::{headers::COOKIE, Cookie},
+    http::{
+        headers::{AUTHORIZATION, COOKIE},
+        Cookie,
+    },
     Next, Request, Response,
 };
 use ttsmagic_types::UserId;
 use uuid::Uuid;
 
-use crate::{secrets::session_private_key, user::User, web::AppState};
+use crate::{api_token, secrets::session_private_key, user::User, web::AppState};
 
 #[derive(Clone, Debug)]
 pub struct Session {
     pub session_id: Uuid,
     pub user: Option<User>,
+    /// Whether the request came with an API token instead of a session cookie.
+    pub from_api_token: bool,
 }
 
 pub const SESSION_EXPIRE_SECONDS: usize = 3 * 24 * 60 * 60;
@@ -39,6 +44,7 @@ impl Session {
         Ok(Session {
             session_id,
             user: Some(user),
+            from_api_token: false,
         })
     }
 
@@ -99,6 +105,7 @@ impl Session {
         let session = Session {
             session_id,
             user: None,
+            from_api_token: false,
         };
         let key = session.redis_key();
         match redis.get::<String, Option<String>>(key).await? {
@@ -148,6 +155,7 @@ fn test_cookie_sig_roundtrip() {
     let session = Session {
         session_id: Uuid::new_v4(),
         user: None,
+        from_api_token: false,
     };
     println!("session: {:?}", session);
     let cookie = session.make_cookie();
@@ -188,6 +196,16 @@ impl<'a> SessionGetExt<'a>
                 &http::HeaderMap,
             ),
         ) -> Result<Option<Session>> {
+            // A header that isn't even text can't hold an API token, so it's
+            // treated like a missing one.
+            let auth_str = headers
+                .get(AUTHORIZATION.as_str())
+                .and_then(|h| h.to_str().ok());
+            if let Some(auth_str) = auth_str {
+                if let Some(s) = from_authorization_header(&mut *db, auth_str).await? {
+                    return Ok(Some(s));
+                }
+            }
             let cookie_header = headers
                 .get("Cookie")
                 .ok_or_else(|| anyhow!("Cookie header is missing"))
@@ -258,6 +276,24 @@ impl<'a> SessionClearExt<'a> for &'a mut Response {
     }
 }
 
+/// API tokens get a session of their own for each request, which is never
+/// saved.
+pub async fn from_authorization_header(
+    db: &mut PgConnection,
+    header_value: &str,
+) -> Result<Option<Session>> {
+    let token = match api_token::parse_authorization(header_value) {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    let user_opt = api_token::user_for_token(db, token).await?;
+    Ok(user_opt.map(|user| Session {
+        session_id: Uuid::new_v4(),
+        user: Some(user),
+        from_api_token: true,
+    }))
+}
+
 pub async fn from_cookie_header(
     db: &mut PgConnection,
     redis: &mut redis::aio::Connection,
@@ -307,6 +343,7 @@ impl SessionMiddleware {
         let mut session = Session {
             session_id: Uuid::new_v4(),
             user: None,
+            from_api_token: false,
         };
         {
             let state = &req.state();
@@ -319,7 +356,19 @@ impl SessionMiddleware {
                 .cloned()
                 .unwrap_or_else(|| vec![].into_iter().collect());
             let mut cookie_session = None;
+            if let Some(auth_values) = req.header(&AUTHORIZATION) {
+                match from_authorization_header(&mut *db_conn, auth_values.last().as_str()).await {
+                    Ok(s) => cookie_session = s,
+                    Err(e) => {
+                        sentry::integrations::anyhow::capture_anyhow(&e);
+                        error!("Failed to check API token: {:?}", e);
+                    }
+                }
+            }
             for hv in cookie_header_values.iter() {
+                if cookie_session.is_some() {
+                    break;
+                }
                 match from_cookie_header(&mut *db_conn, &mut redis_conn, hv.as_str()).await {
                     Ok(s) => cookie_session = s,
                     Err(e) => {
@@ -18,12 +18,14 @@ use futures::{
 };
 use redis::AsyncCommands;
 use sqlx::Postgres;
+use std::str::FromStr;
 use ttsmagic_types::{frontend_to_server as f2s, server_to_frontend as s2f};
 
 use crate::{
-    deck::{get_decks_for_user, Deck},
+    api_token,
+    deck::{diff::diff_decks, get_decks_for_user, legality::validate_deck, Deck},
     notify,
-    scryfall::api::ScryfallApi,
+    scryfall::{self, api::ScryfallApi, search, ScryfallCard},
     user::User,
     utils::AsyncStdStreamWrapper,
     web::{session::SessionGetExt as _, AppState},
@@ -55,6 +57,7 @@ async fn handle_connection(
     ws_stream: WebSocketStream<TcpStream>,
     state: AppState,
     user: User,
+    from_api_token: bool,
 ) -> Result<()> {
     debug!("Got a websocket connection for {}", user);
 
@@ -156,6 +159,7 @@ async fn handle_connection(
                 spawn(async move {
                     let handle_result = handle_incoming_message(
                         user,
+                        from_api_token,
                         api,
                         db_conn,
                         redis_conn,
@@ -186,8 +190,13 @@ async fn handle_connection(
     Ok(())
 }
 
+/// API tokens can't be used to manage API tokens, so that a leaked token can't
+/// make new ones or keep itself around.
+const API_TOKEN_MANAGEMENT_ERROR: &str = "API tokens can only be managed after logging in";
+
 async fn handle_incoming_message(
     user: User,
+    from_api_token: bool,
     api: Arc<ScryfallApi>,
     mut db: sqlx::pool::PoolConnection<Postgres>,
     mut redis_conn: impl AsyncCommands + 'static,
@@ -195,6 +204,12 @@ async fn handle_incoming_message(
     msg: f2s::FrontendToServerMessage,
 ) -> Result<()> {
     match msg {
+        f2s::FrontendToServerMessage::CreateApiToken { name } => {
+            ensure!(!from_api_token, API_TOKEN_MANAGEMENT_ERROR);
+            let (token, secret) = api_token::create(&mut *db, user.id, &name).await?;
+            let msg = s2f::ServerToFrontendMessage::ApiTokenCreated { token, secret };
+            handle_sink.send(msg).await?;
+        }
         f2s::FrontendToServerMessage::DeleteDeck { id } => {
             let deck: Deck = Deck::get_by_id(&mut *db, id)
                 .await?
@@ -205,6 +220,67 @@ async fn handle_incoming_message(
             );
             deck.delete(&mut *db, &mut redis_conn).await?;
         }
+        f2s::FrontendToServerMessage::DiffDecks { old, new } => {
+            let mut decks = Vec::with_capacity(2);
+            for version in [old, new].iter() {
+                let deck: Deck = Deck::get_version(&mut *db, *version)
+                    .await?
+                    .ok_or_else(|| anyhow!("Invalid deck ID or revision"))?;
+                ensure!(
+                    deck.user_id == user.id,
+                    "Invalid deck ID (that doesn't belong to you)"
+                );
+                decks.push(deck);
+            }
+            let diff = diff_decks(old, &decks[0], new, &decks[1])?;
+            let msg = s2f::ServerToFrontendMessage::DeckDiff(diff);
+            handle_sink.send(msg).await?;
+        }
+        f2s::FrontendToServerMessage::GetApiTokens => {
+            ensure!(!from_api_token, API_TOKEN_MANAGEMENT_ERROR);
+            let tokens = api_token::list_for_user(&mut *db, user.id).await?;
+            let msg = s2f::ServerToFrontendMessage::ApiTokens { tokens };
+            handle_sink.send(msg).await?;
+        }
+        f2s::FrontendToServerMessage::GetDeck { id } => {
+            let deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            let detail = deck.detail(&mut *db).await?;
+            let msg = s2f::ServerToFrontendMessage::DeckDetail(detail);
+            handle_sink.send(msg).await?;
+        }
+        f2s::FrontendToServerMessage::GetPrintings { oracle_id } => {
+            let printings = scryfall::printings_by_oracle_id(&mut *db, oracle_id.into())
+                .await?
+                .iter()
+                .map(ScryfallCard::printing)
+                .collect::<Result<Vec<_>>>()?;
+            let msg = s2f::ServerToFrontendMessage::Printings {
+                oracle_id,
+                printings,
+            };
+            handle_sink.send(msg).await?;
+        }
+        f2s::FrontendToServerMessage::GetDeckRevisions { id } => {
+            let deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            let revisions = Deck::revisions(&mut *db, id).await?;
+            let msg = s2f::ServerToFrontendMessage::DeckRevisions {
+                deck_id: id,
+                revisions,
+            };
+            handle_sink.send(msg).await?;
+        }
         f2s::FrontendToServerMessage::GetDecks => {
             let decks = get_decks_for_user(&mut *db, user.id).await?;
             let msg = s2f::ServerToFrontendMessage::DeckList { decks };
@@ -221,6 +297,145 @@ async fn handle_incoming_message(
             })
             .await?;
         }
+        f2s::FrontendToServerMessage::CopySharedDeck { share_token } => {
+            let shared: Deck = Deck::get_by_share_token(&mut *db, &share_token)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid share link"))?;
+            spawn_blocking::<_, Result<()>>(move || {
+                block_on(async move {
+                    let mut deck = shared
+                        .copy_to_user(&mut *db, &mut redis_conn, user.id)
+                        .await?;
+                    deck.render(api, &mut *db, &mut redis_conn).await?;
+                    Ok(())
+                })
+            })
+            .await?;
+        }
+        f2s::FrontendToServerMessage::RenderSavedDeck { id } => {
+            let mut deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            spawn_blocking::<_, Result<()>>(move || {
+                block_on(async move {
+                    deck.render(api, &mut *db, &mut redis_conn).await?;
+                    Ok(())
+                })
+            })
+            .await?;
+        }
+        f2s::FrontendToServerMessage::EditDeck { id, edit } => {
+            let mut deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            deck.edit(&mut *db, &mut redis_conn, edit).await?;
+        }
+        f2s::FrontendToServerMessage::RestoreDeckRevision { id, revision } => {
+            let mut deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            spawn_blocking::<_, Result<()>>(move || {
+                block_on(async move {
+                    deck.restore_revision(&mut *db, &mut redis_conn, revision)
+                        .await?;
+                    deck.render(api, &mut *db, &mut redis_conn).await?;
+                    Ok(())
+                })
+            })
+            .await?;
+        }
+        f2s::FrontendToServerMessage::ValidateDeck { id, format } => {
+            let deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            let report = validate_deck(&deck, format)?;
+            let msg = s2f::ServerToFrontendMessage::LegalityReport {
+                deck_id: id,
+                report,
+            };
+            handle_sink.send(msg).await?;
+        }
+        f2s::FrontendToServerMessage::RevokeApiToken { id } => {
+            ensure!(!from_api_token, API_TOKEN_MANAGEMENT_ERROR);
+            api_token::revoke(&mut *db, user.id, id).await?;
+            let tokens = api_token::list_for_user(&mut *db, user.id).await?;
+            let msg = s2f::ServerToFrontendMessage::ApiTokens { tokens };
+            handle_sink.send(msg).await?;
+        }
+        f2s::FrontendToServerMessage::SearchCards { query } => {
+            let results = match search::Query::from_str(&query) {
+                Ok(parsed) => {
+                    let cards = search::search(&mut *db, &parsed, search::RESULT_LIMIT).await?;
+                    let cards = cards
+                        .iter()
+                        .map(ScryfallCard::summary)
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(cards)
+                }
+                Err(e) => Err(format!("{}", e)),
+            };
+            let msg = s2f::ServerToFrontendMessage::CardSearchResults { query, results };
+            handle_sink.send(msg).await?;
+        }
+        f2s::FrontendToServerMessage::SetCommanders { id, commanders } => {
+            let mut deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            let mut oracle_ids = Vec::with_capacity(commanders.len());
+            for name in commanders.iter() {
+                oracle_ids.push(scryfall::oracle_id_by_name(&mut *db, name).await?);
+            }
+            spawn_blocking::<_, Result<()>>(move || {
+                block_on(async move {
+                    deck.set_commanders(&mut *db, &mut redis_conn, oracle_ids)
+                        .await?;
+                    deck.render(api, &mut *db, &mut redis_conn).await?;
+                    Ok(())
+                })
+            })
+            .await?;
+        }
+        f2s::FrontendToServerMessage::SetDeckShared { id, shared } => {
+            let mut deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            deck.set_shared(&mut *db, &mut redis_conn, shared).await?;
+        }
+        f2s::FrontendToServerMessage::SetRenderOptions { id, options } => {
+            let mut deck: Deck = Deck::get_by_id(&mut *db, id)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid deck ID"))?;
+            ensure!(
+                deck.user_id == user.id,
+                "Invalid deck ID (that doesn't belong to you)"
+            );
+            deck.set_render_options(&mut *db, &mut redis_conn, options)
+                .await?;
+        }
     };
     Ok(())
 }
@@ -230,7 +445,8 @@ struct ServerCallback {
 }
 
 impl ServerCallback {
-    async fn get_user(&self, state: &AppState) -> Option<User> {
+    /// The user, and whether they authenticated with an API token.
+    async fn get_user(&self, state: &AppState) -> Option<(User, bool)> {
         let headers = match self.headers.as_ref() {
             Some(hs) => hs,
             None => {
@@ -259,7 +475,8 @@ impl ServerCallback {
         };
 
         let session_get_tuple = (&mut db_conn, &mut redis_conn, headers);
-        session_get_tuple.get_session().await.and_then(|s| s.user)
+        let session = session_get_tuple.get_session().await?;
+        Some((session.user?, session.from_api_token))
     }
 }
 
@@ -296,14 +513,14 @@ pub async fn listen((host, port): (IpAddr, u16), state: AppState) -> Result<()>
                     return;
                 }
             };
-            let user = match callback.get_user(&stream_state).await {
+            let (user, from_api_token) = match callback.get_user(&stream_state).await {
                 Some(u) => u,
                 None => {
                     warn!("Dropping a websocket connection without a valid user");
                     return;
                 }
             };
-            match handle_connection(ws_stream, stream_state, user).await {
+            match handle_connection(ws_stream, stream_state, user, from_api_token).await {
                 Ok(()) => (),
                 Err(e) => {
                     sentry::integrations::anyhow::capture_anyhow(&e);
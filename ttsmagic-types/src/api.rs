@@ -0,0 +1,41 @@
+//! Request and response bodies for the `/api/v1/` HTTP routes. Decks are
+//! returned as the same types the websocket uses.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::DeckId;
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum CreateDeck {
+    /// Loads the deck from a deck site, like pasting the URL on the home page.
+    Url { url: Url },
+    /// A pasted decklist, one `COUNT NAME` line per card, with optional
+    /// "Commander", "Companion", "Sideboard" and "Maybeboard" headings.
+    Text { title: String, decklist: String },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct RenderStatus {
+    pub deck_id: DeckId,
+    pub rendered: bool,
+    /// How the last render started through the API went, if there was one.
+    pub state: Option<RenderState>,
+    /// Where to download the rendered deck, once it's rendered.
+    pub json_url: Option<String>,
+    pub thumbnail_url: Option<Url>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum RenderState {
+    Pending,
+    Done,
+    Failed { message: String },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ApiError {
+    pub error: String,
+}
@@ -0,0 +1,69 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+use uuid::Uuid;
+
+/// Scryfall's images of a card face, from smallest to largest.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct CardImageUrls {
+    pub small: Url,
+    pub normal: Url,
+    pub large: Url,
+    pub png: Url,
+    pub art_crop: Url,
+    pub border_crop: Url,
+}
+
+/// One face of a card. Cards with a single face have exactly one.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct CardFace {
+    pub name: String,
+    pub mana_cost: String,
+    pub type_line: String,
+    pub oracle_text: String,
+    /// Missing for faces that share an image with the rest of the card, like
+    /// split cards.
+    pub image_urls: Option<CardImageUrls>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardLegality {
+    Legal,
+    NotLegal,
+    Restricted,
+    Banned,
+}
+
+/// What sets one printing of a card apart from the others, for picking one.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct CardPrinting {
+    pub scryfall_id: Uuid,
+    pub set_code: String,
+    pub set_name: String,
+    pub collector_number: String,
+    pub released_at: Option<NaiveDate>,
+    /// Scryfall's name for the card frame, like "1997" or "2015".
+    pub frame: String,
+    pub full_art: bool,
+    pub borderless: bool,
+    /// Scryfall's small image, for the front face of double-faced cards.
+    pub image_url: Option<Url>,
+}
+
+/// One printing of a card, from the copy of Scryfall's data we keep.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct Card {
+    pub scryfall_id: Uuid,
+    pub oracle_id: Option<Uuid>,
+    /// The full name, with faces separated by " // ".
+    pub name: String,
+    pub faces: Vec<CardFace>,
+    pub set_code: String,
+    pub collector_number: String,
+    /// Keyed by Scryfall's format names, like [`Format::slug`](crate::Format::slug).
+    pub legalities: BTreeMap<String, CardLegality>,
+    /// The images for the whole card, or for its front face.
+    pub image_urls: Option<CardImageUrls>,
+}
@@ -1,8 +1,10 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::{fmt, str::FromStr};
 use url::Url;
 use uuid::Uuid;
 
+use crate::RenderOptions;
 // use crate::UserId;
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
@@ -105,4 +107,142 @@ pub struct Deck {
     pub rendered: bool,
     #[serde(default)]
     pub color_identity: DeckColorIdentity,
+    #[serde(default)]
+    pub render_options: RenderOptions,
+    #[serde(default)]
+    pub thumbnail_url: Option<Url>,
+    /// Set while the deck is shared at `/shared/$TOKEN.json`.
+    #[serde(default)]
+    pub share_token: Option<String>,
+    /// Whether the card list was edited here since it was last loaded from
+    /// the deck site.
+    #[serde(default)]
+    pub modified: bool,
+}
+
+/// A snapshot of a deck's card list, taken each time it's loaded from the deck
+/// site. Revisions are numbered from 1 for each deck.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct DeckRevision {
+    pub deck_id: DeckId,
+    pub revision: u32,
+    pub loaded_at: DateTime<Utc>,
+    pub title: String,
+    pub url: Url,
+    pub card_count: u32,
+}
+
+/// One printing in a deck's card list.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct DeckCard {
+    pub scryfall_id: Uuid,
+    /// Shared by every printing of the card.
+    #[serde(default)]
+    pub oracle_id: Option<Uuid>,
+    pub name: String,
+    pub count: u8,
+    pub type_line: String,
+    pub set_code: String,
+    pub collector_number: String,
+    /// Scryfall's small card image, for the front face of double-faced cards.
+    pub image_url: Option<Url>,
+    /// The deck site's category, for main deck cards on sites that have them.
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+/// The piles a deck's cards are split into.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pile {
+    Commander,
+    Companion,
+    MainDeck,
+    Sideboard,
+    Maybeboard,
+}
+
+impl Pile {
+    pub const ALL: [Pile; 5] = [
+        Pile::Commander,
+        Pile::Companion,
+        Pile::MainDeck,
+        Pile::Sideboard,
+        Pile::Maybeboard,
+    ];
+
+    /// The pile's value in the database's `deck_pile` type, also used for form
+    /// values.
+    pub fn slug(self) -> &'static str {
+        match self {
+            Pile::Commander => "commander",
+            Pile::Companion => "companion",
+            Pile::MainDeck => "main_deck",
+            Pile::Sideboard => "sideboard",
+            Pile::Maybeboard => "maybeboard",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|pile| pile.slug() == slug)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Pile::Commander => "Commanders",
+            Pile::Companion => "Companion",
+            Pile::MainDeck => "Main deck",
+            Pile::Sideboard => "Sideboard",
+            Pile::Maybeboard => "Maybeboard",
+        }
+    }
+
+    /// Commanders and companions only ever have one copy of each card.
+    pub fn is_single(self) -> bool {
+        match self {
+            Pile::Commander | Pile::Companion => true,
+            Pile::MainDeck | Pile::Sideboard | Pile::Maybeboard => false,
+        }
+    }
+}
+
+/// A change to a deck's card list made by hand. Cards already in the deck are
+/// named by their Scryfall ID.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum DeckEdit {
+    /// Adds copies of the newest printing of the named card, on top of any
+    /// that are already in the pile.
+    AddCard {
+        name: String,
+        pile: Pile,
+        count: u8,
+    },
+    /// A count of 0 removes the card from the pile.
+    SetCount {
+        card: Uuid,
+        pile: Pile,
+        count: u8,
+    },
+    MoveCard {
+        card: Uuid,
+        from: Pile,
+        to: Pile,
+    },
+    /// Swaps the card for another printing of it.
+    SetPrinting {
+        card: Uuid,
+        pile: Pile,
+        printing: Uuid,
+    },
+}
+
+/// A deck with its full card list, sorted by name within each pile.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct DeckDetail {
+    pub deck: Deck,
+    pub commanders: Vec<DeckCard>,
+    pub companions: Vec<DeckCard>,
+    pub main_deck: Vec<DeckCard>,
+    pub sideboard: Vec<DeckCard>,
+    pub maybeboard: Vec<DeckCard>,
 }
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use crate::DeckId;
+
+/// A deck as it is now, or one of its past revisions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct DeckVersion {
+    pub deck_id: DeckId,
+    /// `None` for the deck's current card list.
+    pub revision: Option<u32>,
+}
+
+/// One card whose count differs between the two decks. Cards are compared by
+/// oracle ID, so switching to a different printing isn't a change.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct CardDiff {
+    pub name: String,
+    pub old_count: u8,
+    pub new_count: u8,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct PileDiff {
+    pub added: Vec<CardDiff>,
+    pub removed: Vec<CardDiff>,
+    pub changed: Vec<CardDiff>,
+}
+
+impl PileDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct DeckDiff {
+    pub old: DeckVersion,
+    pub new: DeckVersion,
+    pub commanders: PileDiff,
+    #[serde(default)]
+    pub companions: PileDiff,
+    pub main_deck: PileDiff,
+    pub sideboard: PileDiff,
+}
+
+impl DeckDiff {
+    pub fn is_empty(&self) -> bool {
+        self.commanders.is_empty()
+            && self.companions.is_empty()
+            && self.main_deck.is_empty()
+            && self.sideboard.is_empty()
+    }
+}
@@ -1,11 +1,83 @@
 use serde::{Deserialize, Serialize};
 use url::Url;
+use uuid::Uuid;
 
-use crate::DeckId;
+use crate::{DeckEdit, DeckId, DeckVersion, Format, RenderOptions};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum FrontendToServerMessage {
-    DeleteDeck { id: DeckId },
+    /// Copies someone else's shared deck into the current user's decks.
+    CopySharedDeck {
+        share_token: String,
+    },
+    CreateApiToken {
+        name: String,
+    },
+    DeleteDeck {
+        id: DeckId,
+    },
+    DiffDecks {
+        old: DeckVersion,
+        new: DeckVersion,
+    },
+    /// Changes the deck's card list by hand. The deck has to be rendered
+    /// again afterwards.
+    EditDeck {
+        id: DeckId,
+        edit: DeckEdit,
+    },
+    GetApiTokens,
+    /// Asks for the deck's full card list.
+    GetDeck {
+        id: DeckId,
+    },
+    GetDeckRevisions {
+        id: DeckId,
+    },
     GetDecks,
-    RenderDeck { url: Url },
+    /// Asks for every printing of a card, to pick one with
+    /// [`DeckEdit::SetPrinting`].
+    GetPrintings {
+        oracle_id: Uuid,
+    },
+    RenderDeck {
+        url: Url,
+    },
+    /// Renders the deck's current card list without loading it from its URL
+    /// again, so edits made here are kept.
+    RenderSavedDeck {
+        id: DeckId,
+    },
+    /// Makes an earlier revision the deck's current card list and renders it.
+    RestoreDeckRevision {
+        id: DeckId,
+        revision: u32,
+    },
+    RevokeApiToken {
+        id: Uuid,
+    },
+    /// Searches cards with a subset of Scryfall's search syntax.
+    SearchCards {
+        query: String,
+    },
+    /// Picks the deck's commanders by card name. An empty list lets the deck
+    /// site or the heuristic decide again.
+    SetCommanders {
+        id: DeckId,
+        commanders: Vec<String>,
+    },
+    /// Sharing gives the deck a token for a public download link, and
+    /// unsharing revokes it.
+    SetDeckShared {
+        id: DeckId,
+        shared: bool,
+    },
+    SetRenderOptions {
+        id: DeckId,
+        options: RenderOptions,
+    },
+    ValidateDeck {
+        id: DeckId,
+        format: Format,
+    },
 }
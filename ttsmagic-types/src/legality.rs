@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A format a deck can be checked against.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum Format {
+    Standard,
+    Pioneer,
+    Modern,
+    Legacy,
+    Vintage,
+    Commander,
+    Pauper,
+    Brawl,
+}
+
+impl Format {
+    pub const ALL: [Format; 8] = [
+        Format::Standard,
+        Format::Pioneer,
+        Format::Modern,
+        Format::Legacy,
+        Format::Vintage,
+        Format::Commander,
+        Format::Pauper,
+        Format::Brawl,
+    ];
+
+    /// The key for this format in Scryfall's `legalities` object, also used
+    /// for form values.
+    pub fn slug(self) -> &'static str {
+        match self {
+            Format::Standard => "standard",
+            Format::Pioneer => "pioneer",
+            Format::Modern => "modern",
+            Format::Legacy => "legacy",
+            Format::Vintage => "vintage",
+            Format::Commander => "commander",
+            Format::Pauper => "pauper",
+            Format::Brawl => "brawl",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.slug() == slug)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Standard => "Standard",
+            Format::Pioneer => "Pioneer",
+            Format::Modern => "Modern",
+            Format::Legacy => "Legacy",
+            Format::Vintage => "Vintage",
+            Format::Commander => "Commander",
+            Format::Pauper => "Pauper",
+            Format::Brawl => "Brawl",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum LegalityProblem {
+    Banned {
+        card: String,
+    },
+    NotLegal {
+        card: String,
+    },
+    /// A restricted card with more than one copy.
+    Restricted {
+        card: String,
+        copies: u32,
+    },
+    TooManyCopies {
+        card: String,
+        copies: u32,
+        limit: u32,
+    },
+    DeckTooSmall {
+        cards: u32,
+        minimum: u32,
+    },
+    DeckTooLarge {
+        cards: u32,
+        maximum: u32,
+    },
+    SideboardTooLarge {
+        cards: u32,
+        maximum: u32,
+    },
+    MissingCommander,
+    TooManyCommanders {
+        commanders: u32,
+    },
+    InvalidCommander {
+        card: String,
+    },
+    /// Two commanders that can't be paired with each other.
+    InvalidPartners {
+        first: String,
+        second: String,
+    },
+    OutsideColorIdentity {
+        card: String,
+    },
+}
+
+impl fmt::Display for LegalityProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LegalityProblem::Banned { card } => write!(f, "{} is banned", card),
+            LegalityProblem::NotLegal { card } => write!(f, "{} isn't legal", card),
+            LegalityProblem::Restricted { card, copies } => write!(
+                f,
+                "{} is restricted, but the deck has {} copies",
+                card, copies
+            ),
+            LegalityProblem::TooManyCopies {
+                card,
+                copies,
+                limit,
+            } => write!(f, "{} copies of {} (the limit is {})", copies, card, limit),
+            LegalityProblem::DeckTooSmall { cards, minimum } => write!(
+                f,
+                "The deck has {} cards, but needs at least {}",
+                cards, minimum
+            ),
+            LegalityProblem::DeckTooLarge { cards, maximum } => write!(
+                f,
+                "The deck has {} cards, but can have at most {}",
+                cards, maximum
+            ),
+            LegalityProblem::SideboardTooLarge { cards, maximum } => write!(
+                f,
+                "The sideboard has {} cards, but can have at most {}",
+                cards, maximum
+            ),
+            LegalityProblem::MissingCommander => write!(f, "The deck has no commander"),
+            LegalityProblem::TooManyCommanders { commanders } => {
+                write!(f, "The deck has {} commanders", commanders)
+            }
+            LegalityProblem::InvalidCommander { card } => {
+                write!(f, "{} can't be a commander", card)
+            }
+            LegalityProblem::InvalidPartners { first, second } => {
+                write!(f, "{} and {} can't be commanders together", first, second)
+            }
+            LegalityProblem::OutsideColorIdentity { card } => {
+                write!(f, "{} is outside the commander's color identity", card)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct LegalityReport {
+    pub format: Format,
+    pub problems: Vec<LegalityProblem>,
+}
+
+impl LegalityReport {
+    pub fn is_legal(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
@@ -1,10 +1,23 @@
 #![deny(missing_debug_implementations)]
 #![deny(warnings)]
 
+pub mod api;
+mod api_token;
+mod card;
 mod deck;
+mod deck_diff;
 pub mod frontend_to_server;
+mod legality;
+mod render_options;
 pub mod server_to_frontend;
 mod user;
 
-pub use deck::{Deck, DeckColorIdentity, DeckId};
+pub use api_token::ApiToken;
+pub use card::{Card, CardFace, CardImageUrls, CardLegality, CardPrinting};
+pub use deck::{
+    Deck, DeckCard, DeckColorIdentity, DeckDetail, DeckEdit, DeckId, DeckRevision, Pile,
+};
+pub use deck_diff::{CardDiff, DeckDiff, DeckVersion, PileDiff};
+pub use legality::{Format, LegalityProblem, LegalityReport};
+pub use render_options::{DeckScripts, ExportMode, LayoutPreset, RenderOptions, TokenCopies};
 pub use user::{User, UserId};
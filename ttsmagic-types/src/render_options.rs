@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU8;
+
+/// How many copies of each token to put in the token pile.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum TokenCopies {
+    Single,
+    Fixed(NonZeroU8),
+    /// One copy for every card in the deck that creates the token.
+    Estimated,
+}
+
+impl Default for TokenCopies {
+    fn default() -> Self {
+        TokenCopies::Single
+    }
+}
+
+/// Where the piles of a rendered deck are placed on the table.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum LayoutPreset {
+    /// Every pile side by side in a single row.
+    Row,
+    /// Library and commander in front of the player, everything else off to
+    /// the side and face down.
+    OneVsOneMat,
+    /// One seat at a Commander table, with the token and extra piles locked
+    /// in place.
+    CommanderPod,
+    /// All piles packed closely together in two rows.
+    Compact,
+}
+
+impl LayoutPreset {
+    pub const ALL: [LayoutPreset; 4] = [
+        LayoutPreset::Row,
+        LayoutPreset::OneVsOneMat,
+        LayoutPreset::CommanderPod,
+        LayoutPreset::Compact,
+    ];
+
+    /// Short identifier for command line flags and form values.
+    pub fn slug(self) -> &'static str {
+        match self {
+            LayoutPreset::Row => "row",
+            LayoutPreset::OneVsOneMat => "1v1-mat",
+            LayoutPreset::CommanderPod => "commander-pod",
+            LayoutPreset::Compact => "compact",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|preset| preset.slug() == slug)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LayoutPreset::Row => "Row",
+            LayoutPreset::OneVsOneMat => "1v1 mat",
+            LayoutPreset::CommanderPod => "Commander pod seat",
+            LayoutPreset::Compact => "Compact",
+        }
+    }
+}
+
+impl Default for LayoutPreset {
+    fn default() -> Self {
+        LayoutPreset::Row
+    }
+}
+
+/// The shape of the downloaded JSON file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub enum ExportMode {
+    /// Each pile is a separate object, to be spawned one at a time.
+    LooseObjects,
+    /// A complete saved object with every pile inside a single bag, ready to
+    /// drop into the Tabletop Simulator "Saved Objects" folder.
+    SavedObject,
+}
+
+impl ExportMode {
+    pub const ALL: [ExportMode; 2] = [ExportMode::LooseObjects, ExportMode::SavedObject];
+
+    /// Short identifier for command line flags and form values.
+    pub fn slug(self) -> &'static str {
+        match self {
+            ExportMode::LooseObjects => "loose",
+            ExportMode::SavedObject => "saved-object",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|mode| mode.slug() == slug)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportMode::LooseObjects => "Loose piles",
+            ExportMode::SavedObject => "Saved object (bag)",
+        }
+    }
+}
+
+impl Default for ExportMode {
+    fn default() -> Self {
+        ExportMode::LooseObjects
+    }
+}
+
+/// Optional Lua helpers attached to the rendered piles.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct DeckScripts {
+    /// "Opening hand" and "Mulligan" buttons on the library.
+    #[serde(default)]
+    pub mulligan_buttons: bool,
+    /// A life counter on the commander pile.
+    #[serde(default)]
+    pub life_counter: bool,
+    /// Context menu entries on the token pile to spawn a token by name.
+    #[serde(default)]
+    pub token_spawner: bool,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct RenderOptions {
+    #[serde(default)]
+    pub token_copies: TokenCopies,
+    #[serde(default)]
+    pub layout: LayoutPreset,
+    #[serde(default)]
+    pub export_mode: ExportMode,
+    #[serde(default)]
+    pub scripts: DeckScripts,
+    /// Add the maybeboard as a face-up pile, for playtesting card choices.
+    #[serde(default)]
+    pub include_maybeboard: bool,
+    /// Split the main deck into a labelled pile for each of the deck site's
+    /// categories, like Archidekt's "Ramp" or "Removal".
+    #[serde(default)]
+    pub split_categories: bool,
+}
@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::num::NonZeroU16;
 use url::Url;
+use uuid::Uuid;
 
-use crate::{Deck, DeckColorIdentity, DeckId};
+use crate::{
+    ApiToken, Card, CardPrinting, Deck, DeckColorIdentity, DeckDetail, DeckDiff, DeckId,
+    DeckRevision, LegalityReport, RenderOptions,
+};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Error {
@@ -28,9 +32,18 @@ pub enum RenderProgress {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Notification {
+    DeckCommandersChanged {
+        deck_id: DeckId,
+        commanders: Vec<String>,
+    },
     DeckDeleted {
         deck_id: DeckId,
     },
+    /// The card list was edited by hand, so the deck isn't rendered anymore.
+    DeckEdited {
+        deck_id: DeckId,
+        color_identity: DeckColorIdentity,
+    },
     DeckParseStarted {
         deck_id: DeckId,
         title: String,
@@ -42,6 +55,18 @@ pub enum Notification {
         url: Url,
         color_identity: DeckColorIdentity,
     },
+    DeckThumbnail {
+        deck_id: DeckId,
+        thumbnail_url: Url,
+    },
+    DeckShareChanged {
+        deck_id: DeckId,
+        share_token: Option<String>,
+    },
+    DeckRenderOptionsChanged {
+        deck_id: DeckId,
+        options: RenderOptions,
+    },
     Error(Error),
     RenderProgress {
         deck_id: DeckId,
@@ -51,7 +76,37 @@ pub enum Notification {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum ServerToFrontendMessage {
-    DeckList { decks: Vec<Deck> },
+    /// Sent once, right after the token is created, with its secret.
+    ApiTokenCreated {
+        token: ApiToken,
+        secret: String,
+    },
+    ApiTokens {
+        tokens: Vec<ApiToken>,
+    },
+    /// A query that can't be parsed comes back as an error message.
+    CardSearchResults {
+        query: String,
+        results: Result<Vec<Card>, String>,
+    },
+    DeckDetail(DeckDetail),
+    DeckDiff(DeckDiff),
+    DeckList {
+        decks: Vec<Deck>,
+    },
+    DeckRevisions {
+        deck_id: DeckId,
+        revisions: Vec<DeckRevision>,
+    },
     FatalError(Error),
+    LegalityReport {
+        deck_id: DeckId,
+        report: LegalityReport,
+    },
     Notification(Notification),
+    /// Newest first.
+    Printings {
+        oracle_id: Uuid,
+        printings: Vec<CardPrinting>,
+    },
 }